@@ -0,0 +1,177 @@
+//! Implementation of an addressable FIFO queue where entries can additionally be addressed by an
+//! alias, that is a second key of a different type.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::Deque;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// An addressable FIFO queue with an optional alias per entry.
+///
+/// Every alias refers to at most one entry, so entries can be removed in O(1) by either their key
+/// or their alias.
+pub struct Queue<K, A, V> {
+    inner: Deque<K, (Option<A>, V)>,
+    aliases: HashMap<A, K>,
+}
+
+impl<K, A, V> Queue<K, A, V>
+where
+    K: Clone + Eq + Hash,
+    A: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            inner: Deque::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns true if the queue contains an element for the specified alias.
+    pub fn contains_alias(&self, alias: &A) -> bool {
+        self.aliases.contains_key(alias)
+    }
+
+    /// Returns the key of the entry with the specified alias.
+    pub fn key_of(&self, alias: &A) -> Option<&K> {
+        self.aliases.get(alias)
+    }
+
+    /// Returns the alias of the entry with the specified key, if it has one.
+    pub fn alias_of(&self, key: &K) -> Option<&A> {
+        self.inner.get(key).and_then(|(alias, _)| alias.as_ref())
+    }
+
+    /// Insert an entry without an alias at the end of the queue.
+    ///
+    /// An existing entry for the key is removed, including its alias.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.remove_key(&key);
+        self.inner.push_back(key, (None, value));
+    }
+
+    /// Insert an entry with an alias at the end of the queue.
+    ///
+    /// An existing entry for the key is removed, including its alias. If another entry had the
+    /// same alias, that entry loses its alias but stays in the queue.
+    ///
+    /// ```
+    /// use addressable_queue::alias::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_with_alias(1u32, "conn-a", 'x');
+    /// queue.insert_with_alias(2u32, "conn-b", 'y');
+    ///
+    /// assert_eq!(Some(&2), queue.key_of(&"conn-b"));
+    /// assert_eq!(Some((2, 'y')), queue.remove_by_alias(&"conn-b"));
+    /// assert_eq!(Some('x'), queue.remove_key(&1));
+    /// assert!(!queue.contains_alias(&"conn-a"));
+    /// ```
+    pub fn insert_with_alias(&mut self, key: K, alias: A, value: V) {
+        self.remove_key(&key);
+        if let Some(other) = self.aliases.insert(alias.clone(), key.clone()) {
+            if let Some((old, _)) = self.inner.get_mut(&other) {
+                *old = None;
+            }
+        }
+        self.inner.push_back(key, (Some(alias), value));
+    }
+
+    /// Remove the current head of the queue, and return it if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        let (key, (alias, value)) = self.inner.pop_front()?;
+        self.forget(alias);
+        Some((key, value))
+    }
+
+    /// Remove the current tail of the queue, and return it if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        let (key, (alias, value)) = self.inner.pop_back()?;
+        self.forget(alias);
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let (alias, value) = self.inner.remove_key(key)?;
+        self.forget(alias);
+        Some(value)
+    }
+
+    /// Remove an entry by specifying its alias, and return its key and value.
+    pub fn remove_by_alias(&mut self, alias: &A) -> Option<(K, V)> {
+        let key = self.aliases.remove(alias)?;
+        let (_, value) = self.inner.remove_key(&key)?;
+        Some((key, value))
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    ///
+    /// ```
+    /// use addressable_queue::alias::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_with_alias(1u8, 'a', 2u8);
+    /// queue.insert(3u8, 4u8);
+    /// queue.insert_with_alias(5u8, 'a', 6u8);
+    ///
+    /// assert_eq!(None, queue.alias_of(&1));
+    /// assert_eq!(queue.into_vec(), vec![(1, 2), (3, 4), (5, 6)]);
+    /// ```
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner
+            .into_vec()
+            .into_iter()
+            .map(|(key, (_, value))| (key, value))
+            .collect()
+    }
+
+    fn forget(&mut self, alias: Option<A>) {
+        if let Some(alias) = alias {
+            self.aliases.remove(&alias);
+        }
+    }
+}
+
+impl<K, A, V> Default for Queue<K, A, V>
+where
+    K: Clone + Eq + Hash,
+    A: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
@@ -0,0 +1,111 @@
+//! Serialization of queues as maps from key to value, for use with `#[serde(with)]`.
+//!
+//! Queues are serialized as a sequence of `(key, value)` pairs by default. With these functions
+//! they are serialized as a map instead, which formats like JSON show as an object keyed by the
+//! keys of the queue. The entries are serialized from head to tail, and inserted in the order
+//! they are read when deserializing, so the order of the queue is preserved by formats which
+//! keep the order of map entries.
+//!
+//! Any queue which can be iterated by reference and extended with pairs works, for example a
+//! [`Deque`](../deque/struct.Deque.html), [`fifo::Queue`](../fifo/struct.Queue.html) or
+//! [`lru::Queue`](../lru/struct.Queue.html).
+//!
+//! ```
+//! extern crate addressable_queue;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate serde_json;
+//!
+//! use addressable_queue::fifo::Queue;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct State {
+//!     #[serde(with = "addressable_queue::as_map")]
+//!     jobs: Queue<String, u32>,
+//! }
+//!
+//! # fn main() {
+//! let mut jobs = Queue::new();
+//! jobs.insert("build".to_string(), 2);
+//! jobs.insert("test".to_string(), 1);
+//!
+//! let json = serde_json::to_string(&State { jobs }).unwrap();
+//! assert_eq!(r#"{"jobs":{"build":2,"test":1}}"#, json);
+//!
+//! let mut state: State = serde_json::from_str(&json).unwrap();
+//! assert_eq!(Some(("build".to_string(), 2)), state.jobs.remove_head());
+//! # }
+//! ```
+//!
+//! This module requires the `serde` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt;
+use core::marker::PhantomData;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serialize the entries of the queue as a map, from head to tail.
+pub fn serialize<'a, T, K, V, S>(queue: &'a T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    &'a T: IntoIterator<Item = (&'a K, &'a V)>,
+    K: Serialize + 'a,
+    V: Serialize + 'a,
+    S: Serializer,
+{
+    serializer.collect_map(queue)
+}
+
+/// Deserialize a map into a queue, inserting the entries in the order they are read.
+pub fn deserialize<'de, T, K, V, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Default + Extend<(K, V)>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_map(MapVisitor {
+        _marker: PhantomData,
+    })
+}
+
+struct MapVisitor<T, K, V> {
+    _marker: PhantomData<(T, K, V)>,
+}
+
+impl<'de, T, K, V> Visitor<'de> for MapVisitor<T, K, V>
+where
+    T: Default + Extend<(K, V)>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<T, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut queue = T::default();
+        while let Some(entry) = map.next_entry()? {
+            queue.extend(Some(entry));
+        }
+        Ok(queue)
+    }
+}
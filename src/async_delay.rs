@@ -0,0 +1,212 @@
+//! Implementation of an addressable delay queue, where waiting for the next entry to become
+//! available can be awaited on the timer of tokio.
+//!
+//! This module requires the `tokio` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use delay;
+use futures_core::Stream;
+use std::boxed::Box;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::time::{self, Sleep};
+
+/// An addressable queue, where every entry is hidden until the instant it becomes available,
+/// and waiting for it sleeps on the timer of tokio.
+///
+/// Pending entries can be cancelled by removing them. The queue reads the current time from
+/// tokio, so pausing and advancing the time of a tokio runtime in tests applies to it as well.
+/// It is also a [`Stream`] of the entries in the order they become available.
+///
+/// [`Stream`]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+///
+/// ```edition2018
+/// use addressable_queue::async_delay::Queue;
+/// use std::time::Duration;
+///
+/// #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// async fn main() {
+///     let mut queue = Queue::new();
+///     queue.insert("retry", (), Duration::from_secs(2));
+///     queue.insert("ping", (), Duration::from_secs(1));
+///     queue.insert("cancelled", (), Duration::from_secs(1));
+///     queue.remove_key(&"cancelled");
+///
+///     assert_eq!(Some(("ping", ())), queue.pop().await);
+///     assert_eq!(Some(("retry", ())), queue.pop().await);
+///     assert_eq!(None, queue.pop().await);
+/// }
+/// ```
+pub struct Queue<K, V> {
+    inner: delay::Queue<K, V>,
+    /// The timer for the earliest instant, created on first use so that creating the queue does
+    /// not require a runtime.
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            inner: delay::Queue::new(),
+            sleep: None,
+        }
+    }
+
+    /// Returns the number of elements in the queue, including the ones not yet available.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)
+    }
+
+    /// Returns the instant the entry with the specified key becomes available.
+    pub fn available_at(&self, key: &K) -> Option<Instant> {
+        self.inner.available_at(key)
+    }
+
+    /// Insert an entry which becomes available after `delay` from the current time of tokio,
+    /// and return the old value of the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime with a timer.
+    pub fn insert(&mut self, key: K, value: V, delay: Duration) -> Option<V> {
+        self.insert_at(key, value, time::Instant::now().into_std() + delay)
+    }
+
+    /// Insert an entry which becomes available at `at`, and return the old value of the key.
+    pub fn insert_at(&mut self, key: K, value: V, at: Instant) -> Option<V> {
+        self.inner.insert_at(key, value, at)
+    }
+
+    /// Change the instant an entry becomes available, and return the old one if the key was
+    /// contained.
+    pub fn reset_at(&mut self, key: &K, at: Instant) -> Option<Instant> {
+        self.inner.reset_at(key, at)
+    }
+
+    /// Remove a value by specifying its key, which cancels it if it is not yet available.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Remove the next entry once it is available, or return `None` if the queue is empty.
+    ///
+    /// Waiting is cancelled by dropping the future, which keeps the entries in the queue.
+    pub fn pop(&mut self) -> Pop<'_, K, V> {
+        Pop { queue: self }
+    }
+
+    /// Remove an available entry, or register the task of `cx` to be woken when the earliest
+    /// entry becomes available.
+    ///
+    /// Returns `Poll::Ready(None)` if the queue is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime with a timer.
+    pub fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<Option<(K, V)>> {
+        loop {
+            let now = time::Instant::now();
+            if let Some(entry) = self.inner.poll_expired(now.into_std()).next() {
+                return Poll::Ready(Some(entry));
+            }
+            let at = match self.inner.next_available() {
+                Some(at) => time::Instant::from_std(at),
+                None => return Poll::Ready(None),
+            };
+            let sleep = match self.sleep {
+                Some(ref mut sleep) => {
+                    if sleep.deadline() != at {
+                        sleep.as_mut().reset(at);
+                    }
+                    sleep
+                }
+                None => self
+                    .sleep
+                    .get_or_insert_with(|| Box::pin(time::sleep_until(at))),
+            };
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+// The entries are never pinned.
+impl<K, V> Unpin for Queue<K, V> {}
+
+impl<K, V> Stream for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Item = (K, V);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<(K, V)>> {
+        self.get_mut().poll_expired(cx)
+    }
+}
+
+/// Future removing the next available entry of a [`Queue`](struct.Queue.html), created by
+/// [`Queue::pop`](struct.Queue.html#method.pop).
+pub struct Pop<'a, K: 'a, V: 'a> {
+    queue: &'a mut Queue<K, V>,
+}
+
+impl<'a, K, V> Future for Pop<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Output = Option<(K, V)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.queue.poll_expired(cx)
+    }
+}
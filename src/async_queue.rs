@@ -0,0 +1,677 @@
+//! Implementation of an addressable FIFO queue which can be shared between tasks, where removing
+//! entries can be awaited.
+//!
+//! This module requires the `async` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use deque::Deque;
+use fifo::BoundedQueue;
+use futures_core::Stream;
+use futures_sink::Sink;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::vec::Vec;
+
+pub use fifo::Full;
+pub use sync::Closed;
+
+/// A handle to an addressable FIFO queue shared between tasks.
+///
+/// Cloning the handle does not clone the queue, all clones refer to the same entries. Tasks can
+/// await entries with [`pop`](#method.pop), which is woken by inserting. The queue does not depend
+/// on a particular runtime.
+///
+/// Closing the queue wakes all waiting tasks. They receive the remaining entries and then
+/// [`Closed`](struct.Closed.html), so tasks can terminate once the queue is drained.
+///
+/// Every handle is also a [`Stream`] of the entries, which ends once the queue is closed and
+/// drained, and a [`Sink`] of entries, which waits for room while the queue is full.
+///
+/// [`Stream`]: https://docs.rs/futures-core/0.3/futures_core/stream/trait.Stream.html
+/// [`Sink`]: https://docs.rs/futures-sink/0.3/futures_sink/trait.Sink.html
+///
+/// ```edition2018
+/// use addressable_queue::async_queue::Queue;
+/// use futures::executor::block_on;
+///
+/// let queue = Queue::new();
+/// queue.insert("ping", 1).unwrap();
+/// queue.insert("cancelled", 2).unwrap();
+/// queue.remove_key(&"cancelled");
+/// queue.close();
+///
+/// block_on(async {
+///     assert_eq!(Ok(("ping", 1)), queue.pop().await);
+///     assert!(queue.pop().await.is_err());
+/// });
+/// ```
+///
+/// Consuming the queue as a stream:
+///
+/// ```edition2018
+/// use addressable_queue::async_queue::Queue;
+/// use futures::executor::block_on;
+/// use futures::StreamExt;
+///
+/// let queue = Queue::new();
+/// for key in 0..4 {
+///     queue.insert(key, ()).unwrap();
+/// }
+/// queue.close();
+///
+/// let keys: Vec<_> = block_on(queue.map(|(key, _)| key).collect());
+/// assert_eq!(keys, vec![0, 1, 2, 3]);
+/// ```
+///
+/// Feeding a bounded queue as a sink:
+///
+/// ```edition2018
+/// use addressable_queue::async_queue::Queue;
+/// use futures::executor::block_on;
+/// use futures::{future, stream, SinkExt, StreamExt};
+///
+/// # fn main() {
+/// let queue = Queue::bounded(2);
+/// let mut sink = queue.clone();
+/// let producer = async move {
+///     let mut entries = stream::iter((0..5).map(|key| Ok((key, ()))));
+///     sink.send_all(&mut entries).await.unwrap();
+///     SinkExt::close(&mut sink).await.unwrap();
+/// };
+/// let consumer = queue.map(|(key, _)| key).collect::<Vec<_>>();
+///
+/// let (_, keys) = block_on(future::join(producer, consumer));
+/// assert_eq!(keys, vec![0, 1, 2, 3, 4]);
+/// # }
+/// ```
+pub struct Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    shared: Arc<Mutex<State<K, V>>>,
+    /// The id under which the task polling the handle as a stream waits.
+    consumer: Option<u64>,
+    /// The id under which the task polling the handle as a sink waits.
+    producer: Option<u64>,
+    /// The entry sent into the handle as a sink, which did not fit into the queue yet.
+    buffered: Option<(K, V)>,
+}
+
+struct State<K, V> {
+    queue: BoundedQueue<K, V>,
+    /// The tasks waiting for an entry.
+    consumers: Waiters,
+    /// The tasks waiting for room.
+    producers: Waiters,
+    /// The tasks waiting for the entries of keys to be removed.
+    watches: HashMap<K, Vec<Arc<Mutex<Watch>>>>,
+    closed: bool,
+}
+
+impl<K, V> State<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Resolve the watches of a key whose entry was removed.
+    fn removed(&mut self, key: &K, removed: Removed) {
+        if self.watches.is_empty() {
+            return;
+        }
+        for watch in self.watches.remove(key).into_iter().flatten() {
+            let mut watch = watch
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            watch.removed = Some(removed);
+            if let Some(waker) = watch.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Watch {
+    removed: Option<Removed>,
+    waker: Option<Waker>,
+}
+
+/// How the entry awaited by [`WaitRemoved`](struct.WaitRemoved.html) was removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Removed {
+    /// The entry was removed as the head of the queue.
+    Popped,
+    /// The entry was removed by its key.
+    Cancelled,
+}
+
+/// The wakers of the tasks waiting for something, in the order they started waiting.
+struct Waiters {
+    wakers: Deque<u64, Waker>,
+    next_id: u64,
+}
+
+impl Waiters {
+    fn new() -> Self {
+        Waiters {
+            wakers: Deque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Register the waker to be woken, under the id in `id` or a new one which is stored there.
+    fn register(&mut self, id: &mut Option<u64>, waker: &Waker) {
+        let id = match *id {
+            Some(id) => id,
+            None => {
+                self.next_id += 1;
+                *id = Some(self.next_id);
+                self.next_id
+            }
+        };
+        if let Some(registered) = self.wakers.get_mut(&id) {
+            registered.clone_from(waker);
+        } else {
+            self.wakers.push_back(id, waker.clone());
+        }
+    }
+
+    /// Forget the waker registered under `id`, and return whether it was not yet woken.
+    fn remove(&mut self, id: u64) -> bool {
+        self.wakers.remove_key(&id).is_some()
+    }
+
+    /// Wake the task which has been waiting the longest.
+    fn wake_one(&mut self) {
+        if let Some((_, waker)) = self.wakers.pop_front() {
+            waker.wake();
+        }
+    }
+
+    fn wake_all(&mut self) {
+        while let Some((_, waker)) = self.wakers.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue without a capacity.
+    pub fn new() -> Self {
+        Queue::bounded(usize::MAX)
+    }
+
+    /// Create a new instance of a queue which holds at most `capacity` elements.
+    pub fn bounded(capacity: usize) -> Self {
+        Queue {
+            shared: Arc::new(Mutex::new(State {
+                queue: BoundedQueue::new(capacity),
+                consumers: Waiters::new(),
+                producers: Waiters::new(),
+                watches: HashMap::new(),
+                closed: false,
+            })),
+            consumer: None,
+            producer: None,
+            buffered: None,
+        }
+    }
+
+    /// Returns the maximum number of elements in the queue.
+    pub fn capacity(&self) -> usize {
+        self.lock().queue.bound()
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.lock().queue.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.lock().queue.contains_key(key)
+    }
+
+    /// Insert an entry at the end of the queue, and wake a task waiting for an entry.
+    ///
+    /// If the queue is full the pair is handed back in the error. Replacing the value of a key
+    /// in the queue always succeeds.
+    pub fn insert(&self, key: K, value: V) -> Result<(), Full<K, V>> {
+        let mut state = self.lock();
+        state.queue.try_insert(key, value)?;
+        state.consumers.wake_one();
+        Ok(())
+    }
+
+    /// Insert an entry at the end of the queue, waiting until there is room if the queue is
+    /// full.
+    ///
+    /// If the queue is closed while it is full, waiting ends and the pair is handed back in the
+    /// error. Dropping the future stops waiting, and drops the entry.
+    pub fn push(&self, key: K, value: V) -> Push<'_, K, V> {
+        Push {
+            queue: self,
+            entry: Some((key, value)),
+            waiter: None,
+        }
+    }
+
+    /// Remove the current head of the queue, waiting until there is one if the queue is empty.
+    ///
+    /// Once the queue is closed, the remaining entries are still returned, and then the error.
+    /// Dropping the future stops waiting without losing an entry.
+    pub fn pop(&self) -> Pop<'_, K, V> {
+        Pop {
+            queue: self,
+            waiter: None,
+        }
+    }
+
+    /// Remove the current head of the queue without waiting, and return the value if there was
+    /// one.
+    pub fn remove_head(&self) -> Option<(K, V)> {
+        let mut state = self.lock();
+        let entry = state.queue.remove_head()?;
+        state.producers.wake_one();
+        state.removed(&entry.0, Removed::Popped);
+        Some(entry)
+    }
+
+    /// Remove a value by specifying its key, which cancels the entry.
+    pub fn remove_key(&self, key: &K) -> Option<V> {
+        let mut state = self.lock();
+        let value = state.queue.remove_key(key)?;
+        state.producers.wake_one();
+        state.removed(key, Removed::Cancelled);
+        Some(value)
+    }
+
+    /// Returns a future which resolves once the entry of the key is removed, or `None` if the
+    /// queue contains no entry for the key.
+    ///
+    /// Replacing the value of the key does not resolve the future.
+    ///
+    /// ```edition2018
+    /// use addressable_queue::async_queue::{Queue, Removed};
+    /// use futures::executor::block_on;
+    ///
+    /// let queue = Queue::new();
+    /// queue.insert("request", ()).unwrap();
+    /// let removed = queue.wait_removed(&"request").unwrap();
+    ///
+    /// queue.remove_key(&"request");
+    /// assert_eq!(Removed::Cancelled, block_on(removed));
+    /// assert!(queue.wait_removed(&"request").is_none());
+    /// ```
+    pub fn wait_removed(&self, key: &K) -> Option<WaitRemoved<K, V>> {
+        let mut state = self.lock();
+        if !state.queue.contains_key(key) {
+            return None;
+        }
+        let watch = Arc::new(Mutex::new(Watch::default()));
+        state
+            .watches
+            .entry(key.clone())
+            .or_default()
+            .push(watch.clone());
+        Some(WaitRemoved {
+            shared: self.shared.clone(),
+            key: key.clone(),
+            watch,
+        })
+    }
+
+    /// Close the queue, which wakes all tasks waiting for entries or room.
+    ///
+    /// Entries can still be inserted and removed without waiting.
+    pub fn close(&self) {
+        let mut state = self.lock();
+        state.closed = true;
+        state.consumers.wake_all();
+        state.producers.wake_all();
+    }
+
+    /// Returns true if the queue was closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Remove the current head of the queue, or register the task of `cx` under `waiter` to be
+    /// woken when there is one.
+    fn poll_pop(
+        &self,
+        waiter: &mut Option<u64>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(K, V), Closed>> {
+        let mut state = self.lock();
+        if let Some(entry) = state.queue.remove_head() {
+            if let Some(waiter) = waiter.take() {
+                state.consumers.remove(waiter);
+            }
+            state.producers.wake_one();
+            state.removed(&entry.0, Removed::Popped);
+            return Poll::Ready(Ok(entry));
+        }
+        if state.closed {
+            *waiter = None;
+            return Poll::Ready(Err(Closed));
+        }
+        state.consumers.register(waiter, cx.waker());
+        Poll::Pending
+    }
+
+    /// Insert the entry in `entry`, or register the task of `cx` under `waiter` to be woken when
+    /// there is room.
+    fn poll_push(
+        &self,
+        entry: &mut Option<(K, V)>,
+        waiter: &mut Option<u64>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Full<K, V>>> {
+        let (key, value) = match entry.take() {
+            Some(entry) => entry,
+            None => return Poll::Ready(Ok(())),
+        };
+        let mut state = self.lock();
+        match state.queue.try_insert(key, value) {
+            Ok(()) => {
+                if let Some(waiter) = waiter.take() {
+                    state.producers.remove(waiter);
+                }
+                state.consumers.wake_one();
+                Poll::Ready(Ok(()))
+            }
+            Err(full) => {
+                if state.closed {
+                    *waiter = None;
+                    return Poll::Ready(Err(full));
+                }
+                *entry = Some(full.into_inner());
+                state.producers.register(waiter, cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Stop waiting for an entry under `waiter`.
+    fn stop_popping(&self, waiter: Option<u64>) {
+        if let Some(waiter) = waiter {
+            let mut state = self.lock();
+            // If the task was woken for an entry it does not remove, another task has to be
+            // woken instead.
+            if !state.consumers.remove(waiter) && !state.queue.is_empty() {
+                state.consumers.wake_one();
+            }
+        }
+    }
+
+    /// Stop waiting for room under `waiter`.
+    fn stop_pushing(&self, waiter: Option<u64>) {
+        if let Some(waiter) = waiter {
+            let mut state = self.lock();
+            if !state.producers.remove(waiter) && !state.queue.is_full() {
+                state.producers.wake_one();
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<K, V>> {
+        self.shared
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<K, V> Clone for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn clone(&self) -> Self {
+        Queue {
+            shared: self.shared.clone(),
+            consumer: None,
+            producer: None,
+            buffered: None,
+        }
+    }
+}
+
+impl<K, V> Drop for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn drop(&mut self) {
+        let (consumer, producer) = (self.consumer.take(), self.producer.take());
+        self.stop_popping(consumer);
+        self.stop_pushing(producer);
+    }
+}
+
+// The buffered entry is never pinned.
+impl<K, V> Unpin for Queue<K, V> where K: Clone + Eq + Hash {}
+
+impl<K, V> Stream for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Item = (K, V);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<(K, V)>> {
+        let mut consumer = self.consumer.take();
+        let poll = self.poll_pop(&mut consumer, cx);
+        self.consumer = consumer;
+        poll.map(Result::ok)
+    }
+}
+
+/// Sending an entry waits until the entry sent before fits into the queue. Closing the sink
+/// closes the queue, once the last entry fits into it.
+///
+/// If the queue is closed while it is full, sending fails and the entry which did not fit is
+/// dropped.
+impl<K, V> Sink<(K, V)> for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Error = Closed;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Closed>> {
+        self.poll_flush(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, entry: (K, V)) -> Result<(), Closed> {
+        self.buffered = Some(entry);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Closed>> {
+        let this = &mut *self;
+        let mut buffered = this.buffered.take();
+        let mut producer = this.producer.take();
+        let poll = this.poll_push(&mut buffered, &mut producer, cx);
+        this.buffered = buffered;
+        this.producer = producer;
+        poll.map_err(|_| Closed)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Closed>> {
+        let poll = self.as_mut().poll_flush(cx);
+        if let Poll::Ready(Ok(())) = poll {
+            self.close();
+        }
+        poll
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// Future removing the head of a [`Queue`](struct.Queue.html), created by
+/// [`Queue::pop`](struct.Queue.html#method.pop).
+pub struct Pop<'a, K: 'a, V: 'a>
+where
+    K: Clone + Eq + Hash,
+{
+    queue: &'a Queue<K, V>,
+    /// The id under which the waker of the task is registered.
+    waiter: Option<u64>,
+}
+
+impl<'a, K, V> Future for Pop<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Output = Result<(K, V), Closed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        this.queue.poll_pop(&mut this.waiter, cx)
+    }
+}
+
+impl<'a, K, V> Drop for Pop<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn drop(&mut self) {
+        self.queue.stop_popping(self.waiter.take());
+    }
+}
+
+/// Future inserting an entry into a [`Queue`](struct.Queue.html), created by
+/// [`Queue::push`](struct.Queue.html#method.push).
+pub struct Push<'a, K: 'a, V: 'a>
+where
+    K: Clone + Eq + Hash,
+{
+    queue: &'a Queue<K, V>,
+    entry: Option<(K, V)>,
+    /// The id under which the waker of the task is registered.
+    waiter: Option<u64>,
+}
+
+// The entry is never pinned.
+impl<'a, K, V> Unpin for Push<'a, K, V> where K: Clone + Eq + Hash {}
+
+impl<'a, K, V> Future for Push<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Output = Result<(), Full<K, V>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        this.queue.poll_push(&mut this.entry, &mut this.waiter, cx)
+    }
+}
+
+impl<'a, K, V> Drop for Push<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn drop(&mut self) {
+        self.queue.stop_pushing(self.waiter.take());
+    }
+}
+
+/// Future waiting for the entry of a key to be removed from a [`Queue`](struct.Queue.html),
+/// created by [`Queue::wait_removed`](struct.Queue.html#method.wait_removed).
+pub struct WaitRemoved<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    shared: Arc<Mutex<State<K, V>>>,
+    key: K,
+    watch: Arc<Mutex<Watch>>,
+}
+
+impl<K, V> Future for WaitRemoved<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Output = Removed;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Removed> {
+        let mut watch = self
+            .watch
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match watch.removed {
+            Some(removed) => Poll::Ready(removed),
+            None => {
+                watch.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<K, V> Drop for WaitRemoved<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn drop(&mut self) {
+        let mut state = self
+            .shared
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let emptied = match state.watches.get_mut(&self.key) {
+            Some(watches) => {
+                watches.retain(|watch| !Arc::ptr_eq(watch, &self.watch));
+                watches.is_empty()
+            }
+            None => false,
+        };
+        if emptied {
+            state.watches.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn pop_is_woken_by_insert_from_another_thread() {
+    use futures::executor::block_on;
+    use std::thread;
+    use std::time::Duration;
+
+    let queue = Queue::new();
+    let producer = queue.clone();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        producer.insert(1, "job").unwrap();
+    });
+
+    assert_eq!(Ok((1, "job")), block_on(queue.pop()));
+    handle.join().unwrap();
+}
@@ -0,0 +1,243 @@
+//! Builder for configuring the queues of this crate.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use delay;
+use deque::{DefaultHashBuilder, Deque};
+use fifo;
+use lru;
+
+#[cfg(feature = "std")]
+pub use delay::Timers;
+pub use deque::DuplicateKeys;
+pub use fifo::Overflow;
+pub use lru::Admission;
+
+/// Builder for the queues of this crate.
+///
+/// ```
+/// use addressable_queue::builder::{DuplicateKeys, Overflow, QueueBuilder};
+///
+/// let mut queue = QueueBuilder::new()
+///     .bound(2)
+///     .overflow(Overflow::Reject)
+///     .duplicate_keys(DuplicateKeys::Ignore)
+///     .build_bounded();
+///
+/// assert_eq!(None, queue.insert(2u8, 4u8));
+/// assert_eq!(None, queue.insert(2u8, 5u8));
+/// assert_eq!(None, queue.insert(3u8, 6u8));
+/// assert_eq!(Some((4, 8)), queue.insert(4u8, 8u8));
+/// assert_eq!(queue.into_vec(), vec![(2, 4), (3, 6)]);
+/// ```
+pub struct QueueBuilder<S = DefaultHashBuilder> {
+    capacity: usize,
+    hasher: S,
+    bound: Option<usize>,
+    overflow: Overflow,
+    compaction_threshold: f64,
+    duplicates: DuplicateKeys,
+    admission: Admission,
+    #[cfg(feature = "std")]
+    timers: Timers,
+}
+
+impl QueueBuilder {
+    /// Create a new builder with the default configuration.
+    pub fn new() -> Self {
+        QueueBuilder::default()
+    }
+}
+
+impl<S> QueueBuilder<S>
+where
+    S: BuildHasher,
+{
+    /// Allocate space for at least `capacity` elements up front.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Use the given hash builder to hash keys.
+    pub fn hasher<H>(self, hasher: H) -> QueueBuilder<H>
+    where
+        H: BuildHasher,
+    {
+        QueueBuilder {
+            capacity: self.capacity,
+            hasher,
+            bound: self.bound,
+            overflow: self.overflow,
+            compaction_threshold: self.compaction_threshold,
+            duplicates: self.duplicates,
+            admission: self.admission,
+            #[cfg(feature = "std")]
+            timers: self.timers,
+        }
+    }
+
+    /// Set the maximum number of elements of a bounded queue or LRU queue.
+    pub fn bound(mut self, bound: usize) -> Self {
+        self.bound = Some(bound);
+        self
+    }
+
+    /// Set what a full bounded queue does when another entry is inserted.
+    ///
+    /// By default the head of the queue is evicted.
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Reclaim the slots of removed elements once there are more than `ratio` of them per live
+    /// element.
+    ///
+    /// The default is `1.0`, pass `f64::INFINITY` to only reclaim slots when they reach an end of
+    /// the queue.
+    pub fn compaction_threshold(mut self, ratio: f64) -> Self {
+        self.compaction_threshold = ratio;
+        self
+    }
+
+    /// Set what happens when an entry is inserted for a key which is already contained.
+    ///
+    /// By default the old entry is removed.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// Set which new entries a full LRU queue admits.
+    ///
+    /// ```
+    /// use addressable_queue::builder::{Admission, QueueBuilder};
+    ///
+    /// let queue = QueueBuilder::new()
+    ///     .bound(100)
+    ///     .admission(Admission::TinyLfu)
+    ///     .build_lru::<u32, u32>();
+    /// assert_eq!(Admission::TinyLfu, queue.admission());
+    /// ```
+    pub fn admission(mut self, admission: Admission) -> Self {
+        self.admission = admission;
+        self
+    }
+
+    /// Set how a delay queue keeps track of the instants of its entries.
+    ///
+    /// By default a heap is used.
+    ///
+    /// ```
+    /// use addressable_queue::builder::{QueueBuilder, Timers};
+    /// use std::time::Duration;
+    ///
+    /// let mut queue = QueueBuilder::new()
+    ///     .timers(Timers::Wheel {
+    ///         resolution: Duration::from_millis(1),
+    ///     })
+    ///     .build_delay();
+    /// queue.insert("timeout", (), Duration::from_secs(30));
+    /// assert!(queue.contains_key(&"timeout"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn timers(mut self, timers: Timers) -> Self {
+        self.timers = timers;
+        self
+    }
+
+    /// Build an addressable deque.
+    pub fn build_deque<K, V>(self) -> Deque<K, V, S>
+    where
+        K: Clone + Eq + Hash,
+    {
+        let mut deque = Deque::with_capacity_and_hasher(self.capacity, self.hasher);
+        deque.raw.duplicates = self.duplicates;
+        deque.raw.compaction_threshold = self.compaction_threshold;
+        deque
+    }
+
+    /// Build an addressable FIFO queue.
+    ///
+    /// ```
+    /// use addressable_queue::builder::QueueBuilder;
+    ///
+    /// let mut queue = QueueBuilder::new().capacity(16).build_fifo();
+    /// queue.insert(2u8, 4u8);
+    /// assert!(queue.capacity() >= 16);
+    /// ```
+    pub fn build_fifo<K, V>(self) -> fifo::Queue<K, V, S>
+    where
+        K: Clone + Eq + Hash,
+    {
+        fifo::Queue::from(self.build_deque())
+    }
+
+    /// Build a bounded FIFO queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no bound was set.
+    pub fn build_bounded<K, V>(self) -> fifo::BoundedQueue<K, V, S>
+    where
+        K: Clone + Eq + Hash,
+    {
+        let bound = self.bound.expect("a bounded queue requires a bound");
+        let overflow = self.overflow;
+        fifo::BoundedQueue::from_parts(self.build_deque(), bound, overflow)
+    }
+
+    /// Build an addressable LRU queue.
+    ///
+    /// If a bound was set, it is used as the capacity of the queue.
+    pub fn build_lru<K, V>(self) -> lru::Queue<K, V, S>
+    where
+        K: Clone + Eq + Hash,
+    {
+        let bound = self.bound;
+        let admission = self.admission;
+        let mut queue = lru::Queue::from_parts(self.build_deque(), bound);
+        queue.set_admission(admission);
+        queue
+    }
+
+    /// Build an addressable delay queue.
+    #[cfg(feature = "std")]
+    pub fn build_delay<K, V>(self) -> delay::Queue<K, V>
+    where
+        K: Clone + Eq + Hash,
+    {
+        delay::Queue::with_timers(self.timers)
+    }
+}
+
+impl Default for QueueBuilder {
+    fn default() -> Self {
+        QueueBuilder {
+            capacity: 0,
+            hasher: DefaultHashBuilder::default(),
+            bound: None,
+            overflow: Overflow::default(),
+            compaction_threshold: 1.0,
+            duplicates: DuplicateKeys::default(),
+            admission: Admission::default(),
+            #[cfg(feature = "std")]
+            timers: Timers::default(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Implementation of an addressable CLOCK cache, which gives entries a second chance before they
+//! are evicted.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::hash::Hash;
+use deque::Deque;
+
+/// An addressable CLOCK cache holding at most `capacity` entries.
+///
+/// Accessing an entry only sets its reference bit, it is not moved. When the cache is full, the
+/// hand passes over the entries starting at the oldest one: entries with the bit set have it
+/// cleared and are skipped, the first entry without it is evicted.
+///
+/// ```
+/// use addressable_queue::clock::Cache;
+///
+/// let mut cache = Cache::new(2);
+/// cache.insert('a', 1);
+/// cache.insert('b', 2);
+/// cache.get(&'a');
+///
+/// assert_eq!(Some(('b', 2)), cache.insert('c', 3));
+/// assert_eq!(Some(('a', 1)), cache.insert('d', 4));
+/// ```
+pub struct Cache<K, V> {
+    inner: Deque<K, (bool, V)>,
+    capacity: usize,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Cache {
+            inner: Deque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the cache contains an entry for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Access an entry, which sets its reference bit.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Access an entry mutably, which sets its reference bit.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner
+            .get_mut(key)
+            .map(|&mut (ref mut referenced, ref mut value)| {
+                *referenced = true;
+                value
+            })
+    }
+
+    /// Returns a reference to the value of the specified key, without setting its reference bit.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.inner.get(key).map(|(_, value)| value)
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its value is replaced, its reference bit set, and the
+    /// old entry is returned. Otherwise, if the cache is full, the entry the hand stops at is
+    /// evicted and returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        if let Some(slot) = self.get_mut(&key) {
+            let old = ::core::mem::replace(slot, value);
+            return Some((key, old));
+        }
+        let evicted = if self.len() >= self.capacity {
+            self.evict()
+        } else {
+            None
+        };
+        self.inner.push_back(key, (false, value));
+        evicted
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key).map(|(_, value)| value)
+    }
+
+    /// Advance the hand to the first entry without its reference bit, and evict it.
+    pub fn evict(&mut self) -> Option<(K, V)> {
+        loop {
+            let (key, (referenced, value)) = self.inner.pop_front()?;
+            if !referenced {
+                return Some((key, value));
+            }
+            self.inner.push_back(key, (false, value));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Implementation of an addressable FIFO queue which coalesces entries inserted for the same key.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::Deque;
+
+/// An addressable FIFO queue which merges the value of a reinserted key into the existing one.
+///
+/// A reinserted entry is moved to the tail of the queue, which debounces keys that keep being
+/// inserted.
+pub struct Queue<K, V, F> {
+    inner: Deque<K, V>,
+    merge: F,
+}
+
+impl<K, V, F> Queue<K, V, F>
+where
+    K: Clone + Eq + Hash,
+    F: FnMut(&mut V, V),
+{
+    /// Create a new instance of a queue, merging values with the provided closure.
+    ///
+    /// The closure receives the queued value and the newly inserted one.
+    pub fn new(merge: F) -> Self {
+        Queue {
+            inner: Deque::new(),
+            merge,
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Insert an entry at the end of the queue.
+    ///
+    /// If the key is already contained its value is merged with the new one and the entry is
+    /// moved to the end of the queue, in which case `true` is returned.
+    ///
+    /// ```
+    /// use addressable_queue::coalesce::Queue;
+    ///
+    /// let mut queue = Queue::new(|events: &mut Vec<&str>, mut new| events.append(&mut new));
+    /// assert!(!queue.insert("a.txt", vec!["created"]));
+    /// assert!(!queue.insert("b.txt", vec!["created"]));
+    /// assert!(queue.insert("a.txt", vec!["modified"]));
+    ///
+    /// assert_eq!(queue.into_vec(), vec![
+    ///     ("b.txt", vec!["created"]),
+    ///     ("a.txt", vec!["created", "modified"]),
+    /// ]);
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> bool {
+        match self.inner.remove_key(&key) {
+            Some(mut queued) => {
+                (self.merge)(&mut queued, value);
+                self.inner.push_back(key, queued);
+                true
+            }
+            None => {
+                self.inner.push_back(key, value);
+                false
+            }
+        }
+    }
+
+    /// Remove the current head of the queue, and return it if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        self.inner.pop_front()
+    }
+
+    /// Remove the current tail of the queue, and return it if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        self.inner.pop_back()
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+}
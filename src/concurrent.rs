@@ -0,0 +1,532 @@
+//! Queues which can be shared between threads.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use deque::{DefaultHashBuilder, Deque};
+use lru::{self, Stats};
+use primitives::{Arc, AtomicU64, Condvar, Mutex, MutexGuard, Ordering};
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::thread;
+use std::vec::Vec;
+
+/// A concurrent LRU cache.
+///
+/// The keys are distributed over several shards, each of which is an [`lru::Queue`] behind its
+/// own lock, so threads accessing different shards do not contend. Recency is tracked per shard,
+/// so the evicted entry is the least recently used one of its shard, not of the whole cache.
+///
+/// [`lru::Queue`]: ../lru/struct.Queue.html
+///
+/// ```
+/// use addressable_queue::concurrent::LruCache;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let cache = Arc::new(LruCache::new(100));
+/// let handles: Vec<_> = (0..4u32)
+///     .map(|i| {
+///         let cache = cache.clone();
+///         thread::spawn(move || {
+///             cache.insert(i, i * 2);
+///         })
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert_eq!(4, cache.len());
+/// assert_eq!(Some(6), cache.get(&3));
+/// ```
+pub struct LruCache<K, V, S = DefaultHashBuilder> {
+    shards: Box<[Mutex<lru::Queue<K, V, S>>]>,
+    hasher: S,
+    capacity: usize,
+    /// The keys whose values are currently being loaded by `get_or_insert_with`.
+    loads: Mutex<HashMap<K, Arc<Load>, S>>,
+}
+
+/// A value being loaded, which other threads can wait for.
+#[derive(Default)]
+struct Load {
+    done: Mutex<bool>,
+    finished: Condvar,
+}
+
+impl Load {
+    fn wait(&self) {
+        let mut done = self
+            .done
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while !*done {
+            done = self
+                .finished
+                .wait(done)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+}
+
+/// Ends a load when dropped, also if the loading closure panics.
+struct LoadGuard<'a, K: 'a, V: 'a, S: 'a>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    cache: &'a LruCache<K, V, S>,
+    key: &'a K,
+}
+
+impl<'a, K, V, S> Drop for LoadGuard<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    fn drop(&mut self) {
+        let load = self.cache.loads().remove(self.key);
+        if let Some(load) = load {
+            *load
+                .done
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+            load.finished.notify_all();
+        }
+    }
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new cache holding about `capacity` entries, with a shard count suited to the
+    /// number of available CPUs.
+    pub fn new(capacity: usize) -> Self {
+        let cpus = thread::available_parallelism().map_or(1, usize::from);
+        LruCache::with_shards(capacity, (cpus * 4).next_power_of_two())
+    }
+
+    /// Create a new cache holding about `capacity` entries, distributed over `shards` shards.
+    pub fn with_shards(capacity: usize, shards: usize) -> Self {
+        LruCache::with_shards_and_hasher(capacity, shards, DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Create a new cache holding about `capacity` entries, distributed over `shards` shards,
+    /// which uses the given hash builder to hash keys.
+    ///
+    /// Each shard holds at most `capacity / shards` entries, rounded up.
+    pub fn with_shards_and_hasher(capacity: usize, shards: usize, hasher: S) -> Self {
+        let shards = shards.max(1);
+        let per_shard = capacity.div_ceil(shards);
+        let shards = (0..shards)
+            .map(|_| {
+                let deque = Deque::with_capacity_and_hasher(per_shard, hasher.clone());
+                Mutex::new(lru::Queue::from_parts(deque, Some(per_shard)))
+            })
+            .collect();
+        LruCache {
+            shards,
+            loads: Mutex::new(HashMap::with_hasher(hasher.clone())),
+            hasher,
+            capacity,
+        }
+    }
+
+    /// Returns the capacity the cache was created with.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of entries in the cache.
+    ///
+    /// As the shards are counted one after the other, this is only a snapshot while other
+    /// threads modify the cache.
+    pub fn len(&self) -> usize {
+        (0..self.shards.len()).map(|i| self.lock(i).len()).sum()
+    }
+
+    /// Returns true if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        (0..self.shards.len()).all(|i| self.lock(i).is_empty())
+    }
+
+    /// Returns true if the cache contains an entry for the key, without marking it as used.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.shard(key).contains_key(key)
+    }
+
+    /// Returns a clone of the value of the key, and marks the entry as used.
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.shard(key).get(key).cloned()
+    }
+
+    /// Returns a clone of the value of the key, inserting the value returned by `init` if there
+    /// is none.
+    ///
+    /// If several threads miss the same key at once, only one of them calls its `init` while the
+    /// others wait for its value, so an expensive load is not repeated. Should `init` panic, one
+    /// of the waiting threads takes over the load.
+    ///
+    /// ```
+    /// use addressable_queue::concurrent::LruCache;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let cache = Arc::new(LruCache::new(10));
+    /// let loads = Arc::new(AtomicUsize::new(0));
+    /// let handles: Vec<_> = (0..8)
+    ///     .map(|_| {
+    ///         let (cache, loads) = (cache.clone(), loads.clone());
+    ///         thread::spawn(move || {
+    ///             cache.get_or_insert_with("key", || {
+    ///                 loads.fetch_add(1, Ordering::SeqCst);
+    ///                 thread::sleep(Duration::from_millis(50));
+    ///                 42
+    ///             })
+    ///         })
+    ///     })
+    ///     .collect();
+    /// for handle in handles {
+    ///     assert_eq!(42, handle.join().unwrap());
+    /// }
+    /// assert_eq!(1, loads.load(Ordering::SeqCst));
+    /// ```
+    pub fn get_or_insert_with<F>(&self, key: K, init: F) -> V
+    where
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        loop {
+            if let Some(value) = self.get(&key) {
+                return value;
+            }
+            let pending = {
+                let mut loads = self.loads();
+                // The loading thread inserts the value before ending the load, so checking again
+                // while holding the lock ensures the value is not loaded twice.
+                if let Some(value) = self.peek(&key) {
+                    return value;
+                }
+                match loads.get(&key) {
+                    Some(load) => Some(load.clone()),
+                    None => {
+                        loads.insert(key.clone(), Arc::new(Load::default()));
+                        None
+                    }
+                }
+            };
+            match pending {
+                Some(load) => load.wait(),
+                None => {
+                    let _guard = LoadGuard {
+                        cache: self,
+                        key: &key,
+                    };
+                    let value = init();
+                    self.insert(key.clone(), value.clone());
+                    return value;
+                }
+            }
+        }
+    }
+
+    /// Returns a clone of the value of the key, without marking the entry as used.
+    pub fn peek(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.shard(key).peek(key).cloned()
+    }
+
+    /// Insert an entry as the most recently used one of its shard.
+    ///
+    /// Returns the old entry of the key, or the entries evicted from the shard if it was full.
+    pub fn insert(&self, key: K, value: V) -> Vec<(K, V)> {
+        self.shard(&key).insert(key, value)
+    }
+
+    /// Remove the entry of the key, and return its value.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.shard(key).remove_key(key)
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        for i in 0..self.shards.len() {
+            self.lock(i).clear();
+        }
+    }
+
+    /// Returns the sum of the statistics of all shards.
+    pub fn stats(&self) -> Stats {
+        (0..self.shards.len())
+            .filter_map(|i| self.lock(i).stats())
+            .fold(Stats::default(), |total, stats| Stats {
+                hits: total.hits + stats.hits,
+                misses: total.misses + stats.misses,
+                insertions: total.insertions + stats.insertions,
+                evictions: total.evictions + stats.evictions,
+                expirations: total.expirations + stats.expirations,
+            })
+    }
+
+    /// Reclaim the slots of the removed elements of every shard, locking one shard at a time.
+    pub fn compact(&self) {
+        for i in 0..self.shards.len() {
+            self.lock(i).compact();
+        }
+    }
+
+    fn loads(&self) -> MutexGuard<'_, HashMap<K, Arc<Load>, S>> {
+        self.loads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Lock the shard responsible for the key.
+    fn shard(&self, key: &K) -> MutexGuard<'_, lru::Queue<K, V, S>> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        self.lock(index)
+    }
+
+    /// Lock a shard.
+    ///
+    /// A panic while a shard was locked cannot leave the queue in an inconsistent state, so a
+    /// poisoned lock is used as is.
+    fn lock(&self, index: usize) -> MutexGuard<'_, lru::Queue<K, V, S>> {
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A concurrent addressable FIFO queue.
+///
+/// The keys are distributed over several shards, each of which is a [`Deque`] behind its own
+/// lock, so producers inserting different keys rarely contend and `remove_key` only locks the
+/// shard of its key. Every entry is tagged with a global sequence number when inserted, and
+/// `remove_head` removes the entry with the lowest number among the heads of the shards. As the
+/// shards are inspected one after the other, the order is only approximate while other threads
+/// modify the queue, but entries of the same shard are always removed in insertion order.
+///
+/// [`Deque`]: ../deque/struct.Deque.html
+///
+/// ```
+/// use addressable_queue::concurrent::Queue;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let queue = Arc::new(Queue::new());
+/// let handles: Vec<_> = (0..4u32)
+///     .map(|i| {
+///         let queue = queue.clone();
+///         thread::spawn(move || queue.insert(i, i * 2))
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(Some(6), queue.remove_key(&3));
+///
+/// // Without concurrent modifications the entries are removed in insertion order.
+/// queue.insert(10, 0);
+/// queue.insert(11, 0);
+/// let keys: Vec<_> = std::iter::from_fn(|| queue.remove_head()).map(|(k, _)| k).collect();
+/// assert_eq!(&[10, 11], &keys[keys.len() - 2..]);
+/// assert!(queue.is_empty());
+/// ```
+pub struct Queue<K, V, S = DefaultHashBuilder> {
+    shards: Box<[Mutex<Shard<K, V, S>>]>,
+    hasher: S,
+    /// The sequence number of the next inserted entry.
+    sequence: AtomicU64,
+}
+
+/// A shard of a [`Queue`](struct.Queue.html), storing the sequence number of every entry with
+/// its value.
+type Shard<K, V, S> = Deque<K, (u64, V), S>;
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new queue, with a shard count suited to the number of available CPUs.
+    pub fn new() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, usize::from);
+        Queue::with_shards((cpus * 4).next_power_of_two())
+    }
+
+    /// Create a new queue, which distributes its entries over `shards` shards.
+    pub fn with_shards(shards: usize) -> Self {
+        Queue::with_shards_and_hasher(shards, DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, S> Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Create a new queue, which distributes its entries over `shards` shards and uses the given
+    /// hash builder to hash keys.
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> Self {
+        let shards = (0..shards.max(1))
+            .map(|_| Mutex::new(Deque::with_hasher(hasher.clone())))
+            .collect();
+        Queue {
+            shards,
+            hasher,
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of entries in the queue.
+    ///
+    /// As the shards are counted one after the other, this is only a snapshot while other
+    /// threads modify the queue.
+    pub fn len(&self) -> usize {
+        (0..self.shards.len()).map(|i| self.lock(i).len()).sum()
+    }
+
+    /// Returns true if the queue contains no entries.
+    pub fn is_empty(&self) -> bool {
+        (0..self.shards.len()).all(|i| self.lock(i).is_empty())
+    }
+
+    /// Returns true if the queue contains an entry for the key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.shard(key).contains_key(key)
+    }
+
+    /// Returns a clone of the value of the key.
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.shard(key).get(key).map(|(_, value)| value.clone())
+    }
+
+    /// Insert an entry at the end of the queue.
+    ///
+    /// If there already is an entry for the key, it is removed, so the key moves to the end.
+    pub fn insert(&self, key: K, value: V) {
+        let mut shard = self.shard(&key);
+        // Taking the number while holding the lock keeps the numbers of a shard ascending.
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        shard.push_back(key, (sequence, value));
+    }
+
+    /// Remove the oldest head among the shards, and return it if there was one.
+    pub fn remove_head(&self) -> Option<(K, V)> {
+        loop {
+            let mut oldest: Option<(usize, u64)> = None;
+            for i in 0..self.shards.len() {
+                if let Some((_, &(sequence, _))) = self.lock(i).peek_front() {
+                    if oldest.is_none_or(|(_, min)| sequence < min) {
+                        oldest = Some((i, sequence));
+                    }
+                }
+            }
+            let (index, sequence) = oldest?;
+            let mut shard = self.lock(index);
+            // Another thread may have removed the head in the meantime, then look again.
+            if shard.peek_front().map(|(_, &(s, _))| s) == Some(sequence) {
+                return shard.pop_front().map(|(key, (_, value))| (key, value));
+            }
+        }
+    }
+
+    /// Remove a value by specifying its key, locking only the shard of the key.
+    pub fn remove_key(&self, key: &K) -> Option<V> {
+        self.shard(key).remove_key(key).map(|(_, value)| value)
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        for i in 0..self.shards.len() {
+            *self.lock(i) = Deque::with_hasher(self.hasher.clone());
+        }
+    }
+
+    /// Reclaim the slots of the removed elements of every shard, locking one shard at a time.
+    pub fn compact(&self) {
+        for i in 0..self.shards.len() {
+            self.lock(i).compact();
+        }
+    }
+
+    /// Lock the shard responsible for the key.
+    fn shard(&self, key: &K) -> MutexGuard<'_, Shard<K, V, S>> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        self.lock(index)
+    }
+
+    /// Lock a shard, using a poisoned lock as is like [`LruCache`](struct.LruCache.html).
+    fn lock(&self, index: usize) -> MutexGuard<'_, Shard<K, V, S>> {
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn remove_head_follows_insertion_order_across_shards() {
+    let queue = Queue::with_shards(8);
+    for i in 0..100u32 {
+        queue.insert(i, ());
+    }
+    queue.insert(0, ());
+
+    let keys: Vec<u32> = ::std::iter::from_fn(|| queue.remove_head())
+        .map(|(key, _)| key)
+        .collect();
+    let expected: Vec<u32> = (1..100).chain(Some(0)).collect();
+    assert_eq!(expected, keys);
+}
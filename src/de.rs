@@ -0,0 +1,212 @@
+//! Deserialization of queues which decides what happens to duplicate keys.
+//!
+//! A serialized queue never contains a key twice, but hand-written or merged files might. By
+//! default the later entry wins, like inserting it again would. The functions of this module can
+//! be used with `#[serde(deserialize_with)]` to choose another policy, and
+//! [`DequeSeed`](struct.DequeSeed.html) allows choosing it at runtime.
+//!
+//! ```
+//! extern crate addressable_queue;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate serde_json;
+//!
+//! use addressable_queue::fifo::Queue;
+//!
+//! #[derive(Deserialize)]
+//! struct State {
+//!     #[serde(deserialize_with = "addressable_queue::de::reject_duplicates")]
+//!     jobs: Queue<String, u32>,
+//! }
+//!
+//! # fn main() {
+//! let json = r#"{"jobs": [["build", 1], ["test", 2], ["build", 3]]}"#;
+//! let error = serde_json::from_str::<State>(json).err().unwrap();
+//! assert!(error.to_string().starts_with("duplicate key at position 2"));
+//! # }
+//! ```
+//!
+//! This module requires the `serde` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+use deque::{cautious_capacity, DefaultHashBuilder, Deque};
+use serde::de::{DeserializeSeed, Error, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+/// What happens when a deserialized queue contains a key more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// Deserialization fails.
+    Error,
+    /// The first entry is kept, later ones are dropped.
+    FirstWins,
+    /// The later entry replaces the earlier one, and takes its own position.
+    #[default]
+    LastWins,
+}
+
+/// Deserializes a [`Deque`](../deque/struct.Deque.html) with the given policy for duplicate keys.
+///
+/// ```
+/// extern crate addressable_queue;
+/// extern crate serde;
+/// extern crate serde_json;
+///
+/// use addressable_queue::de::{DequeSeed, OnDuplicate};
+/// use addressable_queue::deque::Deque;
+/// use serde::de::DeserializeSeed;
+///
+/// # fn main() {
+/// let mut json = serde_json::Deserializer::from_str(r#"[["a", 1], ["b", 2], ["a", 3]]"#);
+/// let deque: Deque<String, u32> = DequeSeed::new(OnDuplicate::FirstWins)
+///     .deserialize(&mut json)
+///     .unwrap();
+///
+/// assert_eq!(vec![("a".to_string(), 1), ("b".to_string(), 2)], deque.into_vec());
+/// # }
+/// ```
+pub struct DequeSeed<K, V, H = DefaultHashBuilder> {
+    policy: OnDuplicate,
+    _marker: PhantomData<(K, V, H)>,
+}
+
+impl<K, V, H> DequeSeed<K, V, H> {
+    /// Create a seed applying `policy` to duplicate keys.
+    pub fn new(policy: OnDuplicate) -> Self {
+        DequeSeed {
+            policy,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, K, V, H> DeserializeSeed<'de> for DequeSeed<K, V, H>
+where
+    K: Deserialize<'de> + Clone + Eq + Hash,
+    V: Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    type Value = Deque<K, V, H>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(DequeVisitor::new(self.policy))
+    }
+}
+
+/// Deserialize a queue, failing if it contains a key more than once.
+pub fn reject_duplicates<'de, T, K, V, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: From<Deque<K, V>>,
+    K: Deserialize<'de> + Clone + Eq + Hash,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserialize_with(deserializer, OnDuplicate::Error)
+}
+
+/// Deserialize a queue, keeping the first entry of every key.
+pub fn first_wins<'de, T, K, V, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: From<Deque<K, V>>,
+    K: Deserialize<'de> + Clone + Eq + Hash,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserialize_with(deserializer, OnDuplicate::FirstWins)
+}
+
+/// Deserialize a queue, keeping the last entry of every key, which is also what deserializing
+/// it without these functions does.
+pub fn last_wins<'de, T, K, V, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: From<Deque<K, V>>,
+    K: Deserialize<'de> + Clone + Eq + Hash,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    deserialize_with(deserializer, OnDuplicate::LastWins)
+}
+
+fn deserialize_with<'de, T, K, V, D>(deserializer: D, policy: OnDuplicate) -> Result<T, D::Error>
+where
+    T: From<Deque<K, V>>,
+    K: Deserialize<'de> + Clone + Eq + Hash,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    DequeSeed::new(policy)
+        .deserialize(deserializer)
+        .map(T::from)
+}
+
+/// Builds a deque from a sequence of pairs as they are read.
+pub(crate) struct DequeVisitor<K, V, H> {
+    policy: OnDuplicate,
+    _marker: PhantomData<(K, V, H)>,
+}
+
+impl<K, V, H> DequeVisitor<K, V, H> {
+    pub(crate) fn new(policy: OnDuplicate) -> Self {
+        DequeVisitor {
+            policy,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, K, V, H> Visitor<'de> for DequeVisitor<K, V, H>
+where
+    K: Deserialize<'de> + Clone + Eq + Hash,
+    V: Deserialize<'de>,
+    H: BuildHasher + Default,
+{
+    type Value = Deque<K, V, H>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence of key-value pairs")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // The hint comes from the input, so like serde itself at most 1 MiB is reserved up front.
+        let capacity = cautious_capacity::<(K, V)>(seq.size_hint().unwrap_or(0));
+        let mut deque = Deque::with_capacity_and_hasher(capacity, H::default());
+        let mut position = 0;
+        while let Some((key, value)) = seq.next_element()? {
+            let duplicate = deque.contains_key(&key);
+            match self.policy {
+                OnDuplicate::Error if duplicate => {
+                    return Err(A::Error::custom(format_args!(
+                        "duplicate key at position {}",
+                        position
+                    )));
+                }
+                OnDuplicate::FirstWins if duplicate => {}
+                _ => deque.push_back(key, value),
+            }
+            position += 1;
+        }
+        Ok(deque)
+    }
+}
@@ -0,0 +1,140 @@
+//! Implementation of an addressable earliest-deadline-first scheduler.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use priority;
+use std::hash::Hash;
+use std::time::Instant;
+use std::vec::Vec;
+
+/// An addressable queue of entries with deadlines, where the entry with the earliest deadline is
+/// removed first.
+///
+/// Entries with equal deadlines are removed in the order they were inserted.
+///
+/// ```
+/// use addressable_queue::deadline::Queue;
+/// use std::time::{Duration, Instant};
+///
+/// let now = Instant::now();
+/// let mut queue = Queue::new();
+/// queue.insert("flush", now + Duration::from_secs(5), ());
+/// queue.insert("heartbeat", now + Duration::from_secs(1), ());
+///
+/// assert_eq!(None, queue.pop_due(now));
+/// assert_eq!(Some(("heartbeat", ())), queue.pop_due(now + Duration::from_secs(1)));
+///
+/// queue.reschedule(&"flush", now);
+/// assert_eq!(Some(("flush", ())), queue.pop_due(now));
+/// ```
+pub struct Queue<K, V> {
+    inner: priority::Queue<K, Instant, V>,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            inner: priority::Queue::new(),
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns the deadline of the entry with the specified key.
+    pub fn deadline(&self, key: &K) -> Option<Instant> {
+        self.inner.priority(key).cloned()
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)
+    }
+
+    /// Returns the entry with the earliest deadline, without removing it.
+    pub fn peek_earliest(&self) -> Option<(&K, Instant, &V)> {
+        self.inner
+            .peek()
+            .map(|(key, &deadline, value)| (key, deadline, value))
+    }
+
+    /// Insert an entry with a deadline.
+    ///
+    /// If the key is already contained, its deadline and value are replaced and the old ones are
+    /// returned.
+    pub fn insert(&mut self, key: K, deadline: Instant, value: V) -> Option<(Instant, V)> {
+        self.inner.push(key, deadline, value)
+    }
+
+    /// Remove the entry with the earliest deadline, if that deadline is not after `now`.
+    pub fn pop_due(&mut self, now: Instant) -> Option<(K, V)> {
+        if self.peek_earliest()?.1 > now {
+            return None;
+        }
+        self.inner.pop_min().map(|(key, _, value)| (key, value))
+    }
+
+    /// Remove the entry with the earliest deadline, and return it if there was one.
+    pub fn pop_earliest(&mut self) -> Option<(K, Instant, V)> {
+        self.inner.pop_min()
+    }
+
+    /// Change the deadline of an entry, and return the old deadline if the key was contained.
+    pub fn reschedule(&mut self, key: &K, deadline: Instant) -> Option<Instant> {
+        self.inner.change_priority(key, deadline)
+    }
+
+    /// Remove a value by specifying its key, and return its deadline and value.
+    pub fn remove_key(&mut self, key: &K) -> Option<(Instant, V)> {
+        self.inner.remove_key(key)
+    }
+
+    /// Convert the queue into a vec, where the first element has the earliest deadline.
+    pub fn into_sorted_vec(self) -> Vec<(K, Instant, V)> {
+        self.inner.into_sorted_vec()
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
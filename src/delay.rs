@@ -0,0 +1,271 @@
+//! Implementation of an addressable delay queue, where entries become available once their delay
+//! has passed.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use deadline;
+use std::boxed::Box;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use time::{Clock, SystemClock};
+use wheel::Wheel;
+
+/// An addressable queue, where every entry is hidden until the instant it becomes available.
+///
+/// Available entries are returned in the order they became available, pending entries can be
+/// cancelled by removing them.
+///
+/// ```
+/// use addressable_queue::delay::Queue;
+/// use std::time::{Duration, Instant};
+///
+/// let now = Instant::now();
+/// let mut queue = Queue::new();
+/// queue.insert_at("retry", (), now + Duration::from_secs(2));
+/// queue.insert_at("ping", (), now + Duration::from_secs(1));
+/// queue.insert_at("cancelled", (), now);
+/// queue.remove_key(&"cancelled");
+///
+/// assert_eq!(0, queue.poll_expired(now).count());
+///
+/// let later = now + Duration::from_secs(2);
+/// let keys: Vec<_> = queue.poll_expired(later).map(|(key, _)| key).collect();
+/// assert_eq!(keys, vec!["ping", "retry"]);
+/// ```
+pub struct Queue<K, V> {
+    inner: Backend<K, V>,
+    clock: Box<dyn Clock>,
+}
+
+/// How a delay [`Queue`](struct.Queue.html) keeps track of the instants of its entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timers {
+    /// A binary heap, where inserting and removing an entry takes `O(log n)` time.
+    #[default]
+    Heap,
+    /// A hierarchical timing wheel, where inserting and removing an entry takes `O(1)` time.
+    ///
+    /// Instants are rounded up to multiples of the resolution, so entries can become available
+    /// up to the resolution late, and entries within the same multiple in the order they were
+    /// inserted.
+    Wheel {
+        /// The resolution of the wheel.
+        resolution: Duration,
+    },
+}
+
+enum Backend<K, V> {
+    Heap(deadline::Queue<K, V>),
+    Wheel(Wheel<K, V>),
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue, which keeps track of the instants in a heap.
+    pub fn new() -> Self {
+        Queue::with_timers(Timers::Heap)
+    }
+
+    /// Create a new instance of a queue, which keeps track of the instants as specified.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resolution of a timing wheel is zero.
+    ///
+    /// ```
+    /// use addressable_queue::delay::{Queue, Timers};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut queue = Queue::with_timers(Timers::Wheel {
+    ///     resolution: Duration::from_millis(10),
+    /// });
+    /// let at = Instant::now() + Duration::from_millis(25);
+    /// queue.insert_at("timeout", (), at);
+    ///
+    /// assert_eq!(0, queue.poll_expired(at - Duration::from_millis(10)).count());
+    /// assert_eq!(1, queue.poll_expired(at + Duration::from_millis(10)).count());
+    /// ```
+    pub fn with_timers(timers: Timers) -> Self {
+        let inner = match timers {
+            Timers::Heap => Backend::Heap(deadline::Queue::new()),
+            Timers::Wheel { resolution } => Backend::Wheel(Wheel::new(resolution)),
+        };
+        Queue {
+            inner,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// Read the current time from `clock` for inserting entries with a delay, instead of from
+    /// the system.
+    ///
+    /// ```
+    /// use addressable_queue::delay::Queue;
+    /// use addressable_queue::time::{Clock, MockClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut queue = Queue::new();
+    /// queue.set_clock(clock.clone());
+    /// queue.insert("ping", (), Duration::from_secs(30));
+    ///
+    /// assert_eq!(0, queue.poll_expired(clock.now()).count());
+    /// clock.advance(Duration::from_secs(30));
+    /// assert_eq!(1, queue.poll_expired(clock.now()).count());
+    /// ```
+    pub fn set_clock<C>(&mut self, clock: C)
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Box::new(clock);
+    }
+
+    /// Returns the number of elements in the queue, including the ones not yet available.
+    pub fn len(&self) -> usize {
+        match self.inner {
+            Backend::Heap(ref heap) => heap.len(),
+            Backend::Wheel(ref wheel) => wheel.len(),
+        }
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        match self.inner {
+            Backend::Heap(ref heap) => heap.contains_key(key),
+            Backend::Wheel(ref wheel) => wheel.contains_key(key),
+        }
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match self.inner {
+            Backend::Heap(ref heap) => heap.get(key),
+            Backend::Wheel(ref wheel) => wheel.get(key).map(|(_, value)| value),
+        }
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.inner {
+            Backend::Heap(ref mut heap) => heap.get_mut(key),
+            Backend::Wheel(ref mut wheel) => {
+                wheel.get_mut(key).map(|&mut (_, ref mut value)| value)
+            }
+        }
+    }
+
+    /// Returns the instant the entry with the specified key becomes available.
+    pub fn available_at(&self, key: &K) -> Option<Instant> {
+        match self.inner {
+            Backend::Heap(ref heap) => heap.deadline(key),
+            Backend::Wheel(ref wheel) => wheel.get(key).map(|&(at, _)| at),
+        }
+    }
+
+    /// Returns the earliest instant an entry becomes available, which is when the queue should
+    /// be polled next.
+    pub fn next_available(&self) -> Option<Instant> {
+        match self.inner {
+            Backend::Heap(ref heap) => heap.peek_earliest().map(|(_, at, _)| at),
+            Backend::Wheel(ref wheel) => wheel.next_instant(),
+        }
+    }
+
+    /// Insert an entry which becomes available after `delay`, and return the old value of the
+    /// key.
+    pub fn insert(&mut self, key: K, value: V, delay: Duration) -> Option<V> {
+        let at = self.clock.now() + delay;
+        self.insert_at(key, value, at)
+    }
+
+    /// Insert an entry which becomes available at `at`, and return the old value of the key.
+    pub fn insert_at(&mut self, key: K, value: V, at: Instant) -> Option<V> {
+        let old = match self.inner {
+            Backend::Heap(ref mut heap) => heap.insert(key, at, value),
+            Backend::Wheel(ref mut wheel) => wheel.insert(key, at, value),
+        };
+        old.map(|(_, value)| value)
+    }
+
+    /// Change the instant an entry becomes available, and return the old one if the key was
+    /// contained.
+    pub fn reset_at(&mut self, key: &K, at: Instant) -> Option<Instant> {
+        match self.inner {
+            Backend::Heap(ref mut heap) => heap.reschedule(key, at),
+            Backend::Wheel(ref mut wheel) => wheel.reschedule(key, at),
+        }
+    }
+
+    /// Remove the entries available at `now`, in the order they became available.
+    ///
+    /// Entries are removed as the iterator is advanced, the ones it was not advanced over stay in
+    /// the queue.
+    pub fn poll_expired(&mut self, now: Instant) -> Expired<'_, K, V> {
+        Expired { queue: self, now }
+    }
+
+    /// Remove a value by specifying its key, which cancels it if it is not yet available.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let entry = match self.inner {
+            Backend::Heap(ref mut heap) => heap.remove_key(key),
+            Backend::Wheel(ref mut wheel) => wheel.remove_key(key),
+        };
+        entry.map(|(_, value)| value)
+    }
+
+    /// Remove an entry available at `now`.
+    fn pop_due(&mut self, now: Instant) -> Option<(K, V)> {
+        match self.inner {
+            Backend::Heap(ref mut heap) => heap.pop_due(now),
+            Backend::Wheel(ref mut wheel) => wheel.pop_due(now).map(|(key, _, value)| (key, value)),
+        }
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// Iterator removing the available entries of a [`Queue`](struct.Queue.html), created by
+/// [`Queue::poll_expired`](struct.Queue.html#method.poll_expired).
+pub struct Expired<'a, K: 'a, V: 'a> {
+    queue: &'a mut Queue<K, V>,
+    now: Instant,
+}
+
+impl<'a, K, V> Iterator for Expired<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.queue.pop_due(self.now)
+    }
+}
@@ -0,0 +1,902 @@
+//! Implementation of an addressable deque, that is a double-ended queue where it is possible to
+//! directly remove values by a key.
+//!
+//! This is the common base of the other queue types of this crate.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::mem::size_of;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+use mem::{self, MemSize};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+use storage::{self, Engine};
+
+/// The hasher used by the queues unless a different one is specified.
+#[cfg(feature = "std")]
+pub type DefaultHashBuilder = ::std::collections::hash_map::RandomState;
+/// The hasher used by the queues unless a different one is specified.
+#[cfg(not(feature = "std"))]
+pub type DefaultHashBuilder = ::hashbrown::DefaultHashBuilder;
+
+/// What happens when an entry is inserted for a key which is already contained.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// The old entry is removed and the new one inserted at the requested position.
+    #[default]
+    Requeue,
+    /// The value of the old entry is replaced, the entry keeps its position.
+    KeepPosition,
+    /// The new entry is dropped.
+    Ignore,
+}
+
+/// An addressable double-ended queue.
+///
+/// Entries can be added and removed at both ends, and removed directly by specifying their key.
+pub struct Deque<K, V, S = DefaultHashBuilder> {
+    pub(crate) raw: Engine<K, V, HashMap<K, u64, S>>,
+}
+
+impl<K, V> Deque<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a deque.
+    pub fn new() -> Self {
+        Deque::with_hasher(DefaultHashBuilder::default())
+    }
+
+    /// Create a new instance of a deque with space for at least `capacity` elements.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Deque::with_capacity_and_hasher(capacity, DefaultHashBuilder::default())
+    }
+}
+
+impl<K, V, S> Deque<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Create a new instance of a deque which uses the given hash builder to hash keys.
+    pub fn with_hasher(hasher: S) -> Self {
+        Deque::with_capacity_and_hasher(0, hasher)
+    }
+
+    /// Create a new instance of a deque with space for at least `capacity` elements, which uses
+    /// the given hash builder to hash keys.
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Deque {
+            raw: Engine::with_backends(
+                HashMap::with_capacity_and_hasher(capacity, hasher),
+                VecDeque::with_capacity(capacity),
+            ),
+        }
+    }
+
+    /// Create a new, empty deque with the same configuration and hash builder.
+    pub(crate) fn empty_like(&self) -> Self
+    where
+        S: Clone,
+    {
+        let mut deque = Deque::with_hasher(self.raw.index.hasher().clone());
+        deque.raw.duplicates = self.raw.duplicates;
+        deque.raw.compaction_threshold = self.raw.compaction_threshold;
+        deque
+    }
+
+    /// Returns the number of elements in the deque.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_front(3u8, 6u8);
+    /// assert_eq!(2, deque.len());
+    /// ```
+    pub fn len(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Returns true if the deque contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Returns the number of elements the deque can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.raw.nodes.capacity().min(self.raw.index.capacity())
+    }
+
+    /// Try to reserve space for at least `additional` more elements.
+    ///
+    /// In contrast to the push methods this reports allocation failures instead of aborting.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.raw
+            .nodes
+            .try_reserve(additional)
+            .map_err(|_| TryReserveError { _private: () })?;
+        self.raw
+            .index
+            .try_reserve(additional)
+            .map_err(|_| TryReserveError { _private: () })
+    }
+
+    /// Returns an estimate of the number of bytes used by the deque.
+    ///
+    /// This covers the slots of the deque (including those of removed elements which were not
+    /// reclaimed yet) and the buckets of the key index, but not heap memory owned by the keys and
+    /// values themselves. See [`deep_memory_usage`](#method.deep_memory_usage) for that.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.raw.nodes.capacity() * size_of::<Option<(K, V)>>()
+            + mem::hash_table_size::<(K, u64)>(self.raw.index.capacity())
+    }
+
+    /// Returns an estimate of the number of bytes used by the deque, including heap memory owned
+    /// by the keys and values.
+    pub fn deep_memory_usage(&self) -> usize
+    where
+        K: MemSize,
+        V: MemSize,
+    {
+        let heap: usize = self
+            .iter()
+            .map(|(key, value)| 2 * key.heap_size() + value.heap_size())
+            .sum();
+        self.memory_usage() + heap
+    }
+
+    /// Returns true if the deque contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.raw.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    ///
+    /// assert_eq!(Some(&4), deque.get(&2));
+    /// assert_eq!(None, deque.get(&3));
+    /// ```
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.raw.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// *deque.get_mut(&2).unwrap() += 1;
+    ///
+    /// assert_eq!(Some(&5), deque.get(&2));
+    /// ```
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.raw.get_mut(key)
+    }
+
+    /// Returns mutable references to the values of several entries at once.
+    ///
+    /// Returns `None` if any key has no entry, or if a key is given more than once.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back('a', 1u8);
+    /// deque.push_back('b', 2u8);
+    ///
+    /// if let Some([a, b]) = deque.get_many_mut([&'a', &'b']) {
+    ///     ::std::mem::swap(a, b);
+    /// }
+    /// assert_eq!(Some(&2), deque.get(&'a'));
+    /// assert_eq!(None, deque.get_many_mut([&'a', &'a']));
+    /// ```
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [&K; N]) -> Option<[&mut V; N]> {
+        let mut slots = [(0, 0); N];
+        for (i, key) in keys.iter().enumerate() {
+            slots[i] = (self.raw.slot(*self.raw.index.get(*key)?), i);
+        }
+        slots.sort_unstable();
+        if slots.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return None;
+        }
+
+        let mut values: [Option<&mut V>; N] = ::core::array::from_fn(|_| None);
+        let mut nodes = self.raw.nodes.iter_mut();
+        let mut next = 0;
+        for &(slot, i) in slots.iter() {
+            let node = nodes.nth(slot - next)?;
+            values[i] = node.as_mut().map(|node| &mut node.1);
+            next = slot + 1;
+        }
+        Some(values.map(|value| value.unwrap()))
+    }
+
+    /// Returns the entry at the front of the deque.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_back(3u8, 6u8);
+    ///
+    /// assert_eq!(Some((&2, &4)), deque.peek_front());
+    /// assert_eq!(Some((&3, &6)), deque.peek_back());
+    /// ```
+    pub fn peek_front(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    /// Returns the entry at the back of the deque.
+    pub fn peek_back(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    /// Returns the entry at the back of the deque, with a mutable reference to its value.
+    pub fn peek_back_mut(&mut self) -> Option<(&K, &mut V)> {
+        self.raw.peek_back_mut()
+    }
+
+    /// Returns the first entry, from front to back, for which the predicate returns true.
+    pub fn find<F>(&self, mut pred: F) -> Option<(&K, &V)>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.iter().find(|&(key, value)| pred(key, value))
+    }
+
+    /// Returns the position of the first entry, from front to back, for which the predicate
+    /// returns true. The front is at position `0`.
+    pub fn position_by<F>(&self, mut pred: F) -> Option<usize>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.iter().position(|(key, value)| pred(key, value))
+    }
+
+    /// Returns an iterator over the entries from front to back.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_back(3u8, 6u8);
+    /// deque.push_front(1u8, 2u8);
+    ///
+    /// let pairs: Vec<_> = deque.iter().collect();
+    /// assert_eq!(pairs, vec![(&1, &2), (&2, &4), (&3, &6)]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.raw.iter()
+    }
+
+    /// Insert an entry at the back of the deque.
+    ///
+    /// If there already is an entry for the key, it is handled according to the configured
+    /// [`DuplicateKeys`](enum.DuplicateKeys.html) policy. By default the old entry is removed.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_back(3u8, 6u8);
+    /// deque.push_back(2u8, 5u8);
+    ///
+    /// assert_eq!(deque.into_vec(), vec![(3, 6), (2, 5)]);
+    /// ```
+    pub fn push_back(&mut self, key: K, value: V) {
+        self.raw.push_back(key, value);
+    }
+
+    /// Insert an entry at the front of the deque.
+    ///
+    /// If there already is an entry for the key, it is handled according to the configured
+    /// [`DuplicateKeys`](enum.DuplicateKeys.html) policy. By default the old entry is removed.
+    pub fn push_front(&mut self, key: K, value: V) {
+        self.raw.push_front(key, value);
+    }
+
+    /// Insert an entry at the back of the deque, without allocating.
+    ///
+    /// If there is not enough spare capacity the pair is handed back instead. Space freed by
+    /// removed elements is reclaimed before giving up. An entry for a key which is already
+    /// contained always fits, as the old entry is removed or updated instead.
+    pub fn push_back_within_capacity(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if self.raw.index.contains_key(&key) {
+            if self.raw.duplicates == DuplicateKeys::Requeue {
+                self.requeue_back_within_capacity(&key, value);
+            } else {
+                self.push_back(key, value);
+            }
+            return Ok(());
+        }
+        if self.raw.index.len() >= self.raw.index.capacity() {
+            return Err((key, value));
+        }
+        if self.raw.nodes.len() == self.raw.nodes.capacity() {
+            if self.raw.nodes.len() == self.raw.index.len() {
+                return Err((key, value));
+            }
+            self.raw.compact();
+        }
+        self.push_back(key, value);
+        Ok(())
+    }
+
+    /// Replace the entry of a contained key by one at the back, without allocating.
+    ///
+    /// The node of the old entry is reclaimed first if there is no spare capacity.
+    pub(crate) fn requeue_back_within_capacity(&mut self, key: &K, value: V) -> Option<V> {
+        let full = self.raw.nodes.len() == self.raw.nodes.capacity();
+        self.raw.requeue_back(key, value, full)
+    }
+
+    /// Remove the entry at the front of the deque, and return it if there was one.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_back(3u8, 6u8);
+    ///
+    /// assert_eq!(Some((2, 4)), deque.pop_front());
+    /// assert_eq!(Some((3, 6)), deque.pop_front());
+    /// assert_eq!(None, deque.pop_front());
+    /// ```
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        self.raw.pop_front()
+    }
+
+    /// Remove the entry at the back of the deque, and return it if there was one.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_back(3u8, 6u8);
+    ///
+    /// assert_eq!(Some((3, 6)), deque.pop_back());
+    /// assert_eq!(Some((2, 4)), deque.pop_back());
+    /// assert_eq!(None, deque.pop_back());
+    /// ```
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        self.raw.pop_back()
+    }
+
+    /// Move the entry at the front of the deque to the back.
+    ///
+    /// Returns false if the deque is empty.
+    pub fn requeue_front(&mut self) -> bool {
+        self.raw.requeue_front()
+    }
+
+    /// Move the entry with the specified key to the back of the deque.
+    ///
+    /// Returns false if there is no entry for the key. This does not hash the key more than once.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_back(3u8, 6u8);
+    /// deque.push_back(4u8, 8u8);
+    ///
+    /// assert!(deque.move_to_back(&2));
+    /// assert!(deque.move_to_front(&4));
+    /// assert!(!deque.move_to_back(&5));
+    /// assert_eq!(deque.into_vec(), vec![(4, 8), (3, 6), (2, 4)]);
+    /// ```
+    pub fn move_to_back(&mut self, key: &K) -> bool {
+        self.raw.move_to_back(key)
+    }
+
+    /// Move the entry with the specified key to the front of the deque.
+    ///
+    /// Returns false if there is no entry for the key.
+    pub fn move_to_front(&mut self, key: &K) -> bool {
+        self.raw.move_to_front(key)
+    }
+
+    /// Replace the value of an entry without changing its position in the deque.
+    ///
+    /// Returns the old value, or `None` if there is no entry for the key, in which case nothing is
+    /// inserted.
+    pub fn replace(&mut self, key: &K, value: V) -> Option<V> {
+        self.raw.replace(key, value)
+    }
+
+    /// Change the key of an entry without changing its position in the deque.
+    pub fn rename_key(&mut self, old: &K, new: K) -> Result<(), RenameError> {
+        self.raw.rename_key(old, new)
+    }
+
+    /// Remove a value by specifying its key.
+    ///
+    /// ```
+    /// use addressable_queue::deque::Deque;
+    ///
+    /// let mut deque = Deque::new();
+    /// deque.push_back(2u8, 4u8);
+    /// deque.push_back(3u8, 6u8);
+    /// deque.push_back(4u8, 8u8);
+    ///
+    /// assert_eq!(Some(6), deque.remove_key(&3));
+    /// assert_eq!(None, deque.remove_key(&3));
+    /// assert_eq!(Some((2, 4)), deque.pop_front());
+    /// assert_eq!(Some((4, 8)), deque.pop_front());
+    /// ```
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.raw.remove_key(key)
+    }
+
+    /// Reclaim the slots of all removed elements now, instead of once there are more of them
+    /// than the compaction threshold allows.
+    pub fn compact(&mut self) {
+        self.raw.compact();
+    }
+
+    /// Convert the deque into a vec, where the first element is the front.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.raw.into_vec()
+    }
+}
+
+impl<K, V, S> Default for Deque<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Deque::with_hasher(S::default())
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a Deque<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for Deque<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Insert the entries at the back, in the order they are yielded.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        for (key, value) in entries {
+            self.push_back(key, value);
+        }
+    }
+}
+
+/// Iterator over the entries of a deque.
+///
+/// Created by [`Deque::iter`](struct.Deque.html#method.iter).
+pub type Iter<'a, K, V> = storage::Iter<'a, K, V, VecDeque<Option<(K, V)>>>;
+
+/// The error returned by [`Deque::try_reserve`](struct.Deque.html#method.try_reserve) if memory
+/// could not be allocated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TryReserveError {
+    _private: (),
+}
+
+impl fmt::Display for TryReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("memory allocation failed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for TryReserveError {}
+
+/// The error returned by [`Deque::rename_key`](struct.Deque.html#method.rename_key).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenameError {
+    /// There is no entry for the old key.
+    NotFound,
+    /// There already is an entry for the new key.
+    AlreadyExists,
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenameError::NotFound => f.write_str("no entry for the key"),
+            RenameError::AlreadyExists => f.write_str("an entry for the new key already exists"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for RenameError {}
+
+#[cfg(test)]
+#[test]
+fn push_back_within_capacity_reuses_removed_slots() {
+    let mut deque = Deque::with_capacity(4);
+    let mut n = 0u32;
+    while deque.push_back_within_capacity(n, n).is_ok() {
+        n += 1;
+    }
+    assert_eq!(deque.len() as u32, n);
+
+    assert_eq!(deque.remove_key(&1), Some(1));
+    assert_eq!(deque.push_back_within_capacity(n, n), Ok(()));
+    assert_eq!(deque.remove_key(&2), Some(2));
+    assert_eq!(deque.pop_front(), Some((0, 0)));
+    for k in 3..=n {
+        assert_eq!(deque.pop_front(), Some((k, k)));
+    }
+    assert!(deque.is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn push_back_within_capacity_replaces_contained_keys() {
+    let mut deque = Deque::with_capacity(4);
+    let mut n = 0u32;
+    while deque.push_back_within_capacity(n, n).is_ok() {
+        n += 1;
+    }
+
+    assert_eq!(deque.push_back_within_capacity(0, 1), Ok(()));
+    assert_eq!(deque.push_back_within_capacity(n - 1, n), Ok(()));
+    assert_eq!(deque.len() as u32, n);
+    assert_eq!(deque.pop_back(), Some((n - 1, n)));
+    assert_eq!(deque.pop_back(), Some((0, 1)));
+}
+
+#[cfg(test)]
+#[test]
+fn push_back_within_capacity_keeps_contained_keys_of_a_full_index() {
+    for capacity in 1..64 {
+        let mut deque = Deque::with_capacity(capacity);
+        let mut n = 0u32;
+        while deque.raw.index.len() < deque.raw.index.capacity() {
+            deque.push_back(n, n);
+            n += 1;
+        }
+
+        for k in 0..n {
+            assert_eq!(deque.push_back_within_capacity(k, k + 1), Ok(()));
+            assert_eq!(deque.len() as u32, n);
+        }
+        for k in 0..n {
+            assert_eq!(deque.pop_front(), Some((k, k + 1)));
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn removed_slots_are_compacted() {
+    let mut deque = Deque::new();
+    deque.push_back(0u32, 0u32);
+    for k in 1..1000 {
+        deque.push_back(k, k);
+        deque.remove_key(&k);
+        assert!(deque.raw.nodes.len() <= 2 * deque.len() + 1);
+    }
+    deque.push_back(1, 1);
+    deque.push_front(2, 2);
+    assert_eq!(deque.remove_key(&0), Some(0));
+    assert_eq!(deque.into_vec(), vec![(2, 2), (1, 1)]);
+}
+
+#[cfg(test)]
+#[test]
+fn duplicate_key_policies() {
+    let mut deque = Deque::new();
+    deque.raw.duplicates = DuplicateKeys::KeepPosition;
+    deque.push_back(1u8, 1u8);
+    deque.push_back(2, 2);
+    deque.push_back(1, 3);
+    assert_eq!(deque.len(), 2);
+    assert_eq!(deque.into_vec(), vec![(1, 3), (2, 2)]);
+
+    let mut deque = Deque::new();
+    deque.raw.duplicates = DuplicateKeys::Ignore;
+    deque.push_back(1u8, 1u8);
+    deque.push_front(1, 3);
+    assert_eq!(deque.into_vec(), vec![(1, 1)]);
+
+    let mut deque = Deque::new();
+    deque.push_back(1u8, 1u8);
+    deque.push_back(2, 2);
+    deque.push_front(2, 3);
+    assert_eq!(deque.len(), 2);
+    assert_eq!(deque.into_vec(), vec![(2, 3), (1, 1)]);
+}
+
+#[cfg(test)]
+#[test]
+fn repeated_moves_are_compacted() {
+    let mut deque = Deque::new();
+    for k in 0..4u32 {
+        deque.push_back(k, k);
+    }
+    for _ in 0..1000 {
+        assert!(deque.move_to_back(&0));
+        assert!(deque.move_to_front(&3));
+        assert!(deque.raw.nodes.len() <= 2 * deque.len() + 1);
+    }
+    assert_eq!(deque.into_vec(), vec![(3, 3), (1, 1), (2, 2), (0, 0)]);
+}
+
+/// The capacity to reserve for `len` entries read from untrusted input, at most 1 MiB.
+#[cfg(any(feature = "serde", feature = "borsh", feature = "bincode"))]
+pub(crate) fn cautious_capacity<T>(len: usize) -> usize {
+    len.min(1024 * 1024 / size_of::<T>().max(1))
+}
+
+#[cfg(feature = "serde")]
+mod serde_compat {
+    use super::Deque;
+    use core::hash::{BuildHasher, Hash};
+    use de::{DequeVisitor, OnDuplicate};
+    use serde::ser::SerializeSeq;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl<K, V, H> Serialize for Deque<K, V, H>
+    where
+        K: Serialize + Clone + Eq + Hash,
+        V: Serialize,
+        H: BuildHasher,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut seq = serializer.serialize_seq(Some(self.len()))?;
+
+            for pair in self.iter() {
+                seq.serialize_element(&pair)?;
+            }
+
+            seq.end()
+        }
+    }
+
+    /// The entries are inserted as they are read, without collecting them first, and a later
+    /// entry of a key replaces the earlier one. See the [`de`](../de/index.html) module for other
+    /// policies.
+    impl<'de, K, V, H> Deserialize<'de> for Deque<K, V, H>
+    where
+        K: Deserialize<'de> + Clone + Eq + Hash,
+        V: Deserialize<'de>,
+        H: BuildHasher + Default,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_seq(DequeVisitor::new(OnDuplicate::LastWins))
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn deserialize_reserves_the_hinted_capacity() {
+        use alloc::vec::Vec;
+        use serde_json;
+        // Unlike a string, a value tells the visitor how many entries follow.
+        let pairs: Vec<(u32, u32)> = (0..10_000).map(|k| (k, k)).collect();
+        let value = serde_json::to_value(&pairs).unwrap();
+        let deque: Deque<u32, u32> = Deque::deserialize(value).unwrap();
+
+        assert_eq!(10_000, deque.len());
+        assert!(deque.raw.nodes.capacity() < 10_000 * 5 / 4);
+        assert_eq!(deque.into_vec(), pairs);
+    }
+}
+
+#[cfg(feature = "borsh")]
+mod borsh_compat {
+    use super::{cautious_capacity, Deque};
+    use borsh::io::{Error, ErrorKind, Read, Result, Write};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use core::convert::TryFrom;
+    use core::hash::{BuildHasher, Hash};
+
+    /// Encoded like a `Vec<(K, V)>`, as a `u32` length followed by the pairs from head to tail.
+    impl<K, V, H> BorshSerialize for Deque<K, V, H>
+    where
+        K: BorshSerialize + Clone + Eq + Hash,
+        V: BorshSerialize,
+        H: BuildHasher,
+    {
+        fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+            let len = u32::try_from(self.len())
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "queue longer than u32::MAX"))?;
+            len.serialize(writer)?;
+            for (key, value) in self.iter() {
+                key.serialize(writer)?;
+                value.serialize(writer)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// A later entry of a key replaces the earlier one, like it does when deserializing with
+    /// serde.
+    impl<K, V, H> BorshDeserialize for Deque<K, V, H>
+    where
+        K: BorshDeserialize + Clone + Eq + Hash,
+        V: BorshDeserialize,
+        H: BuildHasher + Default,
+    {
+        fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+            let len = u32::deserialize_reader(reader)? as usize;
+            let capacity = cautious_capacity::<(K, V)>(len);
+            let mut deque = Deque::with_capacity_and_hasher(capacity, H::default());
+            for _ in 0..len {
+                let key = K::deserialize_reader(reader)?;
+                let value = V::deserialize_reader(reader)?;
+                deque.push_back(key, value);
+            }
+            Ok(deque)
+        }
+    }
+}
+
+#[cfg(feature = "bincode")]
+mod bincode_compat {
+    use super::{cautious_capacity, Deque};
+    use bincode::de::{BorrowDecoder, Decoder};
+    use bincode::enc::Encoder;
+    use bincode::error::{DecodeError, EncodeError};
+    use bincode::{BorrowDecode, Decode, Encode};
+    use core::convert::TryFrom;
+    use core::hash::{BuildHasher, Hash};
+    use core::mem::size_of;
+
+    /// Encoded like a `Vec<(K, V)>`, as a `u64` length followed by the pairs from head to tail.
+    impl<K, V, H> Encode for Deque<K, V, H>
+    where
+        K: Encode + Clone + Eq + Hash,
+        V: Encode,
+        H: BuildHasher,
+    {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            (self.len() as u64).encode(encoder)?;
+            for (key, value) in self.iter() {
+                key.encode(encoder)?;
+                value.encode(encoder)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// A later entry of a key replaces the earlier one, like it does when deserializing with
+    /// serde.
+    impl<Context, K, V, H> Decode<Context> for Deque<K, V, H>
+    where
+        K: Decode<Context> + Clone + Eq + Hash,
+        V: Decode<Context>,
+        H: BuildHasher + Default,
+    {
+        fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+            decode_entries(decoder, |decoder| {
+                Ok((K::decode(decoder)?, V::decode(decoder)?))
+            })
+        }
+    }
+
+    impl<'de, Context, K, V, H> BorrowDecode<'de, Context> for Deque<K, V, H>
+    where
+        K: BorrowDecode<'de, Context> + Clone + Eq + Hash,
+        V: BorrowDecode<'de, Context>,
+        H: BuildHasher + Default,
+    {
+        fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+            decoder: &mut D,
+        ) -> Result<Self, DecodeError> {
+            decode_entries(decoder, |decoder| {
+                Ok((K::borrow_decode(decoder)?, V::borrow_decode(decoder)?))
+            })
+        }
+    }
+
+    /// Read the length, then that many entries with `entry`, respecting the limit of the decoder.
+    fn decode_entries<D, K, V, H, F>(
+        decoder: &mut D,
+        mut entry: F,
+    ) -> Result<Deque<K, V, H>, DecodeError>
+    where
+        D: Decoder,
+        K: Clone + Eq + Hash,
+        H: BuildHasher + Default,
+        F: FnMut(&mut D) -> Result<(K, V), DecodeError>,
+    {
+        let len = u64::decode(decoder)?;
+        let len = usize::try_from(len).map_err(|_| DecodeError::OutsideUsizeRange(len))?;
+        decoder.claim_container_read::<(K, V)>(len)?;
+        let capacity = cautious_capacity::<(K, V)>(len);
+        let mut deque = Deque::with_capacity_and_hasher(capacity, H::default());
+        for _ in 0..len {
+            // The entry claims its own bytes as it is decoded.
+            decoder.unclaim_bytes_read(size_of::<(K, V)>());
+            let (key, value) = entry(decoder)?;
+            deque.push_back(key, value);
+        }
+        Ok(deque)
+    }
+}
+
+#[cfg(feature = "schemars")]
+mod schemars_compat {
+    use super::Deque;
+    use alloc::borrow::Cow;
+    use alloc::format;
+    use alloc::vec::Vec;
+    use schemars::{JsonSchema, Schema, SchemaGenerator};
+
+    /// Described like the serialized form, as an array of `[key, value]` pairs from head to tail.
+    impl<K, V, H> JsonSchema for Deque<K, V, H>
+    where
+        K: JsonSchema,
+        V: JsonSchema,
+    {
+        fn inline_schema() -> bool {
+            true
+        }
+
+        fn schema_name() -> Cow<'static, str> {
+            format!("Deque_of_{}_and_{}", K::schema_name(), V::schema_name()).into()
+        }
+
+        fn schema_id() -> Cow<'static, str> {
+            format!(
+                "addressable_queue::deque::Deque<{}, {}>",
+                K::schema_id(),
+                V::schema_id()
+            )
+            .into()
+        }
+
+        fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+            <Vec<(K, V)>>::json_schema(generator)
+        }
+    }
+}
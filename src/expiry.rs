@@ -0,0 +1,269 @@
+//! Expiration of entries after a time to live or time to idle.
+//!
+//! Without the `std` feature there is no clock, so the tracker does nothing.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use mem;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::Hash;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "std")]
+use time::{Clock, SystemClock};
+
+/// When an entry expires.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Expiry {
+    /// The entry expires once this much time passed since it was inserted.
+    pub ttl: Option<Duration>,
+    /// The entry expires once this much time passed since it was last accessed.
+    pub tti: Option<Duration>,
+}
+
+#[cfg(feature = "std")]
+impl Expiry {
+    fn is_expired(&self, stamp: &Stamp, now: Instant) -> bool {
+        let elapsed = |since: Instant| now.saturating_duration_since(since);
+        self.ttl.is_some_and(|ttl| elapsed(stamp.inserted) >= ttl)
+            || self.tti.is_some_and(|tti| elapsed(stamp.accessed) >= tti)
+    }
+}
+
+#[cfg(feature = "std")]
+struct Stamp {
+    inserted: Instant,
+    accessed: Instant,
+    /// Overrides the expiry of the tracker for this entry.
+    expiry: Option<Expiry>,
+}
+
+/// Tracks when the entries of a queue were inserted and last accessed.
+///
+/// Entries are only tracked once expiration was enabled, either for the whole queue or by
+/// inserting an entry with its own expiry.
+#[cfg(feature = "std")]
+pub(crate) struct Tracker<K> {
+    expiry: Expiry,
+    stamps: Option<HashMap<K, Stamp>>,
+    /// Shared with the trackers of queues split off this one.
+    clock: Arc<dyn Clock>,
+}
+
+#[cfg(feature = "std")]
+impl<K> Tracker<K> {
+    pub fn new() -> Self {
+        Tracker {
+            expiry: Expiry::default(),
+            stamps: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = Arc::from(clock);
+    }
+
+    /// Returns the current time of the clock.
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K> Tracker<K>
+where
+    K: Clone + Eq + Hash,
+{
+    pub fn expiry(&self) -> Expiry {
+        self.expiry
+    }
+
+    /// Returns a tracker without entries, with the same clock and expiry as this one.
+    pub fn empty_like(&self) -> Self {
+        Tracker {
+            expiry: self.expiry,
+            stamps: self.stamps.as_ref().map(|_| HashMap::new()),
+            clock: self.clock.clone(),
+        }
+    }
+
+    /// Returns true if any entry is tracked, so it may expire.
+    pub fn is_tracking(&self) -> bool {
+        self.stamps
+            .as_ref()
+            .is_some_and(|stamps| !stamps.is_empty())
+    }
+
+    /// Returns an estimate of the heap memory used by the tracker.
+    pub fn memory_usage(&self) -> usize {
+        self.stamps.as_ref().map_or(0, |stamps| {
+            mem::hash_table_size::<(K, Stamp)>(stamps.capacity())
+        })
+    }
+
+    /// Set the expiry of all entries without one of their own, and start tracking the given keys
+    /// if they are not tracked yet.
+    pub fn set_expiry<'a, I>(&mut self, expiry: Expiry, keys: I)
+    where
+        I: IntoIterator<Item = &'a K>,
+        K: 'a,
+    {
+        self.expiry = expiry;
+        self.enable(keys);
+    }
+
+    /// Start tracking an inserted entry.
+    pub fn stamp(&mut self, key: &K) {
+        self.stamp_with(key, None);
+    }
+
+    /// Start tracking an inserted entry, which has its own expiry.
+    pub fn stamp_with(&mut self, key: &K, expiry: Option<Expiry>) {
+        if let Some(ref mut stamps) = self.stamps {
+            let now = self.clock.now();
+            stamps.insert(
+                key.clone(),
+                Stamp {
+                    inserted: now,
+                    accessed: now,
+                    expiry,
+                },
+            );
+        }
+    }
+
+    /// Start tracking, using the given keys for the entries already contained.
+    pub fn enable<'a, I>(&mut self, keys: I)
+    where
+        I: IntoIterator<Item = &'a K>,
+        K: 'a,
+    {
+        if self.stamps.is_none() {
+            let now = self.clock.now();
+            let stamps = keys
+                .into_iter()
+                .map(|key| {
+                    let stamp = Stamp {
+                        inserted: now,
+                        accessed: now,
+                        expiry: None,
+                    };
+                    (key.clone(), stamp)
+                })
+                .collect();
+            self.stamps = Some(stamps);
+        }
+    }
+
+    pub fn touch(&mut self, key: &K) {
+        let now = self.clock.now();
+        if let Some(stamp) = self.stamps.as_mut().and_then(|stamps| stamps.get_mut(key)) {
+            stamp.accessed = now;
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        if let Some(ref mut stamps) = self.stamps {
+            stamps.remove(key);
+        }
+    }
+
+    /// Keep tracking an entry under another key.
+    pub fn rename(&mut self, old: &K, new: &K) {
+        if let Some(ref mut stamps) = self.stamps {
+            if let Some(stamp) = stamps.remove(old) {
+                stamps.insert(new.clone(), stamp);
+            }
+        }
+    }
+
+    /// Move the tracking of an entry to another tracker, which starts tracking if it did not.
+    pub fn transfer(&mut self, key: &K, to: &mut Tracker<K>) {
+        if let Some(stamp) = self.stamps.as_mut().and_then(|stamps| stamps.remove(key)) {
+            to.stamps
+                .get_or_insert_with(HashMap::new)
+                .insert(key.clone(), stamp);
+        }
+    }
+
+    /// Returns when a tracked entry was inserted.
+    pub fn inserted(&self, key: &K) -> Option<Instant> {
+        let stamps = self.stamps.as_ref()?;
+        stamps.get(key).map(|stamp| stamp.inserted)
+    }
+
+    pub fn is_expired(&self, key: &K) -> bool {
+        let stamp = match self.stamps.as_ref().and_then(|stamps| stamps.get(key)) {
+            Some(stamp) => stamp,
+            None => return false,
+        };
+        stamp
+            .expiry
+            .unwrap_or(self.expiry)
+            .is_expired(stamp, self.clock.now())
+    }
+}
+
+/// Tracks nothing, as there is no clock without the `std` feature.
+#[cfg(not(feature = "std"))]
+pub(crate) struct Tracker<K> {
+    _marker: PhantomData<K>,
+}
+
+#[cfg(not(feature = "std"))]
+impl<K> Tracker<K> {
+    pub fn new() -> Self {
+        Tracker {
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn empty_like(&self) -> Self {
+        Tracker::new()
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        false
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        0
+    }
+
+    pub fn stamp(&mut self, _key: &K) {}
+
+    pub fn touch(&mut self, _key: &K) {}
+
+    pub fn remove(&mut self, _key: &K) {}
+
+    pub fn rename(&mut self, _old: &K, _new: &K) {}
+
+    pub fn transfer(&mut self, _key: &K, _to: &mut Tracker<K>) {}
+
+    pub fn is_expired(&self, _key: &K) -> bool {
+        false
+    }
+}
@@ -0,0 +1,313 @@
+//! Implementation of an addressable fair queue, which takes turns between the FIFO queues of
+//! several classes.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::boxed::Box;
+use core::hash::Hash;
+use deque::Deque;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+use set;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// An addressable queue with one FIFO queue per class, which are removed from in turns.
+///
+/// The queue uses deficit round-robin: on its turn a class is credited its weight, which is `1`
+/// unless it was set otherwise, and entries are removed from it as long as the credit covers
+/// their cost. Every entry costs `1` unless the queue has a cost function. Credit left over is
+/// kept for the next turn while the class has entries. Entries are addressed by their class and
+/// key, so different classes can use the same keys.
+///
+/// ```
+/// use addressable_queue::fair::Queue;
+///
+/// let mut queue = Queue::new();
+/// for job in 0..3 {
+///     queue.insert("busy tenant", job, ());
+/// }
+/// queue.insert("quiet tenant", 0, ());
+///
+/// assert_eq!(Some(("busy tenant", 0, ())), queue.remove_head());
+/// assert_eq!(Some(("quiet tenant", 0, ())), queue.remove_head());
+/// assert_eq!(Some(("busy tenant", 1, ())), queue.remove_head());
+/// ```
+pub struct Queue<C, K, V> {
+    classes: HashMap<C, Class<K, V>>,
+    /// The classes with entries, in the order of their turns.
+    active: set::Queue<C>,
+    /// The weights which were set, also for classes without entries.
+    weights: HashMap<C, u32>,
+    /// Whether the class at the head of `active` was credited for its current turn.
+    credited: bool,
+    cost: Option<CostFn<V>>,
+    len: usize,
+}
+
+/// Computes the cost of an entry.
+type CostFn<V> = Box<dyn Fn(&V) -> u64 + Send + Sync>;
+
+struct Class<K, V> {
+    entries: Deque<K, V>,
+    credit: u64,
+}
+
+impl<C, K, V> Queue<C, K, V>
+where
+    C: Clone + Eq + Hash,
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            classes: HashMap::new(),
+            active: set::Queue::new(),
+            weights: HashMap::new(),
+            credited: false,
+            cost: None,
+            len: 0,
+        }
+    }
+
+    /// Create a new instance of a queue, where removing an entry uses up as much of the credit
+    /// of its class as `cost` computes for its value.
+    ///
+    /// This makes the share of every class proportional to its weight in terms of cost, such as
+    /// bytes, instead of entries.
+    ///
+    /// ```
+    /// use addressable_queue::fair::Queue;
+    ///
+    /// let mut queue = Queue::with_cost(|packet: &Vec<u8>| packet.len() as u64);
+    /// queue.set_weight("bulk", 1500);
+    /// queue.set_weight("voice", 1500);
+    /// queue.insert("bulk", 0, vec![0; 1500]);
+    /// queue.insert("bulk", 1, vec![0; 1500]);
+    /// for key in 0..3 {
+    ///     queue.insert("voice", key, vec![0; 500]);
+    /// }
+    ///
+    /// let classes: Vec<_> = (0..5).map(|_| queue.remove_head().unwrap().0).collect();
+    /// assert_eq!(classes, vec!["bulk", "voice", "voice", "voice", "bulk"]);
+    /// ```
+    pub fn with_cost<F>(cost: F) -> Self
+    where
+        F: Fn(&V) -> u64 + Send + Sync + 'static,
+    {
+        let mut queue = Queue::new();
+        queue.cost = Some(Box::new(cost));
+        queue
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements of a class.
+    pub fn len_of_class(&self, class: &C) -> usize {
+        self.classes
+            .get(class)
+            .map_or(0, |class| class.entries.len())
+    }
+
+    /// Returns the weight of a class.
+    pub fn weight(&self, class: &C) -> u32 {
+        self.weights.get(class).cloned().unwrap_or(1)
+    }
+
+    /// Set the weight of a class, which is the credit it receives per turn, so the number of its
+    /// entries removed per turn without a cost function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is zero.
+    ///
+    /// ```
+    /// use addressable_queue::fair::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.set_weight('a', 2);
+    /// for key in 0..4 {
+    ///     queue.insert('a', key, ());
+    ///     queue.insert('b', key, ());
+    /// }
+    ///
+    /// let classes: Vec<_> = (0..6).map(|_| queue.remove_head().unwrap().0).collect();
+    /// assert_eq!(classes, vec!['a', 'a', 'b', 'a', 'a', 'b']);
+    /// ```
+    pub fn set_weight(&mut self, class: C, weight: u32) {
+        assert!(weight > 0, "the weight of a class must be positive");
+        self.weights.insert(class, weight);
+    }
+
+    /// Returns true if the queue contains an element for the specified class and key.
+    pub fn contains_key(&self, class: &C, key: &K) -> bool {
+        self.classes
+            .get(class)
+            .is_some_and(|class| class.entries.contains_key(key))
+    }
+
+    /// Returns a reference to the value of the specified class and key.
+    pub fn get(&self, class: &C, key: &K) -> Option<&V> {
+        self.classes.get(class)?.entries.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the specified class and key.
+    pub fn get_mut(&mut self, class: &C, key: &K) -> Option<&mut V> {
+        self.classes.get_mut(class)?.entries.get_mut(key)
+    }
+
+    /// Insert an entry at the end of the queue of its class, and return the old value of the
+    /// class and key.
+    ///
+    /// A class which had no entries takes its turn after the other classes.
+    pub fn insert(&mut self, class: C, key: K, value: V) -> Option<V> {
+        let old = self.remove_key(&class, &key);
+        self.classes
+            .entry(class.clone())
+            .or_insert_with(|| Class {
+                entries: Deque::new(),
+                credit: 0,
+            })
+            .entries
+            .push_back(key, value);
+        self.active.insert(class);
+        self.len += 1;
+        old
+    }
+
+    /// Remove the next entry in turn, and return it if there was one.
+    ///
+    /// Once a whole round passed in which no class could afford the entry at its head, the
+    /// classes are credited for all the rounds until one of them can in a single step.
+    pub fn remove_head(&mut self) -> Option<(C, K, V)> {
+        let mut idle_turns = 0;
+        loop {
+            if !self.credited && idle_turns >= self.active.len() {
+                self.skip_idle_rounds();
+                idle_turns = 0;
+            }
+            let name = self.active.first()?.clone();
+            let weight = self.weight(&name);
+            let class = self.classes.get_mut(&name)?;
+            if !self.credited {
+                class.credit += u64::from(weight);
+                self.credited = true;
+            }
+            let cost = match self.cost {
+                Some(ref cost) => cost(class.entries.peek_front()?.1),
+                None => 1,
+            };
+            if class.credit >= cost {
+                class.credit -= cost;
+                let (key, value) = class.entries.pop_front()?;
+                self.len -= 1;
+                if class.entries.is_empty() {
+                    self.retire(&name);
+                }
+                return Some((name, key, value));
+            }
+            // The turn of the class is over.
+            self.active.remove_head();
+            self.active.insert(name);
+            self.credited = false;
+            idle_turns += 1;
+        }
+    }
+
+    /// Remove a value by specifying its class and key.
+    pub fn remove_key(&mut self, class: &C, key: &K) -> Option<V> {
+        let entries = &mut self.classes.get_mut(class)?.entries;
+        let value = entries.remove_key(key)?;
+        let emptied = entries.is_empty();
+        self.len -= 1;
+        if emptied {
+            self.retire(class);
+        }
+        Some(value)
+    }
+
+    /// Credit every class with entries for the rounds in which none of them can afford the entry
+    /// at its head, leaving the last of them to the regular turns.
+    fn skip_idle_rounds(&mut self) {
+        let Queue {
+            ref active,
+            ref mut classes,
+            ref weights,
+            ref cost,
+            ..
+        } = *self;
+        let weight = |name: &C| u64::from(weights.get(name).cloned().unwrap_or(1));
+        let rounds = active
+            .iter()
+            .filter_map(|name| {
+                let class = classes.get(name)?;
+                let head = class.entries.peek_front()?.1;
+                let cost = cost.as_ref().map_or(1, |cost| cost(head));
+                Some(cost.saturating_sub(class.credit).div_ceil(weight(name)))
+            })
+            .min()
+            .unwrap_or(0);
+        let skipped = rounds.saturating_sub(1);
+        for name in active.iter() {
+            if let Some(class) = classes.get_mut(name) {
+                class.credit = class
+                    .credit
+                    .saturating_add(skipped.saturating_mul(weight(name)));
+            }
+        }
+    }
+
+    /// Forget a class without entries, ending its turn.
+    fn retire(&mut self, class: &C) {
+        if self.active.first() == Some(class) {
+            self.credited = false;
+        }
+        self.active.remove(class);
+        self.classes.remove(class);
+    }
+}
+
+impl<C, K, V> Default for Queue<C, K, V>
+where
+    C: Clone + Eq + Hash,
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn expensive_entries_do_not_take_a_turn_per_credit() {
+    let mut queue = Queue::with_cost(|cost: &u64| *cost);
+    queue.set_weight('b', 2);
+    queue.insert('a', 0, u64::MAX / 2);
+    queue.insert('b', 0, u64::MAX / 2);
+    queue.insert('b', 1, 1);
+
+    assert_eq!(Some(('b', 0, u64::MAX / 2)), queue.remove_head());
+    assert_eq!(Some(('b', 1, 1)), queue.remove_head());
+    assert_eq!(Some(('a', 0, u64::MAX / 2)), queue.remove_head());
+    assert_eq!(None, queue.remove_head());
+}
@@ -15,22 +15,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{HashMap, VecDeque};
-use std::hash::Hash;
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{BuildHasher, Hash};
+use core::ops::{Deref, DerefMut};
+use deque::{self, DefaultHashBuilder, Deque, DuplicateKeys};
+#[cfg(feature = "std")]
+use expiry::Expiry;
+use expiry::Tracker;
+use mem::MemSize;
+use persist::Snapshot;
+use policy::{self, Fifo};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "std")]
+use time::Clock;
 
-struct Item<K, V> {
-    pub key: K,
-    pub val: Mutex<Option<V>>,
-}
+pub use deque::{RenameError, TryReserveError};
 
 /// An addressable FIFO queue.
 ///
 /// This data structure combines operations from a FIFO queue with the option to remove elements by
 /// directly specifying their key, in an efficient manner.
-pub struct Queue<K, V> {
-    items: VecDeque<Arc<Item<K, V>>>,
-    pointers: HashMap<K, Arc<Item<K, V>>>,
+///
+/// With the `std` feature entries can be inserted with a time to live, after which the queue
+/// treats them as absent. Expired entries are removed when they are encountered, or by
+/// [`purge_expired`](#method.purge_expired).
+pub struct Queue<K, V, S = DefaultHashBuilder> {
+    inner: policy::Queue<K, V, Fifo, S>,
+    expiration: Tracker<K>,
+    /// The number of entries `insert_within_capacity` inserts at most.
+    max_len: Option<usize>,
 }
 
 impl<K, V> Queue<K, V>
@@ -39,10 +56,19 @@ where
 {
     /// Create a new instance of a queue.
     pub fn new() -> Self {
-        Queue {
-            items: VecDeque::new(),
-            pointers: HashMap::new(),
-        }
+        Queue::from(Deque::new())
+    }
+
+    /// Create a new instance of a queue with space for at least `capacity` elements.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let queue: Queue<u8, u8> = Queue::with_capacity(10);
+    /// assert!(queue.capacity() >= 10);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        Queue::from(Deque::with_capacity(capacity))
     }
 
     /// Create a new instance of a queue, populated with the provided pairs.
@@ -64,9 +90,17 @@ where
         }
         queue
     }
+}
 
+impl<K, V, S> Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
     /// Returns the lenght of the queue.
     ///
+    /// Expired entries are not counted, which takes `O(n)` time once entries may expire.
+    ///
     /// ```
     /// use addressable_queue::fifo::Queue;
     ///
@@ -83,7 +117,107 @@ where
     /// assert_eq!(0, queue.len());
     /// ```
     pub fn len(&self) -> usize {
-        self.pointers.len()
+        if self.expiration.is_tracking() {
+            self.live().count()
+        } else {
+            self.inner.len()
+        }
+    }
+
+    /// Returns true if the queue contains no elements.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// assert!(queue.is_empty());
+    /// queue.insert(2u8, 4u8);
+    /// assert!(!queue.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.live().next().is_none()
+    }
+
+    /// Returns the number of elements the queue can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.inner.inner.capacity()
+    }
+
+    /// Try to reserve space for at least `additional` more elements.
+    ///
+    /// In contrast to `insert` this reports allocation failures instead of aborting.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue: Queue<u8, u8> = Queue::new();
+    /// queue.try_reserve(10).unwrap();
+    /// assert!(queue.capacity() >= 10);
+    /// ```
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.inner.inner.try_reserve(additional)
+    }
+
+    /// Returns the number of entries which [`insert_within_capacity`](#method.insert_within_capacity)
+    /// inserts at most, if it was limited.
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// Limit the number of entries which
+    /// [`insert_within_capacity`](#method.insert_within_capacity) inserts, in addition to the
+    /// capacity which was allocated, or remove the limit with `None`.
+    ///
+    /// Expired entries which were not removed yet count towards the limit. The other insertion
+    /// methods are not limited.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(10);
+    /// queue.set_max_len(Some(1));
+    ///
+    /// assert_eq!(Ok(()), queue.insert_within_capacity(2u8, 4u8));
+    /// assert_eq!(Err((3, 6)), queue.insert_within_capacity(3, 6));
+    /// assert_eq!(Ok(()), queue.insert_within_capacity(2, 5));
+    /// ```
+    pub fn set_max_len(&mut self, max_len: Option<usize>) {
+        self.max_len = max_len;
+    }
+
+    /// Returns an estimate of the number of bytes used by the queue.
+    ///
+    /// This covers the slots of the queue (including those of removed elements which were not
+    /// reclaimed yet), the buckets of the key index and the insertion times, but not heap memory
+    /// owned by the keys and values themselves. See [`deep_memory_usage`](#method.deep_memory_usage)
+    /// for that.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let empty: Queue<u32, u32> = Queue::new();
+    /// let queue = Queue::new_with(vec![(2u32, 4u32), (3, 6)]);
+    /// assert!(queue.memory_usage() > empty.memory_usage());
+    /// ```
+    pub fn memory_usage(&self) -> usize {
+        self.inner.inner.memory_usage() + self.expiration.memory_usage()
+    }
+
+    /// Returns an estimate of the number of bytes used by the queue, including heap memory owned
+    /// by the keys and values.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let queue = Queue::new_with(vec![(1u8, String::with_capacity(100))]);
+    /// assert!(queue.deep_memory_usage() >= queue.memory_usage() + 100);
+    /// ```
+    pub fn deep_memory_usage(&self) -> usize
+    where
+        K: MemSize,
+        V: MemSize,
+    {
+        self.inner.inner.deep_memory_usage() + self.expiration.memory_usage()
     }
 
     /// Returns true if the queue contains an element for the specified key.
@@ -102,11 +236,92 @@ where
     /// assert_eq!(queue.contains_key(&4), true);
     /// ```
     pub fn contains_key(&self, key: &K) -> bool {
-        self.pointers.contains_key(key)
+        self.inner.contains_key(key) && !self.expiration.is_expired(key)
+    }
+
+    /// Returns the first entry which did not expire, without removing it.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new_with(vec![(2u8, 4u8), (3, 6)]);
+    /// assert_eq!(Some((&2, &4)), queue.peek_head());
+    /// ```
+    pub fn peek_head(&self) -> Option<(&K, &V)> {
+        self.live().next()
+    }
+
+    /// Returns the key of the current head of the queue, skipping expired entries.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new_with(vec![(2u8, 4u8), (3, 6), (4, 8)]);
+    /// assert_eq!(Some(&2), queue.first_key());
+    /// queue.remove_key(&2);
+    /// assert_eq!(Some(&3), queue.first_key());
+    /// ```
+    pub fn first_key(&self) -> Option<&K> {
+        self.peek_head().map(|(key, _)| key)
+    }
+
+    /// Returns the key of the current tail of the queue, skipping expired entries.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new_with(vec![(2u8, 4u8), (3, 6), (4, 8)]);
+    /// assert_eq!(Some(&4), queue.last_key());
+    /// queue.remove_key(&4);
+    /// assert_eq!(Some(&3), queue.last_key());
+    /// ```
+    pub fn last_key(&self) -> Option<&K> {
+        self.live().next_back().map(|(key, _)| key)
+    }
+
+    /// Returns the first entry, from head to tail, for which the predicate returns true.
+    /// Expired entries are skipped.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let queue = Queue::new_with(vec![(2u8, 4u8), (3, 6), (4, 8)]);
+    ///
+    /// assert_eq!(Some((&3, &6)), queue.find(|_, v| *v > 4));
+    /// assert_eq!(None, queue.find(|k, _| *k > 4));
+    /// ```
+    pub fn find<F>(&self, mut pred: F) -> Option<(&K, &V)>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.live().find(|&(key, value)| pred(key, value))
+    }
+
+    /// Returns the position of the first entry, from head to tail, for which the predicate
+    /// returns true. The head is at position `0`, and expired entries are skipped without
+    /// being counted.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new_with(vec![(2u8, 4u8), (3, 6), (4, 8)]);
+    /// queue.remove_key(&2);
+    ///
+    /// assert_eq!(Some(1), queue.position_by(|k, _| *k == 4));
+    /// assert_eq!(None, queue.position_by(|k, _| *k == 2));
+    /// ```
+    pub fn position_by<F>(&self, mut pred: F) -> Option<usize>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.live().position(|(key, value)| pred(key, value))
     }
 
     /// Insert an entry at the end of the queue.
     ///
+    /// If the queue already contains an entry for the key, the old entry is removed unless a
+    /// different [`DuplicateKeys`](../deque/enum.DuplicateKeys.html) policy was configured.
+    ///
     /// ```
     /// use addressable_queue::fifo::Queue;
     ///
@@ -121,12 +336,86 @@ where
     /// assert_eq!(None, queue.remove_head());
     /// ```
     pub fn insert(&mut self, key: K, value: V) {
-        let arc = Arc::new(Item {
-            key: key.clone(),
-            val: Mutex::new(Some(value)),
-        });
-        self.items.push_back(Arc::clone(&arc));
-        self.pointers.insert(key, arc);
+        if self.admit(&key) {
+            self.expiration.stamp(&key);
+        }
+        self.inner.inner.push_back(key, value);
+    }
+
+    /// Insert an entry at the end of the queue, which expires once `ttl` passed.
+    ///
+    /// An expired entry is treated as absent: it is skipped when removing the head, and it is no
+    /// longer returned by [`remove_key`](#method.remove_key).
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    /// use std::time::Duration;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_with_ttl("timed out", (), Duration::from_secs(0));
+    /// queue.insert_with_ttl("pending", (), Duration::from_secs(60));
+    ///
+    /// assert!(!queue.contains_key(&"timed out"));
+    /// assert_eq!(None, queue.remove_key(&"timed out"));
+    /// assert_eq!(Some(("pending", ())), queue.remove_head());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) {
+        self.expiration
+            .enable(self.inner.inner.iter().map(|(key, _)| key));
+        if self.admit(&key) {
+            let expiry = Expiry {
+                ttl: Some(ttl),
+                tti: None,
+            };
+            self.expiration.stamp_with(&key, Some(expiry));
+        }
+        self.inner.inner.push_back(key, value);
+    }
+
+    /// Insert an entry at the end of the queue, without allocating.
+    ///
+    /// If there is not enough spare capacity, or the queue holds as many entries as
+    /// [`set_max_len`](#method.set_max_len) allows, the pair is handed back and the queue is not
+    /// modified. Space freed by removed elements is reclaimed before giving up. Replacing the entry
+    /// of a key which is already contained always succeeds.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// assert_eq!(Err((2u8, 4u8)), queue.insert_within_capacity(2, 4));
+    ///
+    /// queue.try_reserve(1).unwrap();
+    /// assert_eq!(Ok(()), queue.insert_within_capacity(2, 4));
+    /// assert_eq!(Some((2, 4)), queue.remove_head());
+    /// ```
+    pub fn insert_within_capacity(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if !self.inner.contains_key(&key) {
+            if self
+                .max_len
+                .is_some_and(|max_len| self.inner.len() >= max_len)
+            {
+                return Err((key, value));
+            }
+            self.inner.inner.push_back_within_capacity(key, value)?;
+            if let Some((key, _)) = self.inner.inner.peek_back() {
+                self.expiration.stamp(key);
+            }
+            return Ok(());
+        }
+        // The old entry makes room for the new one, so nothing below can fail. An expired entry
+        // is treated as absent, so the new one is placed at the back like a new key.
+        if self.expiration.is_expired(&key) {
+            self.expiration.remove(&key);
+            self.inner.inner.requeue_back_within_capacity(&key, value);
+            self.expiration.stamp(&key);
+            return Ok(());
+        }
+        if self.inner.inner.raw.duplicates != DuplicateKeys::Ignore {
+            self.expiration.stamp(&key);
+        }
+        self.inner.inner.push_back_within_capacity(key, value)
     }
 
     /// Insert an entry at the front of the queue.
@@ -148,12 +437,10 @@ where
     /// assert_eq!(None, queue.remove_head());
     /// ```
     pub fn insert_head(&mut self, key: K, value: V) {
-        let arc = Arc::new(Item {
-            key: key.clone(),
-            val: Mutex::new(Some(value)),
-        });
-        self.items.push_front(Arc::clone(&arc));
-        self.pointers.insert(key, arc);
+        if self.admit(&key) {
+            self.expiration.stamp(&key);
+        }
+        self.inner.inner.push_front(key, value);
     }
 
     /// Remove the current head of the queue, and return the value if there was one.
@@ -172,22 +459,77 @@ where
     /// assert_eq!(None, queue.remove_head());
     /// ```
     pub fn remove_head(&mut self) -> Option<(K, V)> {
-        while let Some(item) = self.items.pop_front() {
-            let is_some = item.val.lock().unwrap().is_some();
-            if is_some {
-                self.pointers.remove(&item.key);
-                let key = item.key.clone();
-                let value = Arc::try_unwrap(item)
-                    .ok()
-                    .unwrap()
-                    .val
-                    .into_inner()
-                    .unwrap()
-                    .unwrap();
+        loop {
+            let (key, value) = self.inner.remove_head()?;
+            let expired = self.expiration.is_expired(&key);
+            self.expiration.remove(&key);
+            if !expired {
                 return Some((key, value));
             }
         }
-        None
+    }
+
+    /// Remove the current head of the queue for processing.
+    ///
+    /// The returned guard gives mutable access to the value. Unless the guard is consumed with
+    /// [`HeadGuard::commit`](struct.HeadGuard.html#method.commit), the entry is put back at the
+    /// head of the queue when the guard is dropped, including when a panic unwinds.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new_with(vec![(2u8, 4u8), (3, 6)]);
+    ///
+    /// {
+    ///     let mut head = queue.checkout_head().unwrap();
+    ///     assert_eq!(*head.key(), 2);
+    ///     *head += 1;
+    ///     // Processing failed, the entry is put back on drop.
+    /// }
+    /// assert_eq!(Some(&2), queue.first_key());
+    ///
+    /// let head = queue.checkout_head().unwrap();
+    /// assert_eq!((2, 5), head.commit());
+    /// assert_eq!(Some(&3), queue.first_key());
+    /// ```
+    pub fn checkout_head(&mut self) -> Option<HeadGuard<'_, K, V, S>> {
+        loop {
+            let (key, value) = self.inner.remove_head()?;
+            if self.expiration.is_expired(&key) {
+                self.expiration.remove(&key);
+                continue;
+            }
+            let mut expiration = Tracker::new();
+            self.expiration.transfer(&key, &mut expiration);
+            return Some(HeadGuard {
+                queue: self,
+                pair: Some((key, value)),
+                expiration,
+            });
+        }
+    }
+
+    /// Move the current head of the queue to the end of the queue.
+    ///
+    /// Expired entries at the head are removed first. Returns false if the queue is empty.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new_with(vec![(2u8, 4u8), (3, 6), (4, 8)]);
+    ///
+    /// assert!(queue.requeue_head());
+    /// assert!(queue.requeue_head());
+    /// assert_eq!(Some(4), queue.remove_key(&2));
+    /// assert_eq!(queue.into_vec(), vec![(4, 8), (3, 6)]);
+    /// ```
+    pub fn requeue_head(&mut self) -> bool {
+        while let Some(key) = self.inner.inner.peek_front().map(|(key, _)| key.clone()) {
+            if !self.expire(&key) {
+                return self.inner.inner.requeue_front();
+            }
+        }
+        false
     }
 
     /// Remove the current tail of the queue, and return the value if there was one.
@@ -206,22 +548,62 @@ where
     /// assert_eq!(None, queue.remove_tail());
     /// ```
     pub fn remove_tail(&mut self) -> Option<(K, V)> {
-        while let Some(item) = self.items.pop_back() {
-            let is_some = item.val.lock().unwrap().is_some();
-            if is_some {
-                self.pointers.remove(&item.key);
-                let key = item.key.clone();
-                let value = Arc::try_unwrap(item)
-                    .ok()
-                    .unwrap()
-                    .val
-                    .into_inner()
-                    .unwrap()
-                    .unwrap();
+        loop {
+            let (key, value) = self.inner.remove_tail()?;
+            let expired = self.expiration.is_expired(&key);
+            self.expiration.remove(&key);
+            if !expired {
                 return Some((key, value));
             }
         }
-        None
+    }
+
+    /// Replace the value of an entry without changing its position in the queue.
+    ///
+    /// Returns the old value, or `None` if there is no entry for the key, in which case nothing is
+    /// inserted. An expired entry is removed instead of being replaced.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// assert_eq!(Some(4), queue.replace(&2, 5));
+    /// assert_eq!(None, queue.replace(&4, 8));
+    /// assert_eq!(Some((2, 5)), queue.remove_head());
+    /// assert_eq!(Some((3, 6)), queue.remove_head());
+    /// assert_eq!(None, queue.remove_head());
+    /// ```
+    pub fn replace(&mut self, key: &K, value: V) -> Option<V> {
+        if self.expire(key) {
+            return None;
+        }
+        self.inner.inner.replace(key, value)
+    }
+
+    /// Change the key of an entry without changing its position in the queue.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::{Queue, RenameError};
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// assert_eq!(Ok(()), queue.rename_key(&2, 5));
+    /// assert_eq!(Err(RenameError::NotFound), queue.rename_key(&2, 7));
+    /// assert_eq!(Err(RenameError::AlreadyExists), queue.rename_key(&5, 3));
+    ///
+    /// assert_eq!(Some((5, 4)), queue.remove_head());
+    /// assert_eq!(Some((3, 6)), queue.remove_head());
+    /// ```
+    pub fn rename_key(&mut self, old: &K, new: K) -> Result<(), RenameError> {
+        let renamed = new.clone();
+        self.inner.inner.rename_key(old, new)?;
+        self.expiration.rename(old, &renamed);
+        Ok(())
     }
 
     /// Remove a value by specifying its key.
@@ -241,81 +623,1337 @@ where
     /// assert_eq!(None, queue.remove_head());
     /// ```
     pub fn remove_key(&mut self, key: &K) -> Option<V> {
-        if let Some(item) = self.pointers.remove(key) {
-            let mut val = None;
-            ::std::mem::swap(&mut val, &mut *item.val.lock().unwrap());
-            return val;
+        if self.expire(key) {
+            return None;
         }
-        None
+        self.expiration.remove(key);
+        self.inner.remove_key(key)
     }
 
-    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    /// Remove all expired entries, and return them from head to tail.
+    #[cfg(feature = "std")]
+    pub fn purge_expired(&mut self) -> Vec<(K, V)> {
+        let expiration = &self.expiration;
+        let expired: Vec<K> = self
+            .inner
+            .inner
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| expiration.is_expired(key))
+            .cloned()
+            .collect();
+        self.remove_all(expired)
+    }
+
+    /// Reclaim the slots of all removed elements now, instead of once there are more of them
+    /// than the compaction threshold allows.
+    pub fn compact(&mut self) {
+        self.inner.inner.compact();
+    }
+
+    /// Remove all entries which were inserted `age` or longer ago, and return them from head to
+    /// tail.
+    ///
+    /// Insertion times are only recorded once an entry was inserted with
+    /// [`insert_with_ttl`](#method.insert_with_ttl) or
+    /// [`record_insertion_times`](#method.record_insertion_times) was called, entries inserted
+    /// before that are counted from then on.
     ///
     /// ```
     /// use addressable_queue::fifo::Queue;
+    /// use std::time::Duration;
     ///
     /// let mut queue = Queue::new();
-    /// queue.insert(2u8, 4u8);
-    /// queue.insert(3u8, 6u8);
-    /// queue.insert(4u8, 8u8);
+    /// queue.insert_with_ttl("request", 1u8, Duration::from_secs(60));
     ///
-    /// let vec = queue.into_vec();
-    /// assert_eq!(vec, vec![(2,4), (3,6), (4,8)]);
+    /// assert!(queue.expire_older_than(Duration::from_secs(60)).is_empty());
+    /// assert_eq!(queue.expire_older_than(Duration::from_secs(0)), vec![("request", 1)]);
     /// ```
-    pub fn into_vec(mut self) -> Vec<(K, V)> {
-        let mut vec = Vec::new();
-        while let Some(pair) = self.remove_head() {
-            vec.push(pair);
-        }
-        vec
+    #[cfg(feature = "std")]
+    pub fn expire_older_than(&mut self, age: Duration) -> Vec<(K, V)> {
+        let now = self.expiration.now();
+        let expiration = &self.expiration;
+        let expired: Vec<K> = self
+            .inner
+            .inner
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| {
+                expiration
+                    .inserted(key)
+                    .is_some_and(|inserted| now.saturating_duration_since(inserted) >= age)
+            })
+            .cloned()
+            .collect();
+        self.remove_all(expired)
     }
-}
 
-#[cfg(feature = "serde")]
-mod serde_compat {
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
-    use serde::ser::SerializeSeq;
-    use super::Queue;
-    use std::hash::Hash;
+    /// Start recording when entries are inserted, which is required to know their age.
+    ///
+    /// Entries which are already contained are counted as inserted now.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    /// use std::time::Duration;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.record_insertion_times();
+    /// queue.insert("job", ());
+    ///
+    /// assert!(queue.age_of_head().unwrap() < Duration::from_secs(60));
+    /// assert!(queue.mean_age().unwrap() <= queue.max_age().unwrap());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn record_insertion_times(&mut self) {
+        self.expiration
+            .enable(self.inner.inner.iter().map(|(key, _)| key));
+    }
 
-    impl<K, V> Serialize for Queue<K, V>
+    /// Read the current time from `clock` for expiring entries and their ages, instead of from
+    /// the system.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    /// use addressable_queue::time::MockClock;
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut queue = Queue::new();
+    /// queue.set_clock(clock.clone());
+    /// queue.insert_with_ttl("session", (), Duration::from_secs(60));
+    ///
+    /// clock.advance(Duration::from_secs(59));
+    /// assert!(queue.contains_key(&"session"));
+    /// clock.advance(Duration::from_secs(1));
+    /// assert!(!queue.contains_key(&"session"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn set_clock<C>(&mut self, clock: C)
     where
-        K: Serialize + Clone + Eq + Hash,
-        V: Serialize,
+        C: Clock + 'static,
     {
-        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-        where
-            S: Serializer,
-        {
-            let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        self.expiration.set_clock(Box::new(clock));
+    }
 
-            for item in &self.items {
-                let val = item.val.lock().unwrap();
-                if val.is_some() {
-                    let v = val.as_ref().unwrap();
-                    seq.serialize_element(&(&item.key, v))?;
-                }
-            }
+    /// Returns how long ago the entry of the key was inserted, if its insertion time was
+    /// recorded.
+    #[cfg(feature = "std")]
+    pub fn age_of(&self, key: &K) -> Option<Duration> {
+        self.age_at(key, self.expiration.now())
+    }
 
-            seq.end()
-        }
+    /// Returns how long ago the head of the queue was inserted, if its insertion time was
+    /// recorded.
+    #[cfg(feature = "std")]
+    pub fn age_of_head(&self) -> Option<Duration> {
+        let (key, _) = self.peek_head()?;
+        self.age_of(key)
+    }
+
+    /// Returns the age of the oldest entry whose insertion time was recorded.
+    ///
+    /// This is usually the head, but entries inserted at the head can be younger, so all entries
+    /// are visited.
+    #[cfg(feature = "std")]
+    pub fn max_age(&self) -> Option<Duration> {
+        let now = self.expiration.now();
+        self.inner
+            .inner
+            .iter()
+            .filter_map(|(key, _)| self.age_at(key, now))
+            .max()
+    }
+
+    /// Returns the mean age of the entries whose insertion time was recorded.
+    #[cfg(feature = "std")]
+    pub fn mean_age(&self) -> Option<Duration> {
+        let now = self.expiration.now();
+        let (count, total) = self
+            .inner
+            .inner
+            .iter()
+            .filter_map(|(key, _)| self.age_at(key, now))
+            .fold((0u32, Duration::ZERO), |(count, total), age| {
+                (count + 1, total + age)
+            });
+        total.checked_div(count)
     }
 
-    impl<'de, K, V> Deserialize<'de> for Queue<K, V>
+    /// Split the queue into two queues, the first one containing the entries for which the
+    /// predicate returns true and the second one the others.
+    ///
+    /// Both queues keep the relative order of their entries.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let queue = Queue::new_with(vec![(1u8, 1u8), (2, 4), (3, 9), (4, 16)]);
+    /// let (even, odd) = queue.partition(|k, _| k % 2 == 0);
+    ///
+    /// assert_eq!(even.into_vec(), vec![(2, 4), (4, 16)]);
+    /// assert_eq!(odd.into_vec(), vec![(1, 1), (3, 9)]);
+    /// ```
+    pub fn partition<F>(mut self, mut pred: F) -> (Queue<K, V, S>, Queue<K, V, S>)
     where
-        K: Deserialize<'de> + Clone + Eq + Hash,
-        V: Deserialize<'de>,
+        F: FnMut(&K, &V) -> bool,
+        S: Clone,
     {
-        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: Deserializer<'de>,
-        {
-            let data: Vec<(K, V)> = Vec::deserialize(deserializer)?;
-            Ok(Queue::new_with(data))
+        let mut left = Queue::from(self.inner.inner.empty_like());
+        let mut right = Queue::from(self.inner.inner.empty_like());
+        left.expiration = self.expiration.empty_like();
+        right.expiration = self.expiration.empty_like();
+        left.max_len = self.max_len;
+        right.max_len = self.max_len;
+        while let Some((key, value)) = self.inner.remove_head() {
+            if self.expiration.is_expired(&key) {
+                continue;
+            }
+            let side = if pred(&key, &value) {
+                &mut left
+            } else {
+                &mut right
+            };
+            self.expiration.transfer(&key, &mut side.expiration);
+            side.inner.inner.push_back(key, value);
         }
+        (left, right)
     }
 
-    #[cfg(test)]
+    /// Remove the entries from head to tail in batches of `chunk_size`.
+    ///
+    /// The last batch contains the remaining entries if there are less than `chunk_size` of
+    /// them. Entries are only removed as the iterator advances, entries which were not yielded
+    /// yet stay in the queue if the iterator is dropped early.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is `0`.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new_with(vec![(1u8, 1u8), (2, 4), (3, 9), (4, 16), (5, 25)]);
+    /// let mut chunks = queue.drain_chunks(2);
+    ///
+    /// assert_eq!(chunks.next(), Some(vec![(1, 1), (2, 4)]));
+    /// assert_eq!(chunks.next(), Some(vec![(3, 9), (4, 16)]));
+    /// assert_eq!(chunks.next(), Some(vec![(5, 25)]));
+    /// assert_eq!(chunks.next(), None);
+    /// ```
+    pub fn drain_chunks(&mut self, chunk_size: usize) -> DrainChunks<'_, K, V, S> {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+        DrainChunks {
+            queue: self,
+            chunk_size,
+        }
+    }
+
+    /// Remove entries from head to tail until the time budget is used up.
+    ///
+    /// The deadline is checked before each entry is yielded, so the time spent processing the
+    /// entries while iterating counts towards the budget. Entries which were not yielded yet stay
+    /// in the queue.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    /// use std::time::Duration;
+    ///
+    /// let mut queue = Queue::new_with(vec![(1u8, 1u8), (2, 4), (3, 9)]);
+    ///
+    /// assert_eq!(queue.drain_for(Duration::from_secs(0)).count(), 0);
+    /// assert_eq!(queue.len(), 3);
+    ///
+    /// for (key, value) in queue.drain_for(Duration::from_millis(2)) {
+    ///     assert_eq!(key * key, value);
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn drain_for(&mut self, budget: Duration) -> DrainFor<'_, K, V, S> {
+        DrainFor {
+            queue: self,
+            deadline: Instant::now() + budget,
+        }
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    ///
+    /// ```
+    /// use addressable_queue::fifo::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    /// queue.insert(4u8, 8u8);
+    ///
+    /// let vec = queue.into_vec();
+    /// assert_eq!(vec, vec![(2,4), (3,6), (4,8)]);
+    /// ```
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+
+    /// Returns a [`Snapshot`](../persist/struct.Snapshot.html) of the entries which are not
+    /// expired, from head to tail.
+    pub fn snapshot(&self) -> Snapshot<K, V>
+    where
+        V: Clone,
+    {
+        let entries = self
+            .live()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Snapshot::new(entries)
+    }
+
+    /// Create a queue holding the entries of a snapshot.
+    pub fn restore(snapshot: Snapshot<K, V>) -> Self
+    where
+        S: Default,
+    {
+        let mut queue = Queue::default();
+        queue.extend(snapshot.entries);
+        queue
+    }
+
+    /// Returns an iterator over the entries which did not expire, from head to tail.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.inner.inner.iter(),
+            expiration: &self.expiration,
+        }
+    }
+
+    /// Returns an iterator over the entries which did not expire, from head to tail.
+    pub(crate) fn live(&self) -> Iter<'_, K, V> {
+        self.iter()
+    }
+
+    /// Prepare inserting an entry, and return whether it will be stored.
+    fn admit(&mut self, key: &K) -> bool {
+        self.expire(key);
+        self.inner.inner.raw.duplicates != DuplicateKeys::Ignore || !self.inner.contains_key(key)
+    }
+
+    /// Remove the entry of the key if it expired, and return whether it did.
+    fn expire(&mut self, key: &K) -> bool {
+        if !self.expiration.is_expired(key) {
+            return false;
+        }
+        self.expiration.remove(key);
+        self.inner.remove_key(key);
+        true
+    }
+
+    /// Returns the age of an entry at `now`, if its insertion time was recorded.
+    #[cfg(feature = "std")]
+    fn age_at(&self, key: &K, now: Instant) -> Option<Duration> {
+        if self.expiration.is_expired(key) {
+            return None;
+        }
+        let inserted = self.expiration.inserted(key)?;
+        Some(now.saturating_duration_since(inserted))
+    }
+
+    /// Remove the entries of the given keys, and return them.
+    #[cfg(feature = "std")]
+    fn remove_all(&mut self, keys: Vec<K>) -> Vec<(K, V)> {
+        keys.into_iter()
+            .filter_map(|key| {
+                self.expiration.remove(&key);
+                let value = self.inner.remove_key(&key)?;
+                Some((key, value))
+            })
+            .collect()
+    }
+}
+
+/// Iterator over the entries of a queue which did not expire.
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: deque::Iter<'a, K, V>,
+    expiration: &'a Tracker<K>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let expiration = self.expiration;
+        self.inner.find(|&(key, _)| !expiration.is_expired(key))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.inner.size_hint() {
+            (len, upper) if !self.expiration.is_tracking() => (len, upper),
+            (_, upper) => (0, upper),
+        }
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let expiration = self.expiration;
+        self.inner.rfind(|&(key, _)| !expiration.is_expired(key))
+    }
+}
+
+/// The removed head of a queue, which is put back unless it is committed.
+///
+/// Created by [`Queue::checkout_head`](struct.Queue.html#method.checkout_head).
+pub struct HeadGuard<'a, K: 'a, V: 'a, S: 'a = DefaultHashBuilder>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    queue: &'a mut Queue<K, V, S>,
+    pair: Option<(K, V)>,
+    /// Holds the insertion time and expiry of the entry while it is checked out.
+    expiration: Tracker<K>,
+}
+
+impl<'a, K, V, S> HeadGuard<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &K {
+        &self.pair.as_ref().unwrap().0
+    }
+
+    /// Finish processing, removing the entry from the queue for good.
+    pub fn commit(mut self) -> (K, V) {
+        self.pair.take().unwrap()
+    }
+}
+
+impl<'a, K, V, S> Deref for HeadGuard<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.pair.as_ref().unwrap().1
+    }
+}
+
+impl<'a, K, V, S> DerefMut for HeadGuard<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.pair.as_mut().unwrap().1
+    }
+}
+
+impl<'a, K, V, S> Drop for HeadGuard<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    fn drop(&mut self) {
+        if let Some((key, value)) = self.pair.take() {
+            self.expiration.transfer(&key, &mut self.queue.expiration);
+            self.queue.inner.inner.push_front(key, value);
+        }
+    }
+}
+
+/// Iterator removing batches of entries from a queue.
+///
+/// Created by [`Queue::drain_chunks`](struct.Queue.html#method.drain_chunks).
+pub struct DrainChunks<'a, K: 'a, V: 'a, S: 'a = DefaultHashBuilder> {
+    queue: &'a mut Queue<K, V, S>,
+    chunk_size: usize,
+}
+
+impl<'a, K, V, S> Iterator for DrainChunks<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = Vec<(K, V)>;
+
+    fn next(&mut self) -> Option<Vec<(K, V)>> {
+        let mut chunk = Vec::with_capacity(self.chunk_size.min(self.queue.len()));
+        while chunk.len() < self.chunk_size {
+            match self.queue.remove_head() {
+                Some(pair) => chunk.push(pair),
+                None => break,
+            }
+        }
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+
+    /// Entries can expire before they are reached, so there is no lower bound.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.queue.len().div_ceil(self.chunk_size)))
+    }
+}
+
+/// Iterator removing entries from a queue until a deadline has passed.
+///
+/// Created by [`Queue::drain_for`](struct.Queue.html#method.drain_for).
+#[cfg(feature = "std")]
+pub struct DrainFor<'a, K: 'a, V: 'a, S: 'a = DefaultHashBuilder> {
+    queue: &'a mut Queue<K, V, S>,
+    deadline: Instant,
+}
+
+#[cfg(feature = "std")]
+impl<'a, K, V, S> Iterator for DrainFor<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        if Instant::now() >= self.deadline {
+            return None;
+        }
+        self.queue.remove_head()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.queue.len()))
+    }
+}
+
+impl<K, V, S> Default for Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Queue::from(Deque::default())
+    }
+}
+
+/// Formats the entries which did not expire, from head to tail.
+impl<K, V, S> fmt::Debug for Queue<K, V, S>
+where
+    K: fmt::Debug + Clone + Eq + Hash,
+    V: fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> From<Deque<K, V, S>> for Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    fn from(inner: Deque<K, V, S>) -> Self {
+        Queue {
+            inner: policy::Queue::from_parts(inner, Fifo),
+            expiration: Tracker::new(),
+            max_len: None,
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Insert the entries at the end, in the order they are yielded.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// An addressable FIFO queue holding at most a fixed number of elements.
+///
+/// Inserting into a full queue evicts the current head, so the queue works like a ring buffer
+/// which keeps the most recently inserted entries.
+pub struct BoundedQueue<K, V, S = DefaultHashBuilder> {
+    inner: Deque<K, V, S>,
+    bound: usize,
+    overflow: Overflow,
+}
+
+impl<K, V> BoundedQueue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue which holds at most `bound` elements.
+    pub fn new(bound: usize) -> Self {
+        BoundedQueue::from_parts(Deque::new(), bound, Overflow::default())
+    }
+}
+
+impl<K, V, S> BoundedQueue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    pub(crate) fn from_parts(inner: Deque<K, V, S>, bound: usize, overflow: Overflow) -> Self {
+        BoundedQueue {
+            inner,
+            bound,
+            overflow,
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if inserting another element evicts the head.
+    pub fn is_full(&self) -> bool {
+        self.inner.len() >= self.bound
+    }
+
+    /// Returns the maximum number of elements in the queue.
+    pub fn bound(&self) -> usize {
+        self.bound
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Insert an entry at the end of the queue.
+    ///
+    /// If the queue is full its head is removed and returned, or if the queue was configured to
+    /// reject new entries when full, the pair is handed back.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::BoundedQueue;
+    ///
+    /// let mut queue = BoundedQueue::new(2);
+    /// assert_eq!(None, queue.insert(2u8, 4u8));
+    /// assert_eq!(None, queue.insert(3u8, 6u8));
+    /// assert_eq!(Some((2, 4)), queue.insert(4u8, 8u8));
+    ///
+    /// assert_eq!(queue.into_vec(), vec![(3, 6), (4, 8)]);
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        match self.overflow {
+            Overflow::EvictHead => self.push_evicting(key, value),
+            Overflow::Reject => self.try_insert(key, value).err().map(Full::into_inner),
+        }
+    }
+
+    /// Insert an entry at the end of the queue, or hand the pair back in the error if the queue
+    /// is full, regardless of the configured overflow behavior.
+    ///
+    /// Replacing the value of a key in the queue always succeeds.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::{BoundedQueue, Full};
+    ///
+    /// let mut queue = BoundedQueue::new(1);
+    /// assert_eq!(Ok(()), queue.try_insert(2u8, 4u8));
+    /// assert_eq!(Err(Full(3, 6)), queue.try_insert(3u8, 6u8));
+    /// assert_eq!(Ok(()), queue.try_insert(2u8, 5u8));
+    /// ```
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<(), Full<K, V>> {
+        if self.is_full() && !self.inner.contains_key(&key) {
+            return Err(Full(key, value));
+        }
+        self.inner.push_back(key, value);
+        Ok(())
+    }
+
+    /// Insert an entry at the end of the queue, evicting and returning the head if the queue is
+    /// full, regardless of the configured overflow behavior.
+    pub fn push_evicting(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.bound == 0 {
+            return Some((key, value));
+        }
+        let evicted = if self.is_full() && !self.inner.contains_key(&key) {
+            self.inner.pop_front()
+        } else {
+            None
+        };
+        self.inner.push_back(key, value);
+        evicted
+    }
+
+    /// Remove the current head of the queue, and return the value if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        self.inner.pop_front()
+    }
+
+    /// Remove the current tail of the queue, and return the value if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        self.inner.pop_back()
+    }
+
+    /// Remove a value by specifying its key.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::BoundedQueue;
+    ///
+    /// let mut queue = BoundedQueue::new(2);
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// assert_eq!(Some(4), queue.remove_key(&2));
+    /// assert_eq!(None, queue.insert(4u8, 8u8));
+    /// ```
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Returns an iterator over the entries from head to tail.
+    pub fn iter(&self) -> deque::Iter<'_, K, V> {
+        self.inner.iter()
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+
+    /// Returns a [`Snapshot`](../persist/struct.Snapshot.html) of the entries from head to tail,
+    /// the bound and the overflow policy.
+    pub fn snapshot(&self) -> Snapshot<K, V>
+    where
+        V: Clone,
+    {
+        let entries = self
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Snapshot {
+            capacity: Some(self.bound),
+            overflow: Some(self.overflow),
+            ..Snapshot::new(entries)
+        }
+    }
+
+    /// Create a queue from a snapshot.
+    ///
+    /// A snapshot without a capacity restores a queue without a practical bound. Entries beyond
+    /// the bound are inserted like any other, so they evict or are rejected by the overflow
+    /// policy.
+    pub fn restore(snapshot: Snapshot<K, V>) -> Self
+    where
+        S: Default,
+    {
+        let bound = snapshot.capacity.unwrap_or(usize::MAX);
+        let overflow = snapshot.overflow.unwrap_or_default();
+        let mut queue = BoundedQueue::from_parts(Deque::default(), bound, overflow);
+        for (key, value) in snapshot.entries {
+            queue.insert(key, value);
+        }
+        queue
+    }
+}
+
+/// What a full [`BoundedQueue`](struct.BoundedQueue.html) does when another entry is inserted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// The head of the queue is evicted to make room.
+    #[default]
+    EvictHead,
+    /// The new entry is rejected.
+    Reject,
+}
+
+/// The error returned when inserting into a full queue, which hands back the rejected pair.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Full<K, V>(pub K, pub V);
+
+impl<K, V> Full<K, V> {
+    /// Returns the rejected pair.
+    pub fn into_inner(self) -> (K, V) {
+        (self.0, self.1)
+    }
+}
+
+impl<K, V> fmt::Debug for Full<K, V>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Full").field(&self.0).finish_non_exhaustive()
+    }
+}
+
+impl<K, V> fmt::Display for Full<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the queue is full")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V> ::std::error::Error for Full<K, V> where K: fmt::Debug {}
+
+/// An addressable cache holding at most a fixed number of entries, which evicts the oldest entry
+/// when it is full.
+///
+/// Accessing an entry does not change its position, and neither does replacing its value.
+///
+/// ```
+/// use addressable_queue::fifo::Cache;
+///
+/// let mut cache = Cache::new(2);
+/// cache.insert('a', 1);
+/// cache.insert('b', 2);
+/// assert_eq!(Some(&1), cache.get(&'a'));
+///
+/// assert_eq!(Some(('a', 1)), cache.insert('c', 3));
+/// assert_eq!(Some(('b', 2)), cache.insert('b', 4));
+/// assert_eq!(cache.into_vec(), vec![('b', 4), ('c', 3)]);
+/// ```
+pub struct Cache<K, V, S = DefaultHashBuilder> {
+    inner: Deque<K, V, S>,
+    capacity: usize,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a cache which holds at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Cache {
+            inner: Deque::with_capacity(capacity),
+            capacity,
+        }
+    }
+}
+
+impl<K, V, S> Cache<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the maximum number of entries in the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns true if the cache contains an entry for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its value is replaced in place and the old entry is
+    /// returned. Otherwise, if the cache is full, the oldest entry is evicted and returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        if let Some(slot) = self.inner.get_mut(&key) {
+            let old = ::core::mem::replace(slot, value);
+            return Some((key, old));
+        }
+        let evicted = if self.inner.len() >= self.capacity {
+            self.inner.pop_front()
+        } else {
+            None
+        };
+        self.inner.push_back(key, value);
+        evicted
+    }
+
+    /// Remove the oldest entry, and return it if there was one.
+    pub fn remove_oldest(&mut self) -> Option<(K, V)> {
+        self.inner.pop_front()
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Returns an iterator over the entries from the oldest to the newest.
+    pub fn iter(&self) -> deque::Iter<'_, K, V> {
+        self.inner.iter()
+    }
+
+    /// Convert the cache into a vec, where the first element is the oldest entry.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+}
+
+/// An addressable FIFO queue with a fixed capacity and all storage inline.
+///
+/// In contrast to [`Queue`](struct.Queue.html) this never allocates, which makes it usable in
+/// environments where allocation after startup is not possible. Keys are looked up with a linear
+/// scan, so it is intended for small values of `N`.
+pub struct ArrayQueue<K, V, const N: usize> {
+    slots: [Option<(K, V)>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<K, V, const N: usize> ArrayQueue<K, V, N>
+where
+    K: Eq,
+{
+    /// Create a new, empty instance of the queue.
+    pub fn new() -> Self {
+        ArrayQueue {
+            slots: core::array::from_fn(|_| None),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if no further elements can be inserted.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::ArrayQueue;
+    ///
+    /// let mut queue: ArrayQueue<u8, u8, 2> = ArrayQueue::new();
+    /// queue.insert(2, 4).unwrap();
+    /// assert!(!queue.is_full());
+    /// queue.insert(3, 6).unwrap();
+    /// assert!(queue.is_full());
+    /// ```
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Returns the maximum number of elements the queue can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.find_index(key).is_some()
+    }
+
+    /// Insert an entry at the end of the queue.
+    ///
+    /// If there already is an entry for the key it is removed first, so it always fits. Otherwise
+    /// the pair is handed back in the error if the queue is full.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::ArrayQueue;
+    ///
+    /// let mut queue: ArrayQueue<u8, u8, 2> = ArrayQueue::new();
+    /// assert_eq!(Ok(()), queue.insert(2, 4));
+    /// assert_eq!(Ok(()), queue.insert(3, 6));
+    /// assert_eq!(Err((4, 8)), queue.insert(4, 8));
+    ///
+    /// assert_eq!(Some((2, 4)), queue.remove_head());
+    /// assert_eq!(Ok(()), queue.insert(4, 8));
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if !self.make_room(&key) {
+            return Err((key, value));
+        }
+        let index = self.slot(self.len);
+        self.slots[index] = Some((key, value));
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Insert an entry at the front of the queue.
+    ///
+    /// If there already is an entry for the key it is removed first, so it always fits. Otherwise
+    /// the pair is handed back in the error if the queue is full.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::ArrayQueue;
+    ///
+    /// let mut queue: ArrayQueue<u8, u8, 2> = ArrayQueue::new();
+    /// assert_eq!(Ok(()), queue.insert_head(2, 4));
+    /// assert_eq!(Ok(()), queue.insert_head(3, 6));
+    /// assert_eq!(Err((4, 8)), queue.insert_head(4, 8));
+    ///
+    /// assert_eq!(Some((3, 6)), queue.remove_head());
+    /// assert_eq!(Some((2, 4)), queue.remove_head());
+    /// ```
+    pub fn insert_head(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if !self.make_room(&key) {
+            return Err((key, value));
+        }
+        self.head = (self.head + N - 1) % N;
+        self.slots[self.head] = Some((key, value));
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove the current head of the queue, and return the value if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        if self.is_empty() {
+            return None;
+        }
+        let pair = self.slots[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        pair
+    }
+
+    /// Remove the current tail of the queue, and return the value if there was one.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::ArrayQueue;
+    ///
+    /// let mut queue: ArrayQueue<u8, u8, 4> = ArrayQueue::new();
+    /// queue.insert(2, 4).unwrap();
+    /// queue.insert(3, 6).unwrap();
+    ///
+    /// assert_eq!(Some((3, 6)), queue.remove_tail());
+    /// assert_eq!(Some((2, 4)), queue.remove_tail());
+    /// assert_eq!(None, queue.remove_tail());
+    /// ```
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        if self.is_empty() {
+            return None;
+        }
+        let index = self.slot(self.len - 1);
+        self.len -= 1;
+        self.slots[index].take()
+    }
+
+    /// Remove a value by specifying its key.
+    ///
+    /// The remaining elements keep their order.
+    ///
+    /// ```
+    /// use addressable_queue::fifo::ArrayQueue;
+    ///
+    /// let mut queue: ArrayQueue<u8, u8, 4> = ArrayQueue::new();
+    /// queue.insert(2, 4).unwrap();
+    /// queue.insert(3, 6).unwrap();
+    /// queue.insert(4, 8).unwrap();
+    ///
+    /// assert_eq!(Some(6), queue.remove_key(&3));
+    /// assert_eq!(None, queue.remove_key(&3));
+    /// assert_eq!(Some((2, 4)), queue.remove_head());
+    /// assert_eq!(Some((4, 8)), queue.remove_head());
+    /// ```
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let i = self.find_index(key)?;
+        self.remove_at(i)
+    }
+
+    /// Remove the old entry of the key, and return whether there is room for the new one.
+    fn make_room(&mut self, key: &K) -> bool {
+        match self.find_index(key) {
+            Some(i) => {
+                self.remove_at(i);
+                true
+            }
+            None => !self.is_full(),
+        }
+    }
+
+    fn remove_at(&mut self, i: usize) -> Option<V> {
+        let pair = self.slots[self.slot(i)].take();
+        // Close the gap by shifting the shorter side of the ring.
+        if i < self.len / 2 {
+            for j in (0..i).rev() {
+                let (from, to) = (self.slot(j), self.slot(j + 1));
+                self.slots.swap(from, to);
+            }
+            self.head = (self.head + 1) % N;
+        } else {
+            for j in i + 1..self.len {
+                let (from, to) = (self.slot(j), self.slot(j - 1));
+                self.slots.swap(from, to);
+            }
+        }
+        self.len -= 1;
+        pair.map(|(_, value)| value)
+    }
+
+    fn slot(&self, i: usize) -> usize {
+        (self.head + i) % N
+    }
+
+    fn find_index(&self, key: &K) -> Option<usize> {
+        (0..self.len).find(|&i| match self.slots[self.slot(i)] {
+            Some((ref k, _)) => k == key,
+            None => false,
+        })
+    }
+}
+
+impl<K, V, const N: usize> Default for ArrayQueue<K, V, N>
+where
+    K: Eq,
+{
+    fn default() -> Self {
+        ArrayQueue::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn head_guard_reinserts_on_panic() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    let mut queue = Queue::new_with(vec![(2u8, 4u8), (3, 6)]);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let _head = queue.checkout_head().unwrap();
+        panic!("processing failed");
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(queue.into_vec(), vec![(2, 4), (3, 6)]);
+}
+
+#[cfg(all(test, feature = "std"))]
+fn expiring_queue() -> (Queue<u8, u8>, ::time::MockClock) {
+    let clock = ::time::MockClock::new();
+    let mut queue = Queue::new();
+    queue.set_clock(clock.clone());
+    queue.insert_with_ttl(1, 1, Duration::from_secs(10));
+    queue.insert(2, 4);
+    queue.insert(3, 9);
+    queue.insert_with_ttl(4, 16, Duration::from_secs(10));
+    clock.advance(Duration::from_secs(10));
+    (queue, clock)
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn lookups_skip_expired_entries() {
+    let (queue, _clock) = expiring_queue();
+
+    assert_eq!(Some(&2), queue.first_key());
+    assert_eq!(Some(&3), queue.last_key());
+    assert_eq!(Some((&2, &4)), queue.find(|_, _| true));
+    assert_eq!(None, queue.find(|key, _| *key == 4));
+    assert_eq!(Some(0), queue.position_by(|key, _| *key == 2));
+    assert_eq!(None, queue.position_by(|key, _| *key == 1));
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn modifications_skip_expired_entries() {
+    let (mut queue, _clock) = expiring_queue();
+
+    assert_eq!(None, queue.replace(&1, 2));
+    assert!(!queue.contains_key(&1));
+    assert!(queue.requeue_head());
+    assert_eq!(Some(&3), queue.first_key());
+
+    let (mut queue, _clock) = expiring_queue();
+    let mut chunks = queue.drain_chunks(3);
+    assert_eq!((0, Some(1)), chunks.size_hint());
+    assert_eq!(Some(vec![(2, 4), (3, 9)]), chunks.next());
+    assert_eq!(None, chunks.next());
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn insert_within_capacity_only_modifies_on_success() {
+    let (mut queue, _clock) = expiring_queue();
+    queue.set_max_len(Some(4));
+
+    assert_eq!(Err((5, 25)), queue.insert_within_capacity(5, 25));
+    assert_eq!((2, 4), (queue.len(), queue.inner.len()));
+    assert_eq!(Ok(()), queue.insert_within_capacity(1, 2));
+    assert_eq!(Ok(()), queue.insert_within_capacity(2, 5));
+    assert_eq!((3, 4), (queue.len(), queue.inner.len()));
+    assert_eq!(Some((&1, &2)), queue.find(|key, _| *key == 1));
+    assert_eq!(Some((&2, &5)), queue.live().next_back());
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn partitions_keep_the_clock() {
+    let (mut queue, clock) = expiring_queue();
+    queue.insert_with_ttl(5, 25, Duration::from_secs(10));
+    let (even, odd) = queue.partition(|key, _| key % 2 == 0);
+    assert_eq!((1, 2), (even.len(), odd.len()));
+
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(vec![(&3, &9)], odd.iter().collect::<Vec<_>>());
+    assert_eq!(vec![(&2, &4)], even.iter().collect::<Vec<_>>());
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn insert_within_capacity_replaces_expired_entries_of_a_full_index() {
+    let clock = ::time::MockClock::new();
+    let mut queue = Queue::with_capacity(28);
+    queue.set_clock(clock.clone());
+    queue.insert_with_ttl(0u32, 0u32, Duration::from_secs(10));
+    let mut n = 1;
+    while queue.inner.inner.raw.index.len() < queue.inner.inner.raw.index.capacity() {
+        queue.insert(n, n);
+        n += 1;
+    }
+    clock.advance(Duration::from_secs(10));
+
+    assert_eq!(Ok(()), queue.insert_within_capacity(0, 1));
+    assert_eq!(Ok(()), queue.insert_within_capacity(1, 2));
+    assert_eq!(n as usize, queue.len());
+    assert_eq!(Some((&1, &2)), queue.live().next_back());
+    assert_eq!(Some(1), queue.remove_key(&0));
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn head_guard_keeps_the_expiry() {
+    let clock = ::time::MockClock::new();
+    let mut queue = Queue::new();
+    queue.set_clock(clock.clone());
+    queue.insert_with_ttl(1u8, 1u8, Duration::from_secs(10));
+    queue.insert(2, 4);
+
+    clock.advance(Duration::from_secs(5));
+    drop(queue.checkout_head().unwrap());
+    assert_eq!(Some(Duration::from_secs(5)), queue.age_of(&1));
+
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(&2, queue.checkout_head().unwrap().key());
+}
+
+#[cfg(test)]
+#[test]
+fn array_queue_requeues_existing_keys() {
+    let mut queue: ArrayQueue<u8, u8, 2> = ArrayQueue::new();
+    queue.insert(2, 4).unwrap();
+    queue.insert(3, 6).unwrap();
+
+    assert_eq!(Ok(()), queue.insert(2, 5));
+    assert_eq!(Ok(()), queue.insert_head(3, 7));
+    assert_eq!(2, queue.len());
+    assert_eq!(Some(7), queue.remove_key(&3));
+    assert!(!queue.contains_key(&3));
+    assert_eq!(Some((2, 5)), queue.remove_head());
+    assert_eq!(None, queue.remove_head());
+}
+
+#[cfg(test)]
+#[test]
+fn array_queue_wrap_around() {
+    let mut queue: ArrayQueue<u8, u8, 4> = ArrayQueue::new();
+    for k in 0..4 {
+        queue.insert(k, k * 2).unwrap();
+    }
+    queue.remove_head();
+    queue.remove_head();
+    queue.insert(4, 8).unwrap();
+    queue.insert_head(1, 2).unwrap();
+
+    assert!(queue.is_full());
+    assert_eq!(queue.remove_key(&3), Some(6));
+    assert_eq!(queue.remove_key(&1), Some(2));
+    assert_eq!(queue.remove_head(), Some((2, 4)));
+    assert_eq!(queue.remove_head(), Some((4, 8)));
+    assert_eq!(queue.remove_head(), None);
+}
+
+#[cfg(feature = "serde")]
+mod serde_compat {
+    use serde::ser::SerializeSeq;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::Queue;
+    use core::hash::{BuildHasher, Hash};
+    use deque::Deque;
+
+    /// Expired entries are skipped.
+    impl<K, V, H> Serialize for Queue<K, V, H>
+    where
+        K: Serialize + Clone + Eq + Hash,
+        V: Serialize,
+        H: BuildHasher,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut seq = serializer.serialize_seq(Some(self.live().count()))?;
+
+            for pair in self.live() {
+                seq.serialize_element(&pair)?;
+            }
+
+            seq.end()
+        }
+    }
+
+    impl<'de, K, V, H> Deserialize<'de> for Queue<K, V, H>
+    where
+        K: Deserialize<'de> + Clone + Eq + Hash,
+        V: Deserialize<'de>,
+        H: BuildHasher + Default,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(Queue::from(Deque::deserialize(deserializer)?))
+        }
+    }
+
+    #[cfg(test)]
     #[test]
     fn serde_test() {
         use serde_json;
@@ -329,4 +1967,289 @@ mod serde_compat {
         assert_eq!(queue2.remove_head(), Some((3, 6)));
         assert_eq!(queue2.remove_head(), Some((4, 8)));
     }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn serde_skips_expired_entries() {
+        use serde_json;
+        let (queue, _clock) = super::expiring_queue();
+
+        assert_eq!("[[2,4],[3,9]]", serde_json::to_string(&queue).unwrap());
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn as_map_skips_expired_entries() {
+        use alloc::vec::Vec;
+        use as_map;
+        use serde_json;
+        let (queue, _clock) = super::expiring_queue();
+
+        let mut json = Vec::new();
+        as_map::serialize(&queue, &mut serde_json::Serializer::new(&mut json)).unwrap();
+        assert_eq!(&b"{\"2\":4,\"3\":9}"[..], &json[..]);
+    }
+}
+
+#[cfg(feature = "borsh")]
+mod borsh_compat {
+    use super::Queue;
+    use borsh::io::{Error, ErrorKind, Read, Result, Write};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use core::convert::TryFrom;
+    use core::hash::{BuildHasher, Hash};
+    use deque::Deque;
+
+    /// Expired entries are skipped.
+    impl<K, V, H> BorshSerialize for Queue<K, V, H>
+    where
+        K: BorshSerialize + Clone + Eq + Hash,
+        V: BorshSerialize,
+        H: BuildHasher,
+    {
+        fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+            let len = u32::try_from(self.live().count())
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "queue longer than u32::MAX"))?;
+            len.serialize(writer)?;
+            for (key, value) in self.live() {
+                key.serialize(writer)?;
+                value.serialize(writer)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<K, V, H> BorshDeserialize for Queue<K, V, H>
+    where
+        K: BorshDeserialize + Clone + Eq + Hash,
+        V: BorshDeserialize,
+        H: BuildHasher + Default,
+    {
+        fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+            Ok(Queue::from(Deque::deserialize_reader(reader)?))
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn borsh_test() {
+        let queue = Queue::new_with(vec![(2u8, 4u16), (3, 6), (4, 8)]);
+
+        let bytes = borsh::to_vec(&queue).unwrap();
+        assert_eq!(vec![3, 0, 0, 0, 2, 4, 0, 3, 6, 0, 4, 8, 0], bytes);
+        let mut queue2: Queue<u8, u16> = borsh::from_slice(&bytes).unwrap();
+
+        assert_eq!(queue2.remove_head(), Some((2, 4)));
+        assert_eq!(queue2.remove_head(), Some((3, 6)));
+        assert_eq!(queue2.remove_head(), Some((4, 8)));
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn borsh_skips_expired_entries() {
+        let (queue, _clock) = super::expiring_queue();
+
+        assert_eq!(vec![2, 0, 0, 0, 2, 4, 3, 9], borsh::to_vec(&queue).unwrap());
+    }
+}
+
+#[cfg(feature = "bincode")]
+mod bincode_compat {
+    use super::Queue;
+    use bincode::de::{BorrowDecoder, Decoder};
+    use bincode::enc::Encoder;
+    use bincode::error::{DecodeError, EncodeError};
+    use bincode::{BorrowDecode, Decode, Encode};
+    use core::hash::{BuildHasher, Hash};
+    use deque::Deque;
+
+    /// Expired entries are skipped.
+    impl<K, V, H> Encode for Queue<K, V, H>
+    where
+        K: Encode + Clone + Eq + Hash,
+        V: Encode,
+        H: BuildHasher,
+    {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            (self.live().count() as u64).encode(encoder)?;
+            for (key, value) in self.live() {
+                key.encode(encoder)?;
+                value.encode(encoder)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<Context, K, V, H> Decode<Context> for Queue<K, V, H>
+    where
+        K: Decode<Context> + Clone + Eq + Hash,
+        V: Decode<Context>,
+        H: BuildHasher + Default,
+    {
+        fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(Queue::from(Deque::decode(decoder)?))
+        }
+    }
+
+    impl<'de, Context, K, V, H> BorrowDecode<'de, Context> for Queue<K, V, H>
+    where
+        K: BorrowDecode<'de, Context> + Clone + Eq + Hash,
+        V: BorrowDecode<'de, Context>,
+        H: BuildHasher + Default,
+    {
+        fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+            decoder: &mut D,
+        ) -> Result<Self, DecodeError> {
+            Ok(Queue::from(Deque::borrow_decode(decoder)?))
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn bincode_test() {
+        let config = bincode::config::standard();
+        let queue = Queue::new_with(vec![("b", 4u8), ("a", 6)]);
+
+        let bytes = bincode::encode_to_vec(&queue, config).unwrap();
+        assert_eq!(vec![2, 1, b'b', 4, 1, b'a', 6], bytes);
+        let (mut queue2, read): (Queue<&str, u8>, _) =
+            bincode::borrow_decode_from_slice(&bytes, config).unwrap();
+
+        assert_eq!(read, bytes.len());
+        assert_eq!(queue2.remove_head(), Some(("b", 4)));
+        assert_eq!(queue2.remove_head(), Some(("a", 6)));
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn bincode_skips_expired_entries() {
+        let config = bincode::config::standard();
+        let (queue, _clock) = super::expiring_queue();
+
+        let bytes = bincode::encode_to_vec(&queue, config).unwrap();
+        assert_eq!(vec![2, 2, 4, 3, 9], bytes);
+    }
+}
+
+#[cfg(feature = "schemars")]
+mod schemars_compat {
+    use super::Queue;
+    use alloc::borrow::Cow;
+    use alloc::format;
+    use deque::Deque;
+    use schemars::{JsonSchema, Schema, SchemaGenerator};
+
+    impl<K, V, H> JsonSchema for Queue<K, V, H>
+    where
+        K: JsonSchema,
+        V: JsonSchema,
+    {
+        fn inline_schema() -> bool {
+            true
+        }
+
+        fn schema_name() -> Cow<'static, str> {
+            format!("Queue_of_{}_and_{}", K::schema_name(), V::schema_name()).into()
+        }
+
+        fn schema_id() -> Cow<'static, str> {
+            format!(
+                "addressable_queue::fifo::Queue<{}, {}>",
+                K::schema_id(),
+                V::schema_id()
+            )
+            .into()
+        }
+
+        fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+            <Deque<K, V>>::json_schema(generator)
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn schemars_test() {
+        use alloc::string::String;
+        use serde_json;
+        let schema = schemars::schema_for!(Queue<String, u32>);
+
+        assert_eq!("array", schema.get("type").unwrap());
+        assert_eq!(
+            serde_json::json!({
+                "type": "array",
+                "prefixItems": [{"type": "string"}, {"type": "integer", "format": "uint32", "minimum": 0}],
+                "minItems": 2,
+                "maxItems": 2,
+            }),
+            schema.get("items").unwrap().clone()
+        );
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_compat {
+    use super::Queue;
+    use arbitrary::{Arbitrary, Result, Unstructured};
+    use core::hash::{BuildHasher, Hash};
+
+    /// Built by a sequence of arbitrary insertions and removals, so the queue may also contain the
+    /// slots of removed entries which are not reclaimed yet.
+    impl<'a, K, V, S> Arbitrary<'a> for Queue<K, V, S>
+    where
+        K: Arbitrary<'a> + Clone + Eq + Hash,
+        V: Arbitrary<'a>,
+        S: BuildHasher + Default,
+    {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let mut queue = Queue::default();
+            // Every step is preceded by a flag, which is false once the data runs out.
+            while u.arbitrary()? {
+                match u.int_in_range(0..=5)? {
+                    0 | 1 => queue.insert(u.arbitrary()?, u.arbitrary()?),
+                    2 => queue.insert_head(u.arbitrary()?, u.arbitrary()?),
+                    3 => {
+                        queue.remove_head();
+                    }
+                    4 => {
+                        queue.remove_tail();
+                    }
+                    _ => {
+                        if let Some(key) = choose_key(u, &queue)? {
+                            queue.remove_key(&key);
+                        }
+                    }
+                }
+            }
+            Ok(queue)
+        }
+    }
+
+    /// Pick one of the keys of the queue, so removals mostly hit an entry.
+    fn choose_key<K, V, S>(u: &mut Unstructured, queue: &Queue<K, V, S>) -> Result<Option<K>>
+    where
+        K: Clone + Eq + Hash,
+        S: BuildHasher,
+    {
+        if queue.is_empty() {
+            return Ok(None);
+        }
+        let index = u.choose_index(queue.len())?;
+        Ok(queue.iter().nth(index).map(|(key, _)| key.clone()))
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn arbitrary_test() {
+        use alloc::vec::Vec;
+
+        for seed in 0..64u8 {
+            let bytes: Vec<u8> = (0..256u32)
+                .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+                .collect();
+            let queue: Queue<u8, u8> = Queue::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+
+            assert_eq!(queue.len(), queue.iter().count());
+            assert!(queue.iter().all(|(key, _)| queue.contains_key(key)));
+        }
+    }
 }
@@ -0,0 +1,228 @@
+//! Implementation of an addressable FIFO queue where entries can belong to a group, with
+//! operations on whole groups.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::Deque;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+use set;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// An addressable FIFO queue whose entries can belong to a group.
+///
+/// For every group the keys of its entries are tracked in queue order.
+pub struct Queue<K, G, V> {
+    inner: Deque<K, (Option<G>, V)>,
+    groups: HashMap<G, set::Queue<K>>,
+}
+
+impl<K, G, V> Queue<K, G, V>
+where
+    K: Clone + Eq + Hash,
+    G: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            inner: Deque::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the number of elements in the group.
+    pub fn len_of_group(&self, group: &G) -> usize {
+        self.groups.get(group).map_or(0, set::Queue::len)
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns the group of the entry with the specified key, if it has one.
+    pub fn group_of(&self, key: &K) -> Option<&G> {
+        self.inner.get(key).and_then(|(group, _)| group.as_ref())
+    }
+
+    /// Insert an entry without a group at the end of the queue.
+    ///
+    /// An existing entry for the key is removed.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.remove_key(&key);
+        self.inner.push_back(key, (None, value));
+    }
+
+    /// Insert an entry belonging to a group at the end of the queue.
+    ///
+    /// An existing entry for the key is removed.
+    ///
+    /// ```
+    /// use addressable_queue::grouped::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_in_group(1u32, "batch-1", 'x');
+    /// queue.insert_in_group(2u32, "batch-2", 'y');
+    /// queue.insert_in_group(3u32, "batch-1", 'z');
+    ///
+    /// assert_eq!(2, queue.len_of_group(&"batch-1"));
+    /// assert_eq!(Some(&"batch-2"), queue.group_of(&2));
+    /// ```
+    pub fn insert_in_group(&mut self, key: K, group: G, value: V) {
+        self.remove_key(&key);
+        self.groups
+            .entry(group.clone())
+            .or_default()
+            .insert(key.clone());
+        self.inner.push_back(key, (Some(group), value));
+    }
+
+    /// Returns an iterator over the entries of the group in queue order.
+    ///
+    /// ```
+    /// use addressable_queue::grouped::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_in_group(1u32, "batch-1", 'x');
+    /// queue.insert_in_group(2u32, "batch-2", 'y');
+    /// queue.insert_in_group(3u32, "batch-1", 'z');
+    ///
+    /// let entries: Vec<_> = queue.iter_group(&"batch-1").collect();
+    /// assert_eq!(entries, vec![(&1, &'x'), (&3, &'z')]);
+    /// ```
+    pub fn iter_group<'a>(&'a self, group: &G) -> impl Iterator<Item = (&'a K, &'a V)> + 'a {
+        let inner = &self.inner;
+        self.groups
+            .get(group)
+            .into_iter()
+            .flat_map(|keys| keys.iter())
+            .filter_map(move |key| inner.get(key).map(|(_, value)| (key, value)))
+    }
+
+    /// Remove the current head of the queue, and return it if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        let (key, (group, value)) = self.inner.pop_front()?;
+        self.leave(&key, group);
+        Some((key, value))
+    }
+
+    /// Remove the oldest entry of the group, and return it if there was one.
+    ///
+    /// ```
+    /// use addressable_queue::grouped::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_in_group(1u32, "a", 'x');
+    /// queue.insert_in_group(2u32, "b", 'y');
+    /// queue.insert_in_group(3u32, "a", 'z');
+    ///
+    /// assert_eq!(Some((2, 'y')), queue.remove_head_of_group(&"b"));
+    /// assert_eq!(Some((1, 'x')), queue.remove_head_of_group(&"a"));
+    /// assert_eq!(None, queue.remove_head_of_group(&"b"));
+    /// ```
+    pub fn remove_head_of_group(&mut self, group: &G) -> Option<(K, V)> {
+        let key = self.groups.get(group)?.first()?.clone();
+        let value = self.remove_key(&key)?;
+        Some((key, value))
+    }
+
+    /// Remove the current tail of the queue, and return it if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        let (key, (group, value)) = self.inner.pop_back()?;
+        self.leave(&key, group);
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let (group, value) = self.inner.remove_key(key)?;
+        self.leave(key, group);
+        Some(value)
+    }
+
+    /// Remove all entries of the group, and return them in queue order.
+    ///
+    /// ```
+    /// use addressable_queue::grouped::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_in_group(1u32, "a", 'x');
+    /// queue.insert(2u32, 'y');
+    /// queue.insert_in_group(3u32, "a", 'z');
+    ///
+    /// assert_eq!(queue.remove_group(&"a"), vec![(1, 'x'), (3, 'z')]);
+    /// assert_eq!(queue.into_vec(), vec![(2, 'y')]);
+    /// ```
+    pub fn remove_group(&mut self, group: &G) -> Vec<(K, V)> {
+        let mut keys = match self.groups.remove(group) {
+            Some(keys) => keys,
+            None => return Vec::new(),
+        };
+        let mut removed = Vec::with_capacity(keys.len());
+        while let Some(key) = keys.remove_head() {
+            if let Some((_, value)) = self.inner.remove_key(&key) {
+                removed.push((key, value));
+            }
+        }
+        removed
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner
+            .into_vec()
+            .into_iter()
+            .map(|(key, (_, value))| (key, value))
+            .collect()
+    }
+
+    fn leave(&mut self, key: &K, group: Option<G>) {
+        if let Some(group) = group {
+            let now_empty = match self.groups.get_mut(&group) {
+                Some(keys) => {
+                    keys.remove(key);
+                    keys.is_empty()
+                }
+                None => false,
+            };
+            if now_empty {
+                self.groups.remove(&group);
+            }
+        }
+    }
+}
+
+impl<K, G, V> Default for Queue<K, G, V>
+where
+    K: Clone + Eq + Hash,
+    G: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
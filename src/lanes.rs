@@ -0,0 +1,297 @@
+//! Implementation of an addressable queue with several priority lanes, each of which is a FIFO
+//! queue.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::Deque;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// An addressable queue with a fixed number of priority lanes.
+///
+/// Lane `0` has the highest priority. Within a lane entries are removed in the order they were
+/// inserted, which lane is removed from next is decided by the schedule of the queue.
+///
+/// ```
+/// use addressable_queue::lanes::Queue;
+///
+/// let mut queue = Queue::new(2);
+/// queue.insert("bulk", 1, ());
+/// queue.insert("control", 0, ());
+///
+/// assert_eq!(Some(("control", ())), queue.remove_head());
+/// assert_eq!(Some(("bulk", ())), queue.remove_head());
+/// ```
+pub struct Queue<K, V> {
+    /// The entries of every lane, with the number of removed heads when they were inserted.
+    lanes: Vec<Deque<K, (u64, V)>>,
+    /// The lane of every key.
+    index: HashMap<K, usize>,
+    schedule: Schedule,
+    aging: Option<AgeFn>,
+    /// Number of heads removed by the schedule, which measures how long entries wait.
+    removed: u64,
+}
+
+/// Computes the lane an entry should be in from its lane and how long it waited.
+type AgeFn = Box<dyn Fn(usize, u64) -> usize + Send + Sync>;
+
+/// Decides which lane of a [`Queue`](struct.Queue.html) is removed from next.
+enum Schedule {
+    /// Always the first lane which is not empty.
+    Strict,
+    /// Every lane in turn, removing up to its weight of entries.
+    Weighted {
+        weights: Vec<u32>,
+        current: usize,
+        credit: u32,
+    },
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue with `lanes` lanes, where a lane is only removed from if
+    /// all lanes of higher priority are empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lanes` is zero.
+    pub fn new(lanes: usize) -> Self {
+        assert!(lanes > 0, "a queue requires at least one lane");
+        Queue {
+            lanes: (0..lanes).map(|_| Deque::new()).collect(),
+            index: HashMap::new(),
+            schedule: Schedule::Strict,
+            aging: None,
+            removed: 0,
+        }
+    }
+
+    /// Create a new instance of a queue with one lane per weight, where the lanes are removed
+    /// from in turn, each up to its weight of entries before the next one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no weights or a weight is zero.
+    ///
+    /// ```
+    /// use addressable_queue::lanes::Queue;
+    ///
+    /// let mut queue = Queue::with_weights(vec![2, 1]);
+    /// for key in 0..3 {
+    ///     queue.insert(key, 0, ());
+    ///     queue.insert(key + 10, 1, ());
+    /// }
+    ///
+    /// let keys: Vec<_> = (0..6).filter_map(|_| queue.remove_head()).map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![0, 1, 10, 2, 11, 12]);
+    /// ```
+    pub fn with_weights(weights: Vec<u32>) -> Self {
+        assert!(!weights.is_empty(), "a queue requires at least one lane");
+        assert!(
+            weights.iter().all(|&weight| weight > 0),
+            "the weights of the lanes must be positive"
+        );
+        Queue {
+            lanes: weights.iter().map(|_| Deque::new()).collect(),
+            index: HashMap::new(),
+            schedule: Schedule::Weighted {
+                credit: weights[0],
+                weights,
+                current: 0,
+            },
+            aging: None,
+            removed: 0,
+        }
+    }
+
+    /// Let entries move up the lanes the longer they wait, so lower lanes are not starved.
+    ///
+    /// How long an entry waited is the number of heads removed by
+    /// [`remove_head`](#method.remove_head) since it was inserted. When a head is removed, the
+    /// heads of the other lanes are moved to the end of the lane `aging` computes from their lane
+    /// and waiting time, if that is a higher one.
+    ///
+    /// ```
+    /// use addressable_queue::lanes::Queue;
+    ///
+    /// let mut queue = Queue::new(2);
+    /// queue.set_aging(|lane, waited| if waited >= 1 { 0 } else { lane });
+    /// queue.insert("bulk", 1, ());
+    /// queue.insert("a", 0, ());
+    /// queue.insert("b", 0, ());
+    ///
+    /// assert_eq!(Some(("a", ())), queue.remove_head());
+    /// queue.insert("c", 0, ());
+    /// assert_eq!(Some(("b", ())), queue.remove_head());
+    /// queue.insert("d", 0, ());
+    ///
+    /// // The bulk entry waited long enough to move into the first lane behind "c".
+    /// assert_eq!(Some(0), queue.lane_of(&"bulk"));
+    /// assert_eq!(Some(("c", ())), queue.remove_head());
+    /// assert_eq!(Some(("bulk", ())), queue.remove_head());
+    /// assert_eq!(Some(("d", ())), queue.remove_head());
+    /// ```
+    pub fn set_aging<F>(&mut self, aging: F)
+    where
+        F: Fn(usize, u64) -> usize + Send + Sync + 'static,
+    {
+        self.aging = Some(Box::new(aging));
+    }
+
+    /// Let entries move up one lane for every `removals` heads removed while they wait.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `removals` is zero.
+    pub fn set_aging_rate(&mut self, removals: u64) {
+        assert!(removals > 0, "the aging rate must be positive");
+        self.set_aging(move |lane, waited| lane - (waited / removals).min(lane as u64) as usize);
+    }
+
+    /// Stop moving entries up the lanes.
+    pub fn clear_aging(&mut self) {
+        self.aging = None;
+    }
+
+    /// Returns the number of lanes.
+    pub fn lanes(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns the number of elements in a lane.
+    pub fn len_of_lane(&self, lane: usize) -> usize {
+        self.lanes.get(lane).map_or(0, Deque::len)
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the lane of the entry with the specified key.
+    pub fn lane_of(&self, key: &K) -> Option<usize> {
+        self.index.get(key).cloned()
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let lane = *self.index.get(key)?;
+        self.lanes[lane].get(key).map(|(_, value)| value)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let lane = *self.index.get(key)?;
+        self.lanes[lane]
+            .get_mut(key)
+            .map(|&mut (_, ref mut value)| value)
+    }
+
+    /// Insert an entry at the end of a lane, and return the old value of the key.
+    ///
+    /// An existing entry for the key is removed, also if it is in another lane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lane does not exist.
+    pub fn insert(&mut self, key: K, lane: usize, value: V) -> Option<V> {
+        assert!(lane < self.lanes.len(), "lane {} does not exist", lane);
+        let old = self.remove_key(&key);
+        self.index.insert(key.clone(), lane);
+        self.lanes[lane].push_back(key, (self.removed, value));
+        old
+    }
+
+    /// Remove the head of the lane picked by the schedule, and return it if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        self.age();
+        let lane = self.next_lane()?;
+        self.removed += 1;
+        self.remove_head_of_lane(lane)
+    }
+
+    /// Remove the head of a lane, and return it if there was one.
+    pub fn remove_head_of_lane(&mut self, lane: usize) -> Option<(K, V)> {
+        let (key, (_, value)) = self.lanes.get_mut(lane)?.pop_front()?;
+        self.index.remove(&key);
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key, in whichever lane it is.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let lane = self.index.remove(key)?;
+        self.lanes[lane].remove_key(key).map(|(_, value)| value)
+    }
+
+    /// Move the heads which waited long enough up to the lane computed by the aging function.
+    fn age(&mut self) {
+        let aging = match self.aging {
+            Some(ref aging) => aging,
+            None => return,
+        };
+        for lane in 1..self.lanes.len() {
+            while let Some((_, &(inserted, _))) = self.lanes[lane].peek_front() {
+                let target = aging(lane, self.removed - inserted);
+                if target >= lane {
+                    break;
+                }
+                let (key, entry) = self.lanes[lane].pop_front().unwrap();
+                self.index.insert(key.clone(), target);
+                self.lanes[target].push_back(key, entry);
+            }
+        }
+    }
+
+    /// Returns the lane to remove from next, and advances the schedule.
+    fn next_lane(&mut self) -> Option<usize> {
+        if self.is_empty() {
+            return None;
+        }
+        let lanes = &self.lanes;
+        match self.schedule {
+            Schedule::Strict => lanes.iter().position(|lane| !lane.is_empty()),
+            Schedule::Weighted {
+                ref weights,
+                ref mut current,
+                ref mut credit,
+            } => loop {
+                if *credit > 0 && !lanes[*current].is_empty() {
+                    *credit -= 1;
+                    return Some(*current);
+                }
+                *current = (*current + 1) % lanes.len();
+                *credit = weights[*current];
+            },
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Implementation of an addressable work queue, where entries are leased for processing and only
+//! removed once they are acknowledged.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use delay;
+use fifo;
+use std::boxed::Box;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+use time::{Clock, SystemClock};
+
+/// An addressable FIFO queue with at-least-once processing of its entries.
+///
+/// Leasing the head moves it to a set of entries in flight, where it stays until it is
+/// acknowledged, which removes it for good. An entry which is not acknowledged, either because it
+/// was rejected or because its lease timed out, is put back at the head of the queue.
+///
+/// ```
+/// use addressable_queue::lease::Queue;
+/// use std::time::Duration;
+///
+/// let mut queue = Queue::new();
+/// queue.insert("a", 1);
+/// queue.insert("b", 2);
+///
+/// let (key, _) = queue.lease(Duration::from_secs(0)).unwrap();
+/// assert_eq!("a", key);
+///
+/// // The lease of "a" timed out without it being acknowledged, so it is leased again.
+/// let (key, value) = queue.lease(Duration::from_secs(30)).unwrap();
+/// assert_eq!(("a", &1), (key, value));
+/// assert_eq!(Some(1), queue.ack(&"a"));
+///
+/// let (key, _) = queue.lease(Duration::from_secs(30)).unwrap();
+/// assert!(queue.nack(&key));
+/// assert_eq!(Some(&"b"), queue.first_key());
+/// ```
+pub struct Queue<K, V> {
+    ready: fifo::Queue<K, V>,
+    /// The leased entries, which become available when their lease times out.
+    in_flight: delay::Queue<K, V>,
+    clock: Box<dyn Clock>,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            ready: fifo::Queue::new(),
+            in_flight: delay::Queue::new(),
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// Read the current time from `clock` for leasing entries and timing out leases, instead of
+    /// from the system.
+    ///
+    /// ```
+    /// use addressable_queue::lease::Queue;
+    /// use addressable_queue::time::MockClock;
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut queue = Queue::new();
+    /// queue.set_clock(clock.clone());
+    /// queue.insert("job", ());
+    ///
+    /// queue.lease(Duration::from_secs(30)).unwrap();
+    /// assert!(queue.lease(Duration::from_secs(30)).is_none());
+    ///
+    /// clock.advance(Duration::from_secs(30));
+    /// assert_eq!("job", queue.lease(Duration::from_secs(30)).unwrap().0);
+    /// ```
+    pub fn set_clock<C>(&mut self, clock: C)
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Box::new(clock);
+    }
+
+    /// Returns the number of entries in the queue, including the ones in flight.
+    pub fn len(&self) -> usize {
+        self.ready.len() + self.in_flight.len()
+    }
+
+    /// Returns true if the queue contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty() && self.in_flight.is_empty()
+    }
+
+    /// Returns the number of entries in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Returns true if the queue contains an entry for the specified key, which may be in
+    /// flight.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.ready.contains_key(key) || self.in_flight.contains_key(key)
+    }
+
+    /// Returns true if the entry for the specified key is in flight.
+    pub fn is_leased(&self, key: &K) -> bool {
+        self.in_flight.contains_key(key)
+    }
+
+    /// Returns the instant the lease of the entry with the specified key times out.
+    pub fn lease_expires_at(&self, key: &K) -> Option<Instant> {
+        self.in_flight.available_at(key)
+    }
+
+    /// Returns the key of the entry which is leased next, not counting leases which timed out.
+    pub fn first_key(&self) -> Option<&K> {
+        self.ready.first_key()
+    }
+
+    /// Insert an entry at the end of the queue, and return the old value of the key.
+    ///
+    /// An entry in flight for the same key is replaced as well, acknowledging it has no effect.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = match self.in_flight.remove_key(&key) {
+            Some(old) => Some(old),
+            None => self.ready.remove_key(&key),
+        };
+        self.ready.insert(key, value);
+        old
+    }
+
+    /// Lease the head of the queue for `timeout`, and return its key and value.
+    ///
+    /// Leases which timed out are put back at the head of the queue first, the one which timed
+    /// out first at the very front.
+    pub fn lease(&mut self, timeout: Duration) -> Option<(K, &V)> {
+        let now = self.clock.now();
+        self.reclaim(now);
+        let (key, value) = self.ready.remove_head()?;
+        self.in_flight.insert_at(key.clone(), value, now + timeout);
+        let value = self.in_flight.get(&key)?;
+        Some((key, value))
+    }
+
+    /// Extend the lease of an entry in flight to `timeout` from now, and return whether it was in
+    /// flight.
+    pub fn extend_lease(&mut self, key: &K, timeout: Duration) -> bool {
+        let at = self.clock.now() + timeout;
+        self.in_flight.reset_at(key, at).is_some()
+    }
+
+    /// Acknowledge an entry in flight, which removes it, and return its value.
+    pub fn ack(&mut self, key: &K) -> Option<V> {
+        self.in_flight.remove_key(key)
+    }
+
+    /// Reject an entry in flight, which puts it back at the head of the queue, and return
+    /// whether it was in flight.
+    pub fn nack(&mut self, key: &K) -> bool {
+        match self.in_flight.remove_key(key) {
+            Some(value) => {
+                self.ready.insert_head(key.clone(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Put the entries whose lease timed out at `now` back at the head of the queue, and return
+    /// how many there were.
+    pub fn reclaim(&mut self, now: Instant) -> usize {
+        let timed_out: Vec<(K, V)> = self.in_flight.poll_expired(now).collect();
+        let count = timed_out.len();
+        for (key, value) in timed_out.into_iter().rev() {
+            self.ready.insert_head(key, value);
+        }
+        count
+    }
+
+    /// Remove a value by specifying its key, which may be in flight.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        match self.in_flight.remove_key(key) {
+            Some(value) => Some(value),
+            None => self.ready.remove_key(key),
+        }
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
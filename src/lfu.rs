@@ -0,0 +1,237 @@
+//! Implementation of an addressable cache evicting the least frequently used entry.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::hash::Hash;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+use set;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// An addressable cache holding at most `capacity` entries, which evicts the least frequently
+/// used entry when it is full.
+///
+/// Every entry counts how often it was inserted or accessed. The keys are kept in one bucket per
+/// count, in the order they reached it, so accesses and evictions take constant time. Among the
+/// entries with the lowest count the least recently used one is evicted.
+///
+/// ```
+/// use addressable_queue::lfu::Cache;
+///
+/// let mut cache = Cache::new(2);
+/// cache.insert('a', 1);
+/// cache.insert('b', 2);
+/// cache.get(&'a');
+///
+/// assert_eq!(Some(('b', 2)), cache.insert('c', 3));
+/// assert_eq!(Some(2), cache.frequency(&'a'));
+/// ```
+pub struct Cache<K, V> {
+    entries: HashMap<K, (u64, V)>,
+    buckets: HashMap<u64, Bucket<K>>,
+    /// The lowest frequency of any entry, the first bucket of the list.
+    lowest: Option<u64>,
+    capacity: usize,
+}
+
+/// The keys with the same frequency, linked to the buckets of the next lower and higher
+/// frequencies.
+struct Bucket<K> {
+    keys: set::Queue<K>,
+    prev: Option<u64>,
+    next: Option<u64>,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Cache {
+            entries: HashMap::with_capacity(capacity),
+            buckets: HashMap::new(),
+            lowest: None,
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if the cache contains an entry for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns how often the entry of the key was inserted or accessed.
+    pub fn frequency(&self, key: &K) -> Option<u64> {
+        self.entries.get(key).map(|&(frequency, _)| frequency)
+    }
+
+    /// Access an entry, which increments its frequency.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Access an entry mutably, which increments its frequency.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+        self.bump(key);
+        self.entries.get_mut(key).map(|(_, value)| value)
+    }
+
+    /// Returns a reference to the value of the specified key, without counting as an access.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(_, value)| value)
+    }
+
+    /// Returns the entry which would be evicted next.
+    pub fn peek_lfu(&self) -> Option<(&K, &V)> {
+        let key = self.buckets.get(&self.lowest?)?.keys.first()?;
+        self.entries.get(key).map(|(_, value)| (key, value))
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its value is replaced, its frequency incremented, and
+    /// the old entry is returned. Otherwise, if the cache is full, the least frequently used
+    /// entry is evicted and returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        if let Some(&mut (_, ref mut old)) = self.entries.get_mut(&key) {
+            let old = ::core::mem::replace(old, value);
+            self.bump(&key);
+            return Some((key, old));
+        }
+        let evicted = if self.len() >= self.capacity {
+            self.pop_lfu()
+        } else {
+            None
+        };
+        self.enter(key.clone(), 1, None);
+        self.entries.insert(key, (1, value));
+        evicted
+    }
+
+    /// Remove the least frequently used entry, and return it if there was one.
+    pub fn pop_lfu(&mut self) -> Option<(K, V)> {
+        let lowest = self.lowest?;
+        let key = self.buckets.get(&lowest)?.keys.first()?.clone();
+        let value = self.remove_key(&key)?;
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let (frequency, value) = self.entries.remove(key)?;
+        self.leave(key, frequency);
+        Some(value)
+    }
+
+    /// Move a key to the bucket of the next higher frequency.
+    fn bump(&mut self, key: &K) {
+        let frequency = match self.entries.get_mut(key) {
+            Some(&mut (ref mut frequency, _)) => {
+                *frequency += 1;
+                *frequency - 1
+            }
+            None => return,
+        };
+        self.enter(key.clone(), frequency + 1, Some(frequency));
+        self.leave(key, frequency);
+    }
+
+    /// Add a key to the bucket of a frequency, which is created after the bucket `prev` if it
+    /// does not exist yet.
+    fn enter(&mut self, key: K, frequency: u64, prev: Option<u64>) {
+        if let Some(bucket) = self.buckets.get_mut(&frequency) {
+            bucket.keys.insert(key);
+            return;
+        }
+        let next = match prev {
+            Some(prev) => self.buckets.get_mut(&prev).and_then(|bucket| {
+                let next = bucket.next;
+                bucket.next = Some(frequency);
+                next
+            }),
+            None => self.lowest.replace(frequency),
+        };
+        if let Some(bucket) = next.and_then(|next| self.buckets.get_mut(&next)) {
+            bucket.prev = Some(frequency);
+        }
+        let mut keys = set::Queue::new();
+        keys.insert(key);
+        self.buckets.insert(frequency, Bucket { keys, prev, next });
+    }
+
+    /// Remove a key from the bucket of its frequency, and unlink the bucket if it is empty.
+    fn leave(&mut self, key: &K, frequency: u64) {
+        let bucket = match self.buckets.get_mut(&frequency) {
+            Some(bucket) => bucket,
+            None => return,
+        };
+        bucket.keys.remove(key);
+        if !bucket.keys.is_empty() {
+            return;
+        }
+        let (prev, next) = (bucket.prev, bucket.next);
+        self.buckets.remove(&frequency);
+        match prev.and_then(|prev| self.buckets.get_mut(&prev)) {
+            Some(bucket) => bucket.next = next,
+            None => self.lowest = next,
+        }
+        if let Some(bucket) = next.and_then(|next| self.buckets.get_mut(&next)) {
+            bucket.prev = prev;
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn buckets_stay_linked() {
+    let mut cache = Cache::new(3);
+    cache.insert(1, ());
+    cache.insert(2, ());
+    cache.insert(3, ());
+    cache.get(&1);
+    cache.get(&1);
+    cache.get(&3);
+    cache.remove_key(&2);
+
+    assert_eq!(Some((3, ())), cache.pop_lfu());
+    cache.insert(4, ());
+    assert_eq!(Some((4, ())), cache.pop_lfu());
+    assert_eq!(Some((1, ())), cache.pop_lfu());
+    assert_eq!(None, cache.pop_lfu());
+    assert!(cache.buckets.is_empty());
+}
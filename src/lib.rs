@@ -12,10 +12,110 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "snapshot")]
+extern crate arc_swap;
+#[cfg(feature = "bincode")]
+extern crate bincode;
+#[cfg(feature = "borsh")]
+extern crate borsh;
+#[cfg(all(test, feature = "async"))]
+extern crate futures;
+#[cfg(feature = "async")]
+extern crate futures_core;
+#[cfg(feature = "async")]
+extern crate futures_sink;
+extern crate hashbrown;
+#[cfg(loom)]
+extern crate loom;
+#[cfg(feature = "testing")]
+extern crate proptest;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(feature = "schemars")]
+extern crate schemars;
 #[cfg(feature = "serde")]
 extern crate serde;
 #[cfg(test)]
 extern crate serde_json;
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate std;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
+#[macro_use]
+mod macros;
+mod expiry;
+#[cfg(feature = "std")]
+mod primitives;
+mod sketch;
+#[cfg(feature = "std")]
+mod wheel;
 
+pub mod alias;
+#[cfg(feature = "serde")]
+pub mod as_map;
+#[cfg(feature = "tokio")]
+pub mod async_delay;
+#[cfg(feature = "async")]
+pub mod async_queue;
+pub mod builder;
+pub mod clock;
+pub mod coalesce;
+#[cfg(feature = "std")]
+pub mod concurrent;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "std")]
+pub mod deadline;
+#[cfg(feature = "std")]
+pub mod delay;
+pub mod deque;
+pub mod fair;
 pub mod fifo;
-//TODO: pub mod lru;
+pub mod grouped;
+pub mod lanes;
+#[cfg(feature = "std")]
+pub mod lease;
+pub mod lfu;
+pub mod lifo;
+pub mod lru;
+#[cfg(feature = "std")]
+pub mod maintenance;
+pub mod mem;
+#[cfg(feature = "std")]
+pub mod mpsc;
+pub mod mru;
+pub mod ord;
+pub mod persist;
+pub mod policy;
+pub mod priority;
+#[cfg(feature = "rand")]
+pub mod random;
+#[cfg(feature = "std")]
+pub mod rate;
+#[cfg(feature = "std")]
+pub mod retry;
+pub mod s3fifo;
+pub mod seq;
+pub mod set;
+#[cfg(feature = "std")]
+pub mod shared;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod storage;
+#[cfg(feature = "std")]
+pub mod stealing;
+#[cfg(feature = "std")]
+pub mod sync;
+pub mod tagged;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod time;
+pub mod two_q;
@@ -0,0 +1,135 @@
+//! Implementation of an addressable LIFO stack, that is a stack where it is possible to remove
+//! values directly by a key.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::Deque;
+
+/// An addressable LIFO stack.
+pub struct Stack<K, V> {
+    inner: Deque<K, V>,
+}
+
+impl<K, V> Stack<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the stack.
+    pub fn new() -> Self {
+        Stack {
+            inner: Deque::new(),
+        }
+    }
+
+    /// Returns the number of elements on the stack.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the stack contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the stack contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Push an entry on top of the stack.
+    ///
+    /// ```
+    /// use addressable_queue::lifo::Stack;
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push(2u8, 4u8);
+    /// stack.push(3u8, 6u8);
+    ///
+    /// assert_eq!(Some((3, 6)), stack.pop());
+    /// assert_eq!(Some((2, 4)), stack.pop());
+    /// assert_eq!(None, stack.pop());
+    /// ```
+    pub fn push(&mut self, key: K, value: V) {
+        self.inner.push_back(key, value);
+    }
+
+    /// Remove the top of the stack, and return it if there was one.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        self.inner.pop_back()
+    }
+
+    /// Returns the top of the stack without removing it.
+    ///
+    /// ```
+    /// use addressable_queue::lifo::Stack;
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push(2u8, 4u8);
+    /// stack.push(3u8, 6u8);
+    /// stack.remove_key(&3);
+    ///
+    /// assert_eq!(Some((&2, &4)), stack.peek());
+    /// ```
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        self.inner.peek_back()
+    }
+
+    /// Remove a value by specifying its key.
+    ///
+    /// ```
+    /// use addressable_queue::lifo::Stack;
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push(2u8, 4u8);
+    /// stack.push(3u8, 6u8);
+    /// stack.push(4u8, 8u8);
+    ///
+    /// assert_eq!(Some(6), stack.remove_key(&3));
+    /// assert_eq!(None, stack.remove_key(&3));
+    /// assert_eq!(Some((4, 8)), stack.pop());
+    /// assert_eq!(Some((2, 4)), stack.pop());
+    /// ```
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Convert the stack into a vec, where the first element is the top of the stack.
+    ///
+    /// ```
+    /// use addressable_queue::lifo::Stack;
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push(2u8, 4u8);
+    /// stack.push(3u8, 6u8);
+    ///
+    /// assert_eq!(stack.into_vec(), vec![(3, 6), (2, 4)]);
+    /// ```
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        let mut vec = self.inner.into_vec();
+        vec.reverse();
+        vec
+    }
+}
+
+impl<K, V> Default for Stack<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Stack::new()
+    }
+}
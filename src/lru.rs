@@ -12,57 +12,1924 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use fifo;
-use std::hash::Hash;
+use alloc::boxed::Box;
+use alloc::vec::{self, Vec};
+use core::convert::Infallible;
+use core::hash::{BuildHasher, Hash};
+use deque::{self, DefaultHashBuilder, Deque};
+use expiry::Tracker;
+use persist::Snapshot;
+use policy::{self, Lru};
+use sketch::FrequencySketch;
+#[cfg(feature = "std")]
+use std::time::Duration;
+#[cfg(feature = "std")]
+use time::Clock;
+
+#[cfg(feature = "std")]
+pub use expiry::Expiry;
 
 /// An addressable LRU queue.
-pub struct Queue<K, V> {
-    inner: fifo::Queue<K, V>,
+///
+/// Without a capacity the queue grows without bounds, otherwise inserting into a full queue
+/// evicts the least recently used entry.
+///
+/// With the `std` feature entries can also expire after a time to live or time to idle. Expired
+/// entries are treated as absent and removed when they are accessed, or by
+/// [`purge_expired`](#method.purge_expired).
+pub struct Queue<K, V, S = DefaultHashBuilder> {
+    inner: policy::Queue<K, V, Lru, S>,
+    capacity: Option<usize>,
+    weigher: Option<Weigher<K, V>>,
+    expiration: Tracker<K>,
+    listener: Option<EvictFn<K, V>>,
+    stats: Option<Stats>,
+    admission: Option<FrequencySketch>,
+}
+
+/// Decides whether a new entry is inserted into a full LRU queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Admission {
+    /// Every new entry is inserted, evicting the least recently used one.
+    #[default]
+    Always,
+    /// A new entry is only inserted if its key was recently used at least as often as the key of
+    /// the least recently used entry, otherwise the new entry is evicted right away.
+    ///
+    /// How often keys are used is estimated by a count-min sketch, which halves its counts
+    /// periodically so only the recent window of uses is considered. This protects frequently
+    /// used entries from being flushed out by keys which are only used once.
+    TinyLfu,
+}
+
+/// Why an entry was evicted from an LRU queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionCause {
+    /// The entry was evicted to make room for another entry.
+    Capacity,
+    /// The entry expired.
+    Expired,
+    /// The value was replaced by inserting another value for the same key.
+    Replaced,
+    /// The entry was removed without being returned to the caller.
+    Removed,
+}
+
+/// Receives the entries evicted from an LRU queue.
+type EvictFn<K, V> = Box<dyn FnMut(K, V, EvictionCause) + Send + Sync>;
+
+/// Counters of the operations on an LRU queue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of accesses which found an entry.
+    pub hits: u64,
+    /// Number of accesses which found no entry.
+    pub misses: u64,
+    /// Number of inserted entries.
+    pub insertions: u64,
+    /// Number of entries evicted to make room for others.
+    pub evictions: u64,
+    /// Number of entries removed because they expired.
+    pub expirations: u64,
+}
+
+impl Stats {
+    /// Returns the share of accesses which found an entry, or `0.0` if there were none.
+    pub fn hit_ratio(&self) -> f64 {
+        let accesses = self.hits + self.misses;
+        if accesses == 0 {
+            0.0
+        } else {
+            self.hits as f64 / accesses as f64
+        }
+    }
+
+    fn access(&mut self, hit: bool) {
+        if hit {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+    }
+}
+
+/// Update the stats, if they are enabled.
+fn record<F>(stats: &mut Option<Stats>, update: F)
+where
+    F: FnOnce(&mut Stats),
+{
+    if let Some(ref mut stats) = *stats {
+        update(stats);
+    }
+}
+
+/// Computes the weight of an entry.
+type WeighFn<K, V> = Box<dyn Fn(&K, &V) -> u64 + Send + Sync>;
+
+/// Bounds an LRU queue by the total weight of its entries.
+struct Weigher<K, V> {
+    weigh: WeighFn<K, V>,
+    max: u64,
+    total: u64,
 }
 
 impl<K, V> Queue<K, V>
 where
-    K: Clone + Eq + Hash
+    K: Clone + Eq + Hash,
 {
     /// Create a new instance of the queue.
     pub fn new() -> Self {
         Queue {
-            inner: fifo::Queue::new(),
+            inner: policy::Queue::new(),
+            capacity: None,
+            weigher: None,
+            expiration: Tracker::new(),
+            listener: None,
+            stats: Some(Stats::default()),
+            admission: None,
+        }
+    }
+
+    /// Create a new instance of the queue holding at most `capacity` elements.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// assert!(queue.insert(1u8, 'a').is_empty());
+    /// assert!(queue.insert(2u8, 'b').is_empty());
+    /// queue.get(&1);
+    ///
+    /// assert_eq!(vec![(2, 'b')], queue.insert(3u8, 'c'));
+    /// assert_eq!(Some(2), queue.capacity());
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
+        Queue {
+            inner: policy::Queue::from_parts(Deque::with_capacity(capacity), Lru),
+            capacity: Some(capacity),
+            weigher: None,
+            expiration: Tracker::new(),
+            listener: None,
+            stats: Some(Stats::default()),
+            admission: None,
+        }
+    }
+
+    /// Create a new instance of the queue, where the total weight of the entries is at most
+    /// `max_weight`.
+    ///
+    /// The weight of an entry is computed by `weigher` when the entry is inserted and when it is
+    /// removed, so it must not change while the entry is in the queue. An entry heavier than
+    /// `max_weight` evicts all other entries, and is itself evicted by the next insertion.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_weigher(8, |_: &u8, buf: &Vec<u8>| buf.len() as u64);
+    /// queue.insert(1, vec![0; 4]);
+    /// queue.insert(2, vec![0; 3]);
+    ///
+    /// assert_eq!(vec![(1, vec![0; 4])], queue.insert(3, vec![0; 2]));
+    /// assert_eq!(5, queue.weight());
+    ///
+    /// let evicted = queue.insert(4, vec![0; 7]);
+    /// assert_eq!(vec![(2, vec![0; 3]), (3, vec![0; 2])], evicted);
+    /// assert_eq!(7, queue.weight());
+    /// ```
+    pub fn with_weigher<F>(max_weight: u64, weigher: F) -> Self
+    where
+        F: Fn(&K, &V) -> u64 + Send + Sync + 'static,
+    {
+        let mut queue = Queue::new();
+        queue.weigher = Some(Weigher {
+            weigh: Box::new(weigher),
+            max: max_weight,
+            total: 0,
+        });
+        queue
+    }
+}
+
+impl<K, V, S> Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    pub(crate) fn from_parts(inner: Deque<K, V, S>, capacity: Option<usize>) -> Self {
+        Queue {
+            inner: policy::Queue::from_parts(inner, Lru),
+            capacity,
+            weigher: None,
+            expiration: Tracker::new(),
+            listener: None,
+            stats: Some(Stats::default()),
+            admission: None,
+        }
+    }
+
+    /// Returns the admission policy of the queue.
+    pub fn admission(&self) -> Admission {
+        match self.admission {
+            Some(_) => Admission::TinyLfu,
+            None => Admission::Always,
+        }
+    }
+
+    /// Set the admission policy, which applies to entries inserted by [`insert`](#method.insert)
+    /// into a full queue.
+    ///
+    /// The usage counts of TinyLFU are sized for the capacity, or for the number of entries if
+    /// the queue is only bounded by its weight. They are resized when the capacity changes, or
+    /// when the entries of a queue bounded by its weight outgrow them.
+    ///
+    /// ```
+    /// use addressable_queue::lru::{Admission, Queue};
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// queue.set_admission(Admission::TinyLfu);
+    /// queue.insert("hot", 1);
+    /// queue.insert("warm", 2);
+    /// for _ in 0..3 {
+    ///     queue.get(&"hot");
+    /// }
+    /// queue.get(&"warm");
+    ///
+    /// // Used less often than the least recently used entry, so it is not admitted.
+    /// assert_eq!(vec![("once", 3)], queue.insert("once", 3));
+    /// assert!(queue.contains_key(&"hot"));
+    /// ```
+    pub fn set_admission(&mut self, admission: Admission) {
+        self.admission = match admission {
+            Admission::Always => None,
+            Admission::TinyLfu => Some(FrequencySketch::new(self.bound())),
+        };
+    }
+
+    /// Returns the maximum number of elements, or `None` if the queue is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Returns the number of elements in the queue.
+    ///
+    /// This includes expired entries which were not removed yet.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an unexpired element for the specified key.
+    ///
+    /// This does not mark the entry as used.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key) && !self.expiration.is_expired(key)
+    }
+
+    /// Remove all entries, keeping the capacity and configuration of the queue.
+    ///
+    /// The entries are passed to the eviction listener, if there is one.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    /// queue.clear();
+    ///
+    /// assert!(queue.is_empty());
+    /// assert!(!queue.contains_key(&2));
+    /// assert_eq!(Some(2), queue.capacity());
+    /// ```
+    pub fn clear(&mut self) {
+        while let Some((key, value)) = self.inner.remove_head() {
+            self.removed(&key, &value);
+            self.notify(key, value, EvictionCause::Removed);
         }
     }
 
-    // TODO
+    /// Register a listener which receives the entries the queue evicts, instead of dropping or
+    /// returning them.
+    ///
+    /// ```
+    /// use addressable_queue::lru::{EvictionCause, Queue};
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let (sender, receiver) = channel();
+    /// let sender = std::sync::Mutex::new(sender);
+    /// let mut queue = Queue::with_capacity(1).on_evict(move |key, value, cause| {
+    ///     sender.lock().unwrap().send((key, value, cause)).unwrap();
+    /// });
+    /// queue.insert("a", 1);
+    /// queue.insert("a", 2);
+    ///
+    /// assert!(queue.insert("b", 3).is_empty());
+    /// assert_eq!(Ok(("a", 1, EvictionCause::Replaced)), receiver.try_recv());
+    /// assert_eq!(Ok(("a", 2, EvictionCause::Capacity)), receiver.try_recv());
+    /// ```
+    pub fn on_evict<F>(mut self, listener: F) -> Self
+    where
+        F: FnMut(K, V, EvictionCause) + Send + Sync + 'static,
+    {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Returns the counters of the queue, or `None` if they were disabled.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(1);
+    /// queue.insert(2u8, 4u8);
+    /// queue.get(&2);
+    /// queue.get(&3);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// let stats = queue.stats().unwrap();
+    /// assert_eq!((1, 1, 2, 1), (stats.hits, stats.misses, stats.insertions, stats.evictions));
+    /// assert_eq!(0.5, stats.hit_ratio());
+    /// ```
+    pub fn stats(&self) -> Option<Stats> {
+        self.stats
+    }
+
+    /// Reset all counters to zero.
+    pub fn reset_stats(&mut self) {
+        record(&mut self.stats, |stats| *stats = Stats::default());
+    }
+
+    /// Disable the counters, so [`stats`](#method.stats) returns `None`.
+    pub fn without_stats(mut self) -> Self {
+        self.stats = None;
+        self
+    }
+
+    /// Returns the total weight of the entries, which is `0` unless the queue has a weigher.
+    pub fn weight(&self) -> u64 {
+        self.weigher.as_ref().map_or(0, |weigher| weigher.total)
+    }
+
+    /// Returns the maximum total weight of the entries, or `None` if the queue has no weigher.
+    pub fn max_weight(&self) -> Option<u64> {
+        self.weigher.as_ref().map(|weigher| weigher.max)
+    }
+
+    /// Change the maximum number of elements, and return the entries evicted to fit the new
+    /// capacity, least recently used first.
+    ///
+    /// The evicted entries are passed to the eviction listener instead, if there is one.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    /// queue.insert(4u8, 8u8);
+    ///
+    /// assert_eq!(queue.set_capacity(1), vec![(2, 4), (3, 6)]);
+    /// assert_eq!(queue.set_capacity(4), vec![]);
+    /// assert_eq!(Some(4), queue.capacity());
+    /// ```
+    pub fn set_capacity(&mut self, capacity: usize) -> Vec<(K, V)> {
+        self.capacity = Some(capacity);
+        if let Some(ref mut sketch) = self.admission {
+            sketch.resize(capacity);
+        }
+        let mut evicted = Vec::new();
+        while self.inner.len() > capacity {
+            match self.evict() {
+                Some((key, value)) => {
+                    evicted.extend(self.notify(key, value, EvictionCause::Capacity));
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+
     /// Access an entry. If it exists it will also be moved to the end of the queue.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// assert_eq!(Some(&4), queue.get(&2));
+    /// assert_eq!(None, queue.get(&4));
+    /// assert_eq!(Some((3, 6)), queue.remove_head());
+    /// assert_eq!(Some((2, 4)), queue.remove_head());
+    /// ```
     pub fn get(&mut self, key: &K) -> Option<&V> {
-        self.inner.remove_key(key).map(|item| {
-            let item_ref = &item;
-            self.insert(key.clone(), item);
-            item_ref
-        })
+        self.sample(key);
+        if self.expire(key) {
+            record(&mut self.stats, |stats| stats.misses += 1);
+            return None;
+        }
+        self.expiration.touch(key);
+        let value = self.inner.get(key);
+        let hit = value.is_some();
+        record(&mut self.stats, |stats| stats.access(hit));
+        value
     }
 
-    /// Insert an entry at the end of the queue.
-    pub fn insert(&mut self, key: K, value: V) {
-        self.inner.insert(key, value);
+    /// Access an entry mutably. If it exists it will also be moved to the end of the queue.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert("a", 0u32);
+    /// queue.insert("b", 0u32);
+    ///
+    /// *queue.get_mut(&"a").unwrap() += 1;
+    /// assert_eq!(Some(("b", 0)), queue.remove_head());
+    /// assert_eq!(Some(("a", 1)), queue.remove_head());
+    /// ```
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.sample(key);
+        if self.expire(key) {
+            record(&mut self.stats, |stats| stats.misses += 1);
+            return None;
+        }
+        self.expiration.touch(key);
+        let value = self.inner.get_mut(key);
+        let hit = value.is_some();
+        record(&mut self.stats, |stats| stats.access(hit));
+        value
     }
 
-    /// Insert an entry at the beginning of the queue.
-    pub fn insert_head(&mut self, key: K, value: V) {
-        self.inner.insert(key, value);
+    /// Access an entry, inserting the value returned by `init` if there is none.
+    ///
+    /// The key is only looked up once. If the queue is full, inserting evicts the least recently
+    /// used entries, which are passed to the eviction listener. Without a listener they are
+    /// dropped, use [`insert`](#method.insert) to get them back instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity of the queue is zero.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// *queue.get_or_insert_with("a", || 0u32) += 1;
+    /// *queue.get_or_insert_with("b", || 0u32) += 1;
+    /// *queue.get_or_insert_with("a", || 0u32) += 1;
+    /// *queue.get_or_insert_with("c", || 0u32) += 1;
+    ///
+    /// assert_eq!(None, queue.peek(&"b"));
+    /// assert_eq!(Some(&2), queue.peek(&"a"));
+    /// ```
+    pub fn get_or_insert_with<F>(&mut self, key: K, init: F) -> &mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self.try_get_or_insert_with(key, || Ok::<V, Infallible>(init())) {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
     }
 
-    /// Remove the current head of the queue, and return the value if there was one.
-    pub fn remove_head(&mut self) -> Option<V> {
-        self.inner.remove_head()
+    /// Access an entry, inserting the value returned by `init` if there is none.
+    ///
+    /// If `init` fails, the error is returned and the queue is not modified. Evicted entries are
+    /// passed to the eviction listener or dropped, like they are by
+    /// [`get_or_insert_with`](#method.get_or_insert_with).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity of the queue is zero.
+    pub fn try_get_or_insert_with<F, E>(&mut self, key: K, init: F) -> Result<&mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        self.sample(&key);
+        if !self.expire(&key) && self.inner.touch(&key) {
+            self.expiration.touch(&key);
+            record(&mut self.stats, |stats| stats.hits += 1);
+        } else {
+            record(&mut self.stats, |stats| stats.misses += 1);
+            let value = init()?;
+            assert!(
+                self.capacity != Some(0),
+                "cannot insert into an LRU queue with zero capacity"
+            );
+            self.insert_admitted(key.clone(), value);
+        }
+        Ok(self.inner.peek_mut(&key).unwrap())
+    }
+
+    /// Mark an entry as the most recently used one without accessing it.
+    ///
+    /// Returns false if there is no entry for the key.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    /// queue.insert(4u8, 8u8);
+    ///
+    /// assert!(queue.touch(&2));
+    /// assert!(queue.demote(&4));
+    /// assert!(!queue.touch(&5));
+    /// assert_eq!(queue.keys().collect::<Vec<_>>(), vec![&4, &3, &2]);
+    /// ```
+    pub fn touch(&mut self, key: &K) -> bool {
+        if self.expire(key) || !self.inner.touch(key) {
+            return false;
+        }
+        self.expiration.touch(key);
+        true
+    }
+
+    /// Mark an entry as the least recently used one, so it is evicted next.
+    ///
+    /// Returns false if there is no entry for the key, or if it expired.
+    pub fn demote(&mut self, key: &K) -> bool {
+        !self.expire(key) && self.inner.move_to_front(key)
+    }
+
+    /// Access several entries at once, which marks them as used in the given order.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert('a', 1);
+    /// queue.insert('b', 2);
+    /// queue.insert('c', 3);
+    ///
+    /// assert_eq!([Some(&2), None, Some(&1)], queue.get_many([&'b', &'d', &'a']));
+    /// assert_eq!(Some(('c', 3)), queue.pop_lru());
+    /// assert_eq!(Some(('b', 2)), queue.pop_lru());
+    /// ```
+    pub fn get_many<const N: usize>(&mut self, keys: [&K; N]) -> [Option<&V>; N] {
+        for key in keys.iter() {
+            let hit = self.touch(key);
+            record(&mut self.stats, |stats| stats.access(hit));
+        }
+        let inner = &self.inner;
+        keys.map(move |key| inner.peek(key))
+    }
+
+    /// Access several entries mutably at once, which marks them as used in the given order.
+    ///
+    /// Returns `None` if any key has no entry, or if a key is given more than once.
+    pub fn get_many_mut<const N: usize>(&mut self, keys: [&K; N]) -> Option<[&mut V; N]> {
+        for key in keys.iter() {
+            let hit = self.touch(key);
+            record(&mut self.stats, |stats| stats.access(hit));
+        }
+        self.inner.inner.get_many_mut(keys)
+    }
+
+    /// Returns the entry for the key, for in-place manipulation.
+    ///
+    /// Getting the entry does not mark it as used, only inserting into it or converting it into
+    /// a mutable reference does.
+    ///
+    /// ```
+    /// use addressable_queue::lru::{Entry, Queue};
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// *queue.entry("a").or_insert(0u32) += 1;
+    /// *queue.entry("b").or_insert(0u32) += 1;
+    /// *queue.entry("a").or_insert(0u32) += 1;
+    ///
+    /// if let Entry::Occupied(entry) = queue.entry("b") {
+    ///     assert_eq!(&1, entry.get());
+    /// }
+    /// assert_eq!(Some(("b", 1)), queue.pop_lru());
+    /// assert_eq!(Some(("a", 2)), queue.pop_lru());
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        self.sample(&key);
+        let occupied = !self.expire(&key) && self.inner.contains_key(&key);
+        record(&mut self.stats, |stats| stats.access(occupied));
+        if occupied {
+            Entry::Occupied(OccupiedEntry { queue: self, key })
+        } else {
+            Entry::Vacant(VacantEntry { queue: self, key })
+        }
+    }
+
+    /// Returns a reference to the value of the specified key, without marking it as used.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// assert_eq!(Some(&4), queue.peek(&2));
+    /// assert_eq!(Some((&2, &4)), queue.peek_lru());
+    /// assert_eq!(Some((&3, &6)), queue.peek_mru());
+    /// ```
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        if self.expiration.is_expired(key) {
+            return None;
+        }
+        self.inner.peek(key)
+    }
+
+    /// Returns the least recently used entry which did not expire, without marking it as used.
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    /// Returns the most recently used entry which did not expire.
+    pub fn peek_mru(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    /// Returns an iterator over the entries which did not expire, from least to most recently
+    /// used.
+    ///
+    /// Iterating does not mark entries as used. Use `.rev()` to start at the most recently used
+    /// entry.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    /// queue.get(&2);
+    ///
+    /// let pairs: Vec<_> = queue.iter().rev().collect();
+    /// assert_eq!(pairs, vec![(&2, &4), (&3, &6)]);
+    /// assert_eq!(queue.keys().collect::<Vec<_>>(), vec![&3, &2]);
+    /// assert_eq!(queue.values().collect::<Vec<_>>(), vec![&6, &4]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.inner.inner.iter(),
+            expiration: &self.expiration,
+        }
     }
 
-    /// Remove the current tail of the queue, and return the value if there was one.
-    pub fn remove_tail(&mut self) -> Option<V> {
-        self.inner.remove_tail()
+    /// Returns an iterator over the keys from least to most recently used.
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Returns an iterator over the values from least to most recently used.
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    /// Convert the queue into a vec, from the least to the most recently used entry.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    /// queue.get(&2);
+    ///
+    /// assert_eq!(queue.into_vec(), vec![(3, 6), (2, 4)]);
+    /// ```
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+
+    /// Returns a [`Snapshot`](../persist/struct.Snapshot.html) of the entries from least to most
+    /// recently used, the capacity and the admission policy.
+    ///
+    /// Like serializing the queue, the weigher, expiration and eviction listener are not
+    /// included.
+    pub fn snapshot(&self) -> Snapshot<K, V>
+    where
+        V: Clone,
+    {
+        let entries = self
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Snapshot {
+            capacity: self.capacity,
+            admission: Some(self.admission()),
+            ..Snapshot::new(entries)
+        }
+    }
+
+    /// Create a queue from a snapshot, evicting the least recently used entries beyond its
+    /// capacity.
+    pub fn restore(snapshot: Snapshot<K, V>) -> Self
+    where
+        S: Default,
+    {
+        let mut inner = Deque::default();
+        inner.extend(snapshot.entries);
+        let mut queue = Queue::from_parts(inner, snapshot.capacity);
+        if let Some(capacity) = snapshot.capacity {
+            queue.set_capacity(capacity);
+        }
+        if let Some(admission) = snapshot.admission {
+            queue.set_admission(admission);
+        }
+        queue
+    }
+
+    /// Insert an entry as the most recently used one.
+    ///
+    /// If the key is already contained, its value is updated, the entry is marked as the most
+    /// recently used one, and the old entry is returned. Otherwise, if the queue is full, the
+    /// least recently used entry is evicted and returned. If the queue has a weigher, least
+    /// recently used entries are evicted until the total weight fits.
+    ///
+    /// Returns the replaced and the evicted entries in the order they left the queue, unless
+    /// there is an eviction listener, which they are passed to instead.
+    ///
+    /// With the [`TinyLfu`](enum.Admission.html#variant.TinyLfu) admission policy, a new entry
+    /// which is not admitted into the full queue is returned or passed to the listener itself.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// queue.insert('a', 1);
+    /// queue.insert('b', 2);
+    ///
+    /// assert_eq!(vec![('a', 1)], queue.insert('a', 3));
+    /// assert_eq!(vec![('b', 2)], queue.insert('c', 4));
+    /// assert_eq!(queue.into_vec(), vec![('a', 3), ('c', 4)]);
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        self.sample(&key);
+        if !self.admit(&key, &value) {
+            return self.reject(key, value);
+        }
+        self.insert_admitted(key, value)
+    }
+
+    /// Insert an entry, bypassing the admission policy.
+    fn insert_admitted(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        if self.capacity == Some(0) {
+            return self.reject(key, value);
+        }
+        let inserted = key.clone();
+        let mut returned: Vec<_> = self.store(key, value).into_iter().collect();
+        while let Some((key, value)) = self.evict_excess(Some(&inserted)) {
+            returned.extend(self.notify(key, value, EvictionCause::Capacity));
+        }
+        returned
+    }
+
+    /// Insert several entries, and evict the entries exceeding the capacity or maximum weight
+    /// only once all of them are inserted.
+    ///
+    /// Returns the replaced and the evicted entries in the order they left the queue, unless
+    /// there is an eviction listener.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// queue.insert('a', 1);
+    ///
+    /// let evicted = queue.insert_many(vec![('b', 2), ('c', 3)]);
+    /// assert_eq!(vec![('a', 1)], evicted);
+    /// assert_eq!(vec![(&'b', &2), (&'c', &3)], queue.iter().collect::<Vec<_>>());
+    /// ```
+    pub fn insert_many<I>(&mut self, entries: I) -> Vec<(K, V)>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut returned: Vec<_> = entries
+            .into_iter()
+            .filter_map(|(key, value)| self.store(key, value))
+            .collect();
+        while let Some((key, value)) = self.evict_excess(None) {
+            returned.extend(self.notify(key, value, EvictionCause::Capacity));
+        }
+        returned
+    }
+
+    /// Insert an entry as the least recently used one, so it is evicted next.
+    ///
+    /// Otherwise this behaves like [`insert`](#method.insert), except that a full queue evicts
+    /// its least recently used entry before the new one is inserted.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::with_capacity(2);
+    /// queue.insert('a', 1);
+    /// queue.insert('b', 2);
+    ///
+    /// assert_eq!(vec![('a', 1)], queue.insert_head('c', 3));
+    /// assert_eq!(Some(('c', 3)), queue.pop_lru());
+    /// ```
+    pub fn insert_head(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        if self.capacity == Some(0) {
+            return self.reject(key, value);
+        }
+        let full = self.capacity.is_some_and(|capacity| self.len() >= capacity);
+        let mut returned = Vec::new();
+        if full && !self.inner.contains_key(&key) {
+            if let Some((key, value)) = self.evict() {
+                returned.extend(self.notify(key, value, EvictionCause::Capacity));
+            }
+        }
+        returned.extend(self.store(key.clone(), value));
+        while let Some((key, value)) = self.evict_excess(Some(&key)) {
+            returned.extend(self.notify(key, value, EvictionCause::Capacity));
+        }
+        self.inner.move_to_front(&key);
+        returned
+    }
+
+    /// Remove the least recently used entry, and return it if there was one.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    /// queue.insert(4u8, 8u8);
+    /// queue.get(&2);
+    ///
+    /// assert_eq!(Some((3, 6)), queue.pop_lru());
+    /// assert_eq!(Some((2, 4)), queue.pop_mru());
+    /// ```
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        let (key, value) = self.inner.remove_head()?;
+        self.removed(&key, &value);
+        Some((key, value))
+    }
+
+    /// Remove the most recently used entry, and return it if there was one.
+    pub fn pop_mru(&mut self) -> Option<(K, V)> {
+        let (key, value) = self.inner.remove_tail()?;
+        self.removed(&key, &value);
+        Some((key, value))
+    }
+
+    /// Remove the current head of the queue, that is the least recently used entry, and return it
+    /// if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        self.pop_lru()
+    }
+
+    /// Remove the current tail of the queue, that is the most recently used entry, and return it
+    /// if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        self.pop_mru()
     }
 
     /// Remove a value by specifying its key.
     pub fn remove_key(&mut self, key: &K) -> Option<V> {
-        self.inner.remove_key(key)
+        let value = self.inner.remove_key(key)?;
+        self.removed(key, &value);
+        Some(value)
+    }
+
+    /// Expire entries which were inserted more than `ttl` ago, or disable this if `None`.
+    ///
+    /// This applies to all entries which were not inserted with their own expiry.
+    #[cfg(feature = "std")]
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        let expiry = Expiry {
+            ttl,
+            tti: self.expiration.expiry().tti,
+        };
+        self.set_expiry(expiry);
+    }
+
+    /// Expire entries which were last accessed more than `tti` ago, or disable this if `None`.
+    ///
+    /// This applies to all entries which were not inserted with their own expiry.
+    ///
+    /// ```
+    /// use addressable_queue::lru::Queue;
+    /// use std::thread::sleep;
+    /// use std::time::Duration;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.set_tti(Some(Duration::from_millis(200)));
+    /// queue.insert("a", 1u8);
+    /// queue.insert("b", 2u8);
+    ///
+    /// sleep(Duration::from_millis(120));
+    /// assert_eq!(Some(&1), queue.get(&"a"));
+    /// sleep(Duration::from_millis(120));
+    ///
+    /// assert_eq!(None, queue.get(&"b"));
+    /// assert_eq!(Some(&1), queue.get(&"a"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn set_tti(&mut self, tti: Option<Duration>) {
+        let expiry = Expiry {
+            ttl: self.expiration.expiry().ttl,
+            tti,
+        };
+        self.set_expiry(expiry);
+    }
+
+    /// Read the current time from `clock` for expiring entries, instead of from the system.
+    #[cfg(feature = "std")]
+    pub fn set_clock<C>(&mut self, clock: C)
+    where
+        C: Clock + 'static,
+    {
+        self.expiration.set_clock(Box::new(clock));
+    }
+
+    /// Insert an entry at the end of the queue, which expires according to `expiry` instead of
+    /// the expiry of the queue.
+    ///
+    /// ```
+    /// use addressable_queue::lru::{Expiry, Queue};
+    /// use std::time::Duration;
+    ///
+    /// let mut queue = Queue::new();
+    /// let expiry = Expiry {
+    ///     ttl: Some(Duration::from_secs(0)),
+    ///     tti: None,
+    /// };
+    /// queue.insert_with_expiry("a", 1u8, expiry);
+    /// queue.insert("b", 2u8);
+    ///
+    /// assert_eq!(queue.purge_expired(), vec![("a", 1)]);
+    /// assert_eq!(Some(&2), queue.get(&"b"));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn insert_with_expiry(&mut self, key: K, value: V, expiry: Expiry) -> Vec<(K, V)> {
+        self.expiration
+            .enable(self.inner.inner.iter().map(|(key, _)| key));
+        let stamped = key.clone();
+        let evicted = self.insert(key, value);
+        if self.inner.contains_key(&stamped) {
+            self.expiration.stamp_with(&stamped, Some(expiry));
+        }
+        evicted
+    }
+
+    /// Remove all expired entries, and return them from least to most recently used.
+    #[cfg(feature = "std")]
+    pub fn purge_expired(&mut self) -> Vec<(K, V)> {
+        let expired: Vec<K> = self
+            .inner
+            .inner
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| self.expiration.is_expired(key))
+            .cloned()
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|key| {
+                let value = self.remove_key(&key)?;
+                record(&mut self.stats, |stats| stats.expirations += 1);
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    /// Reclaim the slots of all removed elements now, instead of once there are more of them
+    /// than the compaction threshold allows.
+    pub fn compact(&mut self) {
+        self.inner.inner.compact();
+    }
+
+    #[cfg(feature = "std")]
+    fn set_expiry(&mut self, expiry: Expiry) {
+        self.expiration
+            .set_expiry(expiry, self.inner.inner.iter().map(|(key, _)| key));
+    }
+
+    /// Update the bookkeeping after an entry was removed from the queue.
+    fn removed(&mut self, key: &K, value: &V) {
+        self.expiration.remove(key);
+        if let Some(ref mut weigher) = self.weigher {
+            weigher.total = weigher.total.saturating_sub((weigher.weigh)(key, value));
+        }
+    }
+
+    /// Count a use of the key for the admission policy.
+    fn sample(&mut self, key: &K) {
+        if let Some(ref mut sketch) = self.admission {
+            sketch.increment(self.inner.inner.raw.index.hasher().hash_one(key));
+        }
+    }
+
+    /// Returns the number of entries the queue is bounded by, which is its capacity or, if it is
+    /// only bounded by its weight, the number of entries it holds.
+    fn bound(&self) -> usize {
+        self.capacity.unwrap_or_else(|| self.inner.len())
+    }
+
+    /// Returns whether a new entry may evict the least recently used entry.
+    fn admit(&self, key: &K, value: &V) -> bool {
+        let sketch = match self.admission {
+            Some(ref sketch) => sketch,
+            None => return true,
+        };
+        let full = self.capacity.is_some_and(|capacity| self.len() >= capacity)
+            || self.weigher.as_ref().is_some_and(|weigher| {
+                weigher.total.saturating_add((weigher.weigh)(key, value)) > weigher.max
+            });
+        if !full || self.inner.contains_key(key) {
+            return true;
+        }
+        let hasher = self.inner.inner.raw.index.hasher();
+        match self.inner.inner.peek_front() {
+            Some((victim, _)) => {
+                sketch.frequency(hasher.hash_one(key)) >= sketch.frequency(hasher.hash_one(victim))
+            }
+            None => true,
+        }
+    }
+
+    /// Insert an entry as the most recently used one, without evicting others.
+    ///
+    /// Returns the old entry of the key, unless it was passed to the eviction listener.
+    fn store(&mut self, key: K, value: V) -> Option<(K, V)> {
+        let replaced = match self.inner.remove_key(&key) {
+            Some(old) => {
+                self.removed(&key, &old);
+                self.notify(key.clone(), old, EvictionCause::Replaced)
+            }
+            None => None,
+        };
+        record(&mut self.stats, |stats| stats.insertions += 1);
+        if let Some(ref mut weigher) = self.weigher {
+            weigher.total = weigher.total.saturating_add((weigher.weigh)(&key, &value));
+        }
+        self.expiration.stamp(&key);
+        self.inner.insert(key, value);
+        if self.capacity.is_none() {
+            let len = self.inner.len();
+            if let Some(ref mut sketch) = self.admission {
+                sketch.grow(len);
+            }
+        }
+        replaced
+    }
+
+    /// Evict the least recently used entry if the queue exceeds its capacity or maximum weight.
+    ///
+    /// The entry of `inserted` is never evicted, so an entry heavier than the maximum weight
+    /// evicts all others and stays until the next insertion.
+    fn evict_excess(&mut self, inserted: Option<&K>) -> Option<(K, V)> {
+        let over_capacity = self.capacity.is_some_and(|capacity| self.len() > capacity);
+        let over_weight = self.weight() > self.max_weight().unwrap_or(u64::MAX);
+        let protected = match (inserted, self.inner.inner.peek_front()) {
+            (Some(inserted), Some((victim, _))) => victim == inserted,
+            _ => false,
+        };
+        if (over_capacity || over_weight) && !protected {
+            self.evict()
+        } else {
+            None
+        }
+    }
+
+    /// Evict the least recently used entry.
+    fn evict(&mut self) -> Option<(K, V)> {
+        let (key, value) = self.inner.remove_victim()?;
+        self.removed(&key, &value);
+        record(&mut self.stats, |stats| stats.evictions += 1);
+        Some((key, value))
+    }
+
+    /// Evict a new entry instead of inserting it.
+    fn reject(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        record(&mut self.stats, |stats| stats.evictions += 1);
+        self.notify(key, value, EvictionCause::Capacity)
+            .into_iter()
+            .collect()
+    }
+
+    /// Remove the entry of the key if it expired, and return whether it did.
+    fn expire(&mut self, key: &K) -> bool {
+        if !self.expiration.is_expired(key) {
+            return false;
+        }
+        if let Some(value) = self.remove_key(key) {
+            record(&mut self.stats, |stats| stats.expirations += 1);
+            self.notify(key.clone(), value, EvictionCause::Expired);
+        }
+        true
+    }
+
+    /// Pass an entry the queue is about to drop to the eviction listener, or hand it back if
+    /// there is none.
+    fn notify(&mut self, key: K, value: V, cause: EvictionCause) -> Option<(K, V)> {
+        match self.listener {
+            Some(ref mut listener) => {
+                listener(key, value, cause);
+                None
+            }
+            None => Some((key, value)),
+        }
+    }
+}
+
+impl<K, V, S> IntoIterator for Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (K, V);
+    type IntoIter = vec::IntoIter<(K, V)>;
+
+    /// Consume the queue, yielding the entries from least to most recently used.
+    fn into_iter(self) -> Self::IntoIter {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Insert the entries like [`insert_many`](struct.Queue.html#method.insert_many), dropping
+    /// the evicted entries.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, entries: I) {
+        self.insert_many(entries);
+    }
+}
+
+impl<K, V, S> Default for Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Queue {
+            inner: policy::Queue::default(),
+            capacity: None,
+            weigher: None,
+            expiration: Tracker::new(),
+            listener: None,
+            stats: Some(Stats::default()),
+            admission: None,
+        }
+    }
+}
+
+impl<K, V, S> From<Deque<K, V, S>> for Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    fn from(inner: Deque<K, V, S>) -> Self {
+        Queue::from_parts(inner, None)
+    }
+}
+
+/// Iterator over the entries of an LRU queue which did not expire.
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: deque::Iter<'a, K, V>,
+    expiration: &'a Tracker<K>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let expiration = self.expiration;
+        self.inner.find(|&(key, _)| !expiration.is_expired(key))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.inner.size_hint() {
+            (len, upper) if !self.expiration.is_tracking() => (len, upper),
+            (_, upper) => (0, upper),
+        }
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let expiration = self.expiration;
+        self.inner.rfind(|&(key, _)| !expiration.is_expired(key))
+    }
+}
+
+/// An entry of an LRU queue, which is either occupied or vacant.
+///
+/// Created by [`Queue::entry`](struct.Queue.html#method.entry).
+pub enum Entry<'a, K: 'a, V: 'a, S: 'a = DefaultHashBuilder> {
+    /// There is an entry for the key.
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    /// There is no entry for the key.
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &K {
+        match *self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// Mark the entry as used if it is occupied, otherwise insert `default`, and return a mutable
+    /// reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Mark the entry as used if it is occupied, otherwise insert the value returned by `init`,
+    /// and return a mutable reference to the value.
+    pub fn or_insert_with<F>(self, init: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(init()),
+        }
+    }
+
+    /// Modify the value of an occupied entry without marking it as used.
+    pub fn and_modify<F>(mut self, modify: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        if let Entry::Occupied(ref mut entry) = self {
+            modify(entry.get_mut());
+        }
+        self
+    }
+}
+
+/// An occupied entry of an LRU queue.
+pub struct OccupiedEntry<'a, K: 'a, V: 'a, S: 'a = DefaultHashBuilder> {
+    queue: &'a mut Queue<K, V, S>,
+    key: K,
+}
+
+impl<'a, K, V, S> OccupiedEntry<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns a reference to the value, without marking the entry as used.
+    pub fn get(&self) -> &V {
+        self.queue.inner.peek(&self.key).unwrap()
+    }
+
+    /// Returns a mutable reference to the value, without marking the entry as used.
+    pub fn get_mut(&mut self) -> &mut V {
+        self.queue.inner.peek_mut(&self.key).unwrap()
+    }
+
+    /// Mark the entry as the most recently used one.
+    ///
+    /// The entry was found when it was looked up, so it is not checked for expiry again.
+    pub fn promote(&mut self) {
+        self.queue.inner.touch(&self.key);
+        self.queue.expiration.touch(&self.key);
+    }
+
+    /// Mark the entry as used, and convert it into a mutable reference to the value.
+    pub fn into_mut(mut self) -> &'a mut V {
+        self.promote();
+        self.queue.inner.peek_mut(&self.key).unwrap()
+    }
+
+    /// Replace the value without marking the entry as used, and return the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        ::core::mem::replace(self.get_mut(), value)
+    }
+
+    /// Remove the entry, and return its value.
+    pub fn remove(self) -> V {
+        self.queue.remove_key(&self.key).unwrap()
+    }
+}
+
+/// A vacant entry of an LRU queue.
+pub struct VacantEntry<'a, K: 'a, V: 'a, S: 'a = DefaultHashBuilder> {
+    queue: &'a mut Queue<K, V, S>,
+    key: K,
+}
+
+impl<'a, K, V, S> VacantEntry<'a, K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Insert a value as the most recently used entry, and return a mutable reference to it.
+    ///
+    /// If the queue is full, the least recently used entries are evicted and passed to the
+    /// eviction listener. Without a listener they are dropped, use
+    /// [`Queue::insert`](struct.Queue.html#method.insert) to get them back instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity of the queue is zero.
+    pub fn insert(self, value: V) -> &'a mut V {
+        assert!(
+            self.queue.capacity != Some(0),
+            "cannot insert into an LRU queue with zero capacity"
+        );
+        self.queue.insert_admitted(self.key.clone(), value);
+        self.queue.inner.peek_mut(&self.key).unwrap()
+    }
+}
+
+/// Iterator over the keys of an LRU queue.
+pub struct Keys<'a, K: 'a, V: 'a> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
+
+/// Iterator over the values of an LRU queue.
+pub struct Values<'a, K: 'a, V: 'a> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, value)| value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn next_back(&mut self) -> Option<&'a V> {
+        self.inner.next_back().map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn oversize_entries_are_kept_by_their_own_insertion() {
+    let mut queue = Queue::with_weigher(4, |_: &u8, buf: &Vec<u8>| buf.len() as u64);
+    assert_eq!(10, queue.get_or_insert_with(1, || vec![0; 10]).len());
+
+    queue.insert(2, vec![0; 1]);
+    assert_eq!(None, queue.peek(&1));
+    assert_eq!(
+        10,
+        queue
+            .try_get_or_insert_with(3, || Ok::<_, ()>(vec![3; 10]))
+            .unwrap()
+            .len()
+    );
+    assert_eq!(vec![3], queue.keys().cloned().collect::<Vec<_>>());
+
+    assert_eq!(&mut vec![4; 10], queue.entry(4).or_insert(vec![4; 10]));
+    assert_eq!(vec![4], queue.keys().cloned().collect::<Vec<_>>());
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn entries_evicted_by_getting_or_inserting_are_reported() {
+    use std::sync::{Arc, Mutex};
+
+    let mut queue = Queue::with_capacity(1);
+    queue.insert(1u8, 1u8);
+    assert_eq!(&mut 2, queue.get_or_insert_with(2, || 2));
+    assert_eq!(&mut 3, queue.entry(3).or_insert(3));
+    assert_eq!(2, queue.stats().unwrap().evictions);
+
+    let listened = Arc::new(Mutex::new(Vec::new()));
+    let sink = listened.clone();
+    let mut queue = Queue::with_capacity(1)
+        .on_evict(move |key, value, cause| sink.lock().unwrap().push((key, value, cause)));
+    queue.insert(1u8, 1u8);
+    queue.get_or_insert_with(2, || 2);
+    queue.entry(3).or_insert(3);
+    assert_eq!(
+        vec![
+            (1, 1, EvictionCause::Capacity),
+            (2, 2, EvictionCause::Capacity)
+        ],
+        *listened.lock().unwrap()
+    );
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn occupied_entries_do_not_expire_while_held() {
+    use time::MockClock;
+
+    let clock = MockClock::new();
+    let mut queue = Queue::new();
+    queue.set_clock(clock.clone());
+    queue.set_ttl(Some(Duration::from_secs(10)));
+    queue.insert("a", 1);
+    clock.advance(Duration::from_secs(5));
+    queue.insert("b", 2);
+
+    let mut entry = match queue.entry("a") {
+        Entry::Occupied(entry) => entry,
+        Entry::Vacant(_) => panic!("entry of a is vacant"),
+    };
+    clock.advance(Duration::from_secs(6));
+    *entry.get_mut() += 10;
+    entry.promote();
+    assert_eq!(&mut 11, entry.into_mut());
+    let order: Vec<_> = queue.inner.inner.iter().map(|(key, _)| *key).collect();
+    assert_eq!(vec!["b", "a"], order);
+    assert_eq!(vec!["b"], queue.keys().cloned().collect::<Vec<_>>());
+}
+
+#[cfg(test)]
+#[test]
+fn weights_saturate_instead_of_overflowing() {
+    let mut queue = Queue::with_weigher(u64::MAX, |_: &u8, weight: &u64| *weight);
+    queue.insert(1, u64::MAX);
+
+    assert!(queue.insert(2, 1).is_empty());
+    assert_eq!(u64::MAX, queue.weight());
+}
+
+#[cfg(test)]
+#[test]
+fn oversize_entries_inserted_at_the_head_stay_at_the_head() {
+    let mut queue = Queue::with_weigher(4, |_: &u8, buf: &Vec<u8>| buf.len() as u64);
+    queue.insert(1, vec![0; 2]);
+    queue.insert(2, vec![0; 2]);
+
+    let evicted = queue.insert_head(3, vec![0; 5]);
+    assert_eq!(vec![(1, vec![0; 2]), (2, vec![0; 2])], evicted);
+    assert_eq!(vec![3], queue.keys().cloned().collect::<Vec<_>>());
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn shrinking_passes_evicted_entries_to_the_listener() {
+    use std::sync::{Arc, Mutex};
+
+    let listened = Arc::new(Mutex::new(Vec::new()));
+    let sink = listened.clone();
+    let mut queue = Queue::with_weigher(10, |_: &u8, value: &u8| u64::from(*value))
+        .on_evict(move |key, value, cause| sink.lock().unwrap().push((key, value, cause)));
+    queue.insert(1, 1);
+    queue.insert(2, 2);
+    queue.insert(3, 3);
+
+    assert!(queue.set_capacity(1).is_empty());
+    assert_eq!(
+        vec![
+            (1, 1, EvictionCause::Capacity),
+            (2, 2, EvictionCause::Capacity)
+        ],
+        *listened.lock().unwrap()
+    );
+    assert_eq!(3, queue.weight());
+    assert_eq!(2, queue.stats().unwrap().evictions);
+}
+
+#[cfg(all(test, feature = "std"))]
+#[test]
+fn views_skip_expired_entries() {
+    use time::MockClock;
+
+    let clock = MockClock::new();
+    let mut queue = Queue::new();
+    queue.set_clock(clock.clone());
+    queue.set_ttl(Some(Duration::from_secs(10)));
+    queue.insert("a", 1);
+    clock.advance(Duration::from_secs(5));
+    queue.insert("b", 2);
+    queue.insert("c", 3);
+    queue.demote(&"b");
+    queue.get(&"a");
+    clock.advance(Duration::from_secs(5));
+
+    assert_eq!(Some((&"b", &2)), queue.peek_lru());
+    assert_eq!(Some((&"c", &3)), queue.peek_mru());
+    assert_eq!(vec![&"b", &"c"], queue.keys().collect::<Vec<_>>());
+    assert!(!queue.demote(&"a"));
+    assert_eq!(2, queue.len());
+}
+
+#[cfg(test)]
+#[test]
+fn tiny_lfu_applies_to_queues_full_by_weight() {
+    let mut queue = Queue::with_weigher(2, |_: &&str, _: &u8| 1);
+    queue.set_admission(Admission::TinyLfu);
+    queue.insert("hot", 1);
+    queue.insert("warm", 2);
+    for _ in 0..3 {
+        queue.get(&"hot");
+    }
+    queue.get(&"warm");
+
+    assert_eq!(vec![("once", 3)], queue.insert("once", 3));
+    assert_eq!(
+        vec!["hot", "warm"],
+        queue.keys().cloned().collect::<Vec<_>>()
+    );
+}
+
+#[cfg(feature = "serde")]
+mod serde_compat {
+    use super::Queue;
+    use core::hash::{BuildHasher, Hash};
+    use deque::Deque;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// The capacity and the entries from least to most recently used are serialized, but not the
+    /// weigher, expiration or eviction listener. Expired entries are skipped.
+    impl<K, V, H> Serialize for Queue<K, V, H>
+    where
+        K: Serialize + Clone + Eq + Hash,
+        V: Serialize,
+        H: BuildHasher,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            (self.capacity, Entries(self)).serialize(serializer)
+        }
+    }
+
+    /// Serializes the entries of a queue which did not expire, like a deque of them.
+    struct Entries<'a, K: 'a, V: 'a, H: 'a>(&'a Queue<K, V, H>);
+
+    impl<'a, K, V, H> Serialize for Entries<'a, K, V, H>
+    where
+        K: Serialize + Clone + Eq + Hash,
+        V: Serialize,
+        H: BuildHasher,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.collect_seq(self.0.iter())
+        }
+    }
+
+    impl<'de, K, V, H> Deserialize<'de> for Queue<K, V, H>
+    where
+        K: Deserialize<'de> + Clone + Eq + Hash,
+        V: Deserialize<'de>,
+        H: BuildHasher + Default,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let (capacity, inner): (Option<usize>, Deque<K, V, H>) =
+                Deserialize::deserialize(deserializer)?;
+            let mut queue = Queue::from_parts(inner, capacity);
+            if let Some(capacity) = capacity {
+                queue.set_capacity(capacity);
+            }
+            Ok(queue)
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn serde_test() {
+        use serde_json;
+        let mut queue = Queue::with_capacity(3);
+        queue.insert(2u8, 4u8);
+        queue.insert(3, 6);
+        queue.insert(4, 8);
+        queue.get(&2);
+
+        let json = serde_json::to_string(&queue).unwrap();
+        let mut queue2: Queue<u8, u8> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(queue2.capacity(), Some(3));
+        assert_eq!(queue2.pop_lru(), Some((3, 6)));
+        assert_eq!(queue2.pop_lru(), Some((4, 8)));
+        assert_eq!(queue2.pop_lru(), Some((2, 4)));
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn serde_skips_expired_entries() {
+        use core::time::Duration;
+        use serde_json;
+        use time::MockClock;
+
+        let clock = MockClock::new();
+        let mut queue = Queue::with_capacity(3);
+        queue.set_clock(clock.clone());
+        queue.set_ttl(Some(Duration::from_secs(10)));
+        queue.insert(2u8, 4u8);
+        clock.advance(Duration::from_secs(5));
+        queue.insert(3, 6);
+        clock.advance(Duration::from_secs(5));
+
+        assert_eq!("[3,[[3,6]]]", serde_json::to_string(&queue).unwrap());
+    }
+}
+
+#[cfg(feature = "borsh")]
+mod borsh_compat {
+    use super::Queue;
+    use borsh::io::{Error, ErrorKind, Read, Result, Write};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use core::convert::TryFrom;
+    use core::hash::{BuildHasher, Hash};
+    use deque::Deque;
+
+    /// The capacity and the entries from least to most recently used are encoded, like they are
+    /// serialized with serde.
+    impl<K, V, H> BorshSerialize for Queue<K, V, H>
+    where
+        K: BorshSerialize + Clone + Eq + Hash,
+        V: BorshSerialize,
+        H: BuildHasher,
+    {
+        fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+            self.capacity.serialize(writer)?;
+            let len = u32::try_from(self.iter().count())
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "queue longer than u32::MAX"))?;
+            len.serialize(writer)?;
+            for (key, value) in self.iter() {
+                key.serialize(writer)?;
+                value.serialize(writer)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<K, V, H> BorshDeserialize for Queue<K, V, H>
+    where
+        K: BorshDeserialize + Clone + Eq + Hash,
+        V: BorshDeserialize,
+        H: BuildHasher + Default,
+    {
+        fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self> {
+            let capacity = Option::<usize>::deserialize_reader(reader)?;
+            let inner = Deque::deserialize_reader(reader)?;
+            let mut queue = Queue::from_parts(inner, capacity);
+            if let Some(capacity) = capacity {
+                queue.set_capacity(capacity);
+            }
+            Ok(queue)
+        }
+    }
+}
+
+#[cfg(feature = "bincode")]
+mod bincode_compat {
+    use super::Queue;
+    use bincode::de::{BorrowDecoder, Decoder};
+    use bincode::enc::Encoder;
+    use bincode::error::{DecodeError, EncodeError};
+    use bincode::{BorrowDecode, Decode, Encode};
+    use core::hash::{BuildHasher, Hash};
+    use deque::Deque;
+
+    /// The capacity and the entries from least to most recently used are encoded, like they are
+    /// serialized with serde.
+    impl<K, V, H> Encode for Queue<K, V, H>
+    where
+        K: Encode + Clone + Eq + Hash,
+        V: Encode,
+        H: BuildHasher,
+    {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.capacity.encode(encoder)?;
+            (self.iter().count() as u64).encode(encoder)?;
+            for (key, value) in self.iter() {
+                key.encode(encoder)?;
+                value.encode(encoder)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl<Context, K, V, H> Decode<Context> for Queue<K, V, H>
+    where
+        K: Decode<Context> + Clone + Eq + Hash,
+        V: Decode<Context>,
+        H: BuildHasher + Default,
+    {
+        fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+            let capacity = Option::<usize>::decode(decoder)?;
+            let inner = Deque::decode(decoder)?;
+            let mut queue = Queue::from_parts(inner, capacity);
+            if let Some(capacity) = capacity {
+                queue.set_capacity(capacity);
+            }
+            Ok(queue)
+        }
+    }
+
+    impl<'de, Context, K, V, H> BorrowDecode<'de, Context> for Queue<K, V, H>
+    where
+        K: BorrowDecode<'de, Context> + Clone + Eq + Hash,
+        V: BorrowDecode<'de, Context>,
+        H: BuildHasher + Default,
+    {
+        fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+            decoder: &mut D,
+        ) -> Result<Self, DecodeError> {
+            let capacity = Option::<usize>::decode(decoder)?;
+            let inner = Deque::borrow_decode(decoder)?;
+            let mut queue = Queue::from_parts(inner, capacity);
+            if let Some(capacity) = capacity {
+                queue.set_capacity(capacity);
+            }
+            Ok(queue)
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn bincode_test() {
+        let config = bincode::config::standard();
+        let mut queue = Queue::with_capacity(3);
+        queue.insert(2u8, 4u8);
+        queue.insert(3, 6);
+        queue.get(&2);
+
+        let bytes = bincode::encode_to_vec(&queue, config).unwrap();
+        let (mut queue2, _): (Queue<u8, u8>, _) =
+            bincode::decode_from_slice(&bytes, config).unwrap();
+
+        assert_eq!(queue2.capacity(), Some(3));
+        assert_eq!(queue2.pop_lru(), Some((3, 6)));
+        assert_eq!(queue2.pop_lru(), Some((2, 4)));
+    }
+}
+
+#[cfg(feature = "schemars")]
+mod schemars_compat {
+    use super::Queue;
+    use alloc::borrow::Cow;
+    use alloc::format;
+    use deque::Deque;
+    use schemars::{JsonSchema, Schema, SchemaGenerator};
+
+    /// Described like the serialized form, as an array of the capacity and the entries.
+    impl<K, V, H> JsonSchema for Queue<K, V, H>
+    where
+        K: JsonSchema,
+        V: JsonSchema,
+    {
+        fn inline_schema() -> bool {
+            true
+        }
+
+        fn schema_name() -> Cow<'static, str> {
+            format!("LruQueue_of_{}_and_{}", K::schema_name(), V::schema_name()).into()
+        }
+
+        fn schema_id() -> Cow<'static, str> {
+            format!(
+                "addressable_queue::lru::Queue<{}, {}>",
+                K::schema_id(),
+                V::schema_id()
+            )
+            .into()
+        }
+
+        fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+            <(Option<usize>, Deque<K, V>)>::json_schema(generator)
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_compat {
+    use super::Queue;
+    use arbitrary::{Arbitrary, Result, Unstructured};
+    use core::hash::{BuildHasher, Hash};
+
+    /// Built with an arbitrary capacity by a sequence of arbitrary insertions, accesses and
+    /// removals, so the order of the entries differs from the order they were inserted in.
+    impl<'a, K, V, S> Arbitrary<'a> for Queue<K, V, S>
+    where
+        K: Arbitrary<'a> + Clone + Eq + Hash,
+        V: Arbitrary<'a>,
+        S: BuildHasher + Default,
+    {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let mut queue = Queue::default();
+            if let Some(capacity) = u.arbitrary::<Option<u8>>()? {
+                queue.set_capacity(usize::from(capacity));
+            }
+            // Every step is preceded by a flag, which is false once the data runs out.
+            while u.arbitrary()? {
+                match u.int_in_range(0..=5)? {
+                    0 | 1 => {
+                        queue.insert(u.arbitrary()?, u.arbitrary()?);
+                    }
+                    2 => {
+                        if let Some(key) = choose_key(u, &queue)? {
+                            queue.get(&key);
+                        }
+                    }
+                    3 => {
+                        if let Some(key) = choose_key(u, &queue)? {
+                            queue.demote(&key);
+                        }
+                    }
+                    4 => {
+                        queue.pop_lru();
+                    }
+                    _ => {
+                        if let Some(key) = choose_key(u, &queue)? {
+                            queue.remove_key(&key);
+                        }
+                    }
+                }
+            }
+            Ok(queue)
+        }
+    }
+
+    /// Pick one of the keys of the queue, so accesses and removals mostly hit an entry.
+    fn choose_key<K, V, S>(u: &mut Unstructured, queue: &Queue<K, V, S>) -> Result<Option<K>>
+    where
+        K: Clone + Eq + Hash,
+        S: BuildHasher,
+    {
+        if queue.is_empty() {
+            return Ok(None);
+        }
+        let index = u.choose_index(queue.len())?;
+        Ok(queue.keys().nth(index).cloned())
     }
 }
@@ -0,0 +1,75 @@
+//! Macros for constructing queues from literals.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Create a [`fifo::Queue`](fifo/struct.Queue.html) containing the given entries in the order
+/// they are written.
+///
+/// ```
+/// #[macro_use]
+/// extern crate addressable_queue;
+///
+/// # fn main() {
+/// let queue = queue! {
+///     2u8 => 4u8,
+///     3 => 6,
+/// };
+///
+/// assert_eq!(queue.into_vec(), vec![(2, 4), (3, 6)]);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! queue {
+    () => {
+        $crate::fifo::Queue::new()
+    };
+    ($($key:expr => $value:expr),+ $(,)*) => {{
+        let mut queue = $crate::fifo::Queue::new();
+        $(
+            queue.insert($key, $value);
+        )+
+        queue
+    }};
+}
+
+/// Create an [`lru::Queue`](lru/struct.Queue.html) containing the given entries in the order
+/// they are written, the first entry being the least recently used one.
+///
+/// ```
+/// #[macro_use]
+/// extern crate addressable_queue;
+///
+/// # fn main() {
+/// let mut queue = lru! {
+///     2u8 => 4u8,
+///     3 => 6,
+/// };
+///
+/// assert_eq!(Some((2, 4)), queue.remove_head());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! lru {
+    () => {
+        $crate::lru::Queue::new()
+    };
+    ($($key:expr => $value:expr),+ $(,)*) => {{
+        let mut queue = $crate::lru::Queue::new();
+        $(
+            queue.insert($key, $value);
+        )+
+        queue
+    }};
+}
@@ -0,0 +1,252 @@
+//! Periodic cleanup of shared queues, so that their hot paths do not pay for it.
+//!
+//! Queues reclaim the slots of removed elements and drop expired entries as they are used. A
+//! [`Maintenance`](struct.Maintenance.html) driver instead does this on a schedule, either on a
+//! thread it spawns or whenever [`run_pending_maintenance`] is called, for example from a task
+//! of an async runtime. Combined with a queue built with
+//! [`compaction_threshold(f64::INFINITY)`](../builder/struct.QueueBuilder.html#method.compaction_threshold),
+//! only the driver compacts it.
+//!
+//! [`run_pending_maintenance`]: struct.Maintenance.html#method.run_pending_maintenance
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use concurrent;
+use shared;
+use std::boxed::Box;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+/// A queue which can be cleaned up through a shared reference.
+pub trait Maintain: Send + Sync {
+    /// Purge expired entries and reclaim the slots of removed elements.
+    fn maintain(&self);
+}
+
+impl<K, V> Maintain for shared::Queue<K, V>
+where
+    K: Clone + Eq + Hash + Send,
+    V: Send,
+{
+    fn maintain(&self) {
+        self.with(|queue| {
+            queue.purge_expired();
+            queue.compact();
+        });
+    }
+}
+
+impl<K, V> Maintain for shared::RwQueue<K, V>
+where
+    K: Clone + Eq + Hash + Send + Sync,
+    V: Send + Sync,
+{
+    fn maintain(&self) {
+        self.write(|queue| {
+            queue.purge_expired();
+            queue.compact();
+        });
+    }
+}
+
+impl<K, V, S> Maintain for concurrent::Queue<K, V, S>
+where
+    K: Clone + Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    fn maintain(&self) {
+        self.compact();
+    }
+}
+
+impl<K, V, S> Maintain for concurrent::LruCache<K, V, S>
+where
+    K: Clone + Eq + Hash + Send,
+    V: Send,
+    S: BuildHasher + Clone + Send + Sync,
+{
+    fn maintain(&self) {
+        self.compact();
+    }
+}
+
+impl<M: Maintain + ?Sized> Maintain for Arc<M> {
+    fn maintain(&self) {
+        (**self).maintain();
+    }
+}
+
+/// A schedule of queues to maintain, each at its own interval.
+///
+/// ```
+/// use addressable_queue::maintenance::Maintenance;
+/// use addressable_queue::shared::Queue;
+/// use std::time::Duration;
+///
+/// let queue = Queue::new();
+/// queue.insert_with_ttl("session", (), Duration::from_secs(0));
+///
+/// let mut maintenance = Maintenance::new();
+/// maintenance.register(queue.clone(), Duration::from_secs(60));
+///
+/// // Every queue is due right after it was registered.
+/// maintenance.run_pending_maintenance();
+/// assert!(queue.with(|queue| queue.iter().next().is_none()));
+/// ```
+pub struct Maintenance {
+    targets: Vec<Target>,
+}
+
+struct Target {
+    queue: Box<dyn Maintain>,
+    interval: Duration,
+    due: Instant,
+}
+
+impl Maintenance {
+    /// Create a new schedule without any queues.
+    pub fn new() -> Self {
+        Maintenance {
+            targets: Vec::new(),
+        }
+    }
+
+    /// Maintain `queue` every `interval`, starting with the next run.
+    pub fn register<M: Maintain + 'static>(&mut self, queue: M, interval: Duration) {
+        self.targets.push(Target {
+            queue: Box::new(queue),
+            interval,
+            due: Instant::now(),
+        });
+    }
+
+    /// Returns the number of registered queues.
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Returns true if no queues are registered.
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Maintain every queue which is due, and return the instant the next one is due.
+    ///
+    /// Returns `None` if no queues are registered.
+    pub fn run_pending_maintenance(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        for target in &mut self.targets {
+            if target.due <= now {
+                target.queue.maintain();
+                target.due = now + target.interval;
+            }
+        }
+        self.targets.iter().map(|target| target.due).min()
+    }
+
+    /// Run the schedule on a new thread until the returned handle is stopped or dropped.
+    ///
+    /// ```
+    /// use addressable_queue::maintenance::{Maintain, Maintenance};
+    /// use std::sync::mpsc::{channel, Sender};
+    /// use std::sync::Mutex;
+    /// use std::time::Duration;
+    ///
+    /// /// Reports every time it is maintained.
+    /// struct Probe(Mutex<Sender<()>>);
+    ///
+    /// impl Maintain for Probe {
+    ///     fn maintain(&self) {
+    ///         let _ = self.0.lock().unwrap().send(());
+    ///     }
+    /// }
+    ///
+    /// let (sender, maintained) = channel();
+    /// let mut maintenance = Maintenance::new();
+    /// maintenance.register(Probe(Mutex::new(sender)), Duration::from_millis(1));
+    /// let driver = maintenance.spawn();
+    ///
+    /// // The probe is due right away, and again after every interval.
+    /// maintained.recv().unwrap();
+    /// maintained.recv().unwrap();
+    /// driver.stop();
+    /// ```
+    pub fn spawn(mut self) -> MaintenanceThread {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let signal = stop.clone();
+        let handle = thread::spawn(move || {
+            let (ref stopped, ref wake) = *signal;
+            let mut stopped = stopped
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            while !*stopped {
+                let timeout = match self.run_pending_maintenance() {
+                    Some(due) => due.saturating_duration_since(Instant::now()),
+                    None => return,
+                };
+                stopped = wake
+                    .wait_timeout(stopped, timeout)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .0;
+            }
+        });
+        MaintenanceThread {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Maintenance::new()
+    }
+}
+
+/// A thread running a [`Maintenance`](struct.Maintenance.html) schedule, created by
+/// [`Maintenance::spawn`](struct.Maintenance.html#method.spawn).
+///
+/// Dropping the handle stops the thread and waits for it.
+pub struct MaintenanceThread {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MaintenanceThread {
+    /// Stop the thread, and wait for a running maintenance to finish.
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+impl Drop for MaintenanceThread {
+    fn drop(&mut self) {
+        let (ref stopped, ref wake) = *self.stop;
+        *stopped
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+        wake.notify_one();
+        if let Some(handle) = self.handle.take() {
+            // A panic during maintenance is not passed on to the owner of the handle.
+            let _ = handle.join();
+        }
+    }
+}
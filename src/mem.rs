@@ -0,0 +1,104 @@
+//! Helpers for estimating the memory used by the queues.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+/// Types which can report how much heap memory they own.
+///
+/// Implement this for your keys and values to include their heap allocations in the estimates of
+/// the `deep_memory_usage` methods.
+///
+/// ```
+/// use addressable_queue::mem::MemSize;
+///
+/// struct Payload {
+///     data: Vec<u8>,
+/// }
+///
+/// impl MemSize for Payload {
+///     fn heap_size(&self) -> usize {
+///         self.data.heap_size()
+///     }
+/// }
+///
+/// assert_eq!(Payload { data: Vec::with_capacity(16) }.heap_size(), 16);
+/// ```
+pub trait MemSize {
+    /// Returns the number of bytes allocated on the heap by this value.
+    ///
+    /// This does not include `size_of::<Self>()`, which is accounted for by the container.
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! impl_mem_size_inline {
+    ($($t:ty),*) => {
+        $(
+            impl MemSize for $t {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_mem_size_inline!(
+    (), bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+impl MemSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: MemSize> MemSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(MemSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: MemSize> MemSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + (**self).heap_size()
+    }
+}
+
+impl<T: MemSize> MemSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, MemSize::heap_size)
+    }
+}
+
+impl<A: MemSize, B: MemSize> MemSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+/// Estimate the heap memory of a hash table holding `capacity` entries of type `T`.
+///
+/// Accounts for the load factor and the control byte stored per bucket.
+pub(crate) fn hash_table_size<T>(capacity: usize) -> usize {
+    if capacity == 0 {
+        return 0;
+    }
+    let buckets = (capacity * 8).div_ceil(7).next_power_of_two();
+    buckets * (size_of::<T>() + 1)
+}
@@ -0,0 +1,316 @@
+//! Implementation of a multi-producer single-consumer channel of addressable entries, where
+//! producers never block.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use deque::Deque;
+use primitives::{Arc, AtomicUsize, Ordering};
+use std::hash::Hash;
+use std::sync::mpsc::{self, RecvTimeoutError, SendError, TryRecvError};
+use std::time::{Duration, Instant};
+
+pub use sync::{Closed, Disconnected};
+
+/// An operation of a producer, applied by the consumer in the order they were sent.
+enum Op<K, V> {
+    Insert(K, V),
+    Cancel(K),
+}
+
+/// Create an unbounded channel of entries which are addressable by their key.
+///
+/// Unlike [`sync::channel`], the producers do not share the queue with the consumer behind a
+/// lock. Every insertion and cancellation is sent over a lock-free channel, and the consumer
+/// applies them to a queue it owns before it reads it. Producers therefore never wait for the
+/// consumer or for each other, at the cost of `cancel` not returning the cancelled value, and
+/// the consumer being the only one which can inspect the queue.
+///
+/// Once all senders are dropped, the [`Receiver`](struct.Receiver.html) receives the remaining
+/// entries and then [`Closed`](struct.Closed.html). Once the receiver is dropped, inserting
+/// fails.
+///
+/// [`sync::channel`]: ../sync/fn.channel.html
+///
+/// ```
+/// use addressable_queue::mpsc;
+/// use std::thread;
+///
+/// let (sender, mut receiver) = mpsc::channel();
+/// let producers: Vec<_> = (0..4u32)
+///     .map(|i| {
+///         let sender = sender.clone();
+///         thread::spawn(move || {
+///             sender.insert(i, "draft").unwrap();
+///             sender.cancel(i);
+///             sender.insert(i, "final").unwrap();
+///         })
+///     })
+///     .collect();
+/// drop(sender);
+/// for producer in producers {
+///     producer.join().unwrap();
+/// }
+///
+/// let mut received = Vec::new();
+/// while let Ok((key, value)) = receiver.pop() {
+///     assert_eq!("final", value);
+///     received.push(key);
+/// }
+/// received.sort();
+/// assert_eq!(vec![0, 1, 2, 3], received);
+/// ```
+pub fn channel<K, V>() -> (Sender<K, V>, Receiver<K, V>)
+where
+    K: Clone + Eq + Hash,
+{
+    let (ops, received) = mpsc::channel();
+    let sent = Arc::new(AtomicUsize::new(0));
+    let receiver = Receiver {
+        ops: received,
+        sent: sent.clone(),
+        applied: 0,
+        queue: Deque::new(),
+        disconnected: false,
+    };
+    (Sender { ops, sent }, receiver)
+}
+
+/// The sending half of a [`channel`](fn.channel.html), which can be cloned to insert from
+/// several threads.
+pub struct Sender<K, V> {
+    ops: mpsc::Sender<Op<K, V>>,
+    /// The number of operations sent by all senders, counted before they are sent.
+    sent: Arc<AtomicUsize>,
+}
+
+impl<K, V> Sender<K, V> {
+    /// Insert an entry at the end of the queue, without waiting.
+    ///
+    /// If there already is an entry for the key when the consumer applies the insertion, it is
+    /// removed, so the key moves to the end. If the receiver was dropped, the pair is handed
+    /// back in the error.
+    pub fn insert(&self, key: K, value: V) -> Result<(), Disconnected<K, V>> {
+        match self.send(Op::Insert(key, value)) {
+            Err(Op::Insert(key, value)) => Err(Disconnected(key, value)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Cancel the entry of the key if it was not yet received, without waiting.
+    ///
+    /// Entries inserted after the cancellation are kept. Does nothing if the receiver was
+    /// dropped, as then there is nothing left to cancel.
+    pub fn cancel(&self, key: K) {
+        let _ = self.send(Op::Cancel(key));
+    }
+
+    /// Send an operation, and hand it back if the receiver was dropped.
+    ///
+    /// The operation is counted before it is sent, so the receiver never receives more than it
+    /// counted, and the count is taken back if sending fails.
+    fn send(&self, op: Op<K, V>) -> Result<(), Op<K, V>> {
+        self.sent.fetch_add(1, Ordering::Release);
+        self.ops.send(op).map_err(|SendError(op)| {
+            self.sent.fetch_sub(1, Ordering::Relaxed);
+            op
+        })
+    }
+}
+
+impl<K, V> Clone for Sender<K, V> {
+    fn clone(&self) -> Self {
+        Sender {
+            ops: self.ops.clone(),
+            sent: self.sent.clone(),
+        }
+    }
+}
+
+/// The receiving half of a [`channel`](fn.channel.html).
+///
+/// Its methods take `&mut self`, as they first apply the operations the senders sent since the
+/// last call.
+pub struct Receiver<K, V> {
+    ops: mpsc::Receiver<Op<K, V>>,
+    sent: Arc<AtomicUsize>,
+    /// The number of operations received, which never exceeds `sent`.
+    applied: usize,
+    queue: Deque<K, V>,
+    disconnected: bool,
+}
+
+impl<K, V> Receiver<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Receive the next entry, waiting until there is one.
+    ///
+    /// Once all senders were dropped, the remaining entries are still returned, and then the
+    /// error.
+    pub fn pop(&mut self) -> Result<(K, V), Closed> {
+        loop {
+            if let Some(entry) = self.try_pop() {
+                return Ok(entry);
+            }
+            if self.disconnected {
+                return Err(Closed);
+            }
+            match self.ops.recv() {
+                Ok(op) => self.apply(op),
+                Err(_) => self.disconnected = true,
+            }
+        }
+    }
+
+    /// Receive the next entry, waiting at most `timeout` until there is one.
+    pub fn pop_timeout(&mut self, timeout: Duration) -> Result<Option<(K, V)>, Closed> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(entry) = self.try_pop() {
+                return Ok(Some(entry));
+            }
+            if self.disconnected {
+                return Err(Closed);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            match self.ops.recv_timeout(deadline - now) {
+                Ok(op) => self.apply(op),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => self.disconnected = true,
+            }
+        }
+    }
+
+    /// Receive the next entry if there is one, without waiting.
+    pub fn try_pop(&mut self) -> Option<(K, V)> {
+        self.receive();
+        self.queue.pop_front()
+    }
+
+    /// Returns the next entry, without receiving it.
+    pub fn peek(&mut self) -> Option<(&K, &V)> {
+        self.receive();
+        self.queue.peek_front()
+    }
+
+    /// Returns true if an entry for the key is waiting to be received.
+    pub fn contains_key(&mut self, key: &K) -> bool {
+        self.receive();
+        self.queue.contains_key(key)
+    }
+
+    /// Remove the entry of the key, and return its value.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.receive();
+        self.queue.remove_key(key)
+    }
+
+    /// Returns the number of entries waiting to be received.
+    pub fn len(&mut self) -> usize {
+        self.receive();
+        self.queue.len()
+    }
+
+    /// Returns true if no entries are waiting to be received.
+    pub fn is_empty(&mut self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply the operations which were sent before the call.
+    ///
+    /// Operations sent while receiving are left to the next call, so this returns even if the
+    /// senders keep sending.
+    fn receive(&mut self) {
+        let pending = self.sent.load(Ordering::Acquire).wrapping_sub(self.applied);
+        for _ in 0..pending {
+            match self.ops.try_recv() {
+                Ok(op) => self.apply(op),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.disconnected = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, op: Op<K, V>) {
+        self.applied = self.applied.wrapping_add(1);
+        match op {
+            Op::Insert(key, value) => self.queue.push_back(key, value),
+            Op::Cancel(key) => {
+                self.queue.remove_key(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn cancel_only_removes_earlier_insertions() {
+    let (sender, mut receiver) = channel();
+    sender.insert("a", 1).unwrap();
+    sender.cancel("a");
+    sender.cancel("b");
+    sender.insert("b", 2).unwrap();
+    drop(sender);
+
+    assert_eq!(Ok(("b", 2)), receiver.pop());
+    assert_eq!(Err(Closed), receiver.pop());
+}
+
+#[cfg(test)]
+#[test]
+fn receiving_returns_while_senders_keep_sending() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+
+    let (sender, mut receiver) = channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let (started, wait_started) = mpsc::channel();
+    let producer = {
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut key = 0u64;
+            while !stop.load(Ordering::Relaxed) {
+                sender.insert(key, ()).unwrap();
+                key += 1;
+                if key == 1000 {
+                    started.send(()).unwrap();
+                }
+            }
+        })
+    };
+    wait_started.recv().unwrap();
+
+    let (done, wait_done) = mpsc::channel();
+    let consumer = thread::spawn(move || {
+        receiver.try_pop();
+        // Hand the receiver back, so the producer is not disconnected.
+        done.send(receiver).unwrap();
+    });
+    let finished = wait_done.recv_timeout(Duration::from_secs(30));
+    stop.store(true, Ordering::Relaxed);
+
+    assert!(finished.is_ok());
+    producer.join().unwrap();
+    consumer.join().unwrap();
+}
@@ -0,0 +1,128 @@
+//! Implementation of an addressable bounded queue evicting the most recently used entry.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+use deque::{DefaultHashBuilder, Deque};
+use policy::{self, Mru};
+
+/// An addressable queue holding at most `capacity` entries, which evicts the most recently used
+/// entry when it is full.
+///
+/// This suits cyclic access patterns over more entries than the queue holds, where the least
+/// recently used entry is the one needed next.
+///
+/// ```
+/// use addressable_queue::mru::Queue;
+///
+/// let mut queue = Queue::new(2);
+/// queue.insert('a', 1);
+/// queue.insert('b', 2);
+/// queue.get(&'a');
+///
+/// assert_eq!(Some(('a', 1)), queue.insert('c', 3));
+/// assert_eq!(queue.into_vec(), vec![('b', 2), ('c', 3)]);
+/// ```
+pub struct Queue<K, V, S = DefaultHashBuilder> {
+    inner: policy::Queue<K, V, Mru, S>,
+    capacity: usize,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the queue holding at most `capacity` elements.
+    pub fn new(capacity: usize) -> Self {
+        Queue {
+            inner: policy::Queue::from_parts(Deque::with_capacity(capacity), Mru),
+            capacity,
+        }
+    }
+}
+
+impl<K, V, S> Queue<K, V, S>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+{
+    /// Returns the maximum number of elements.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Access an entry, which marks it as the most recently used one.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Access an entry mutably, which marks it as the most recently used one.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)
+    }
+
+    /// Returns a reference to the value of the specified key, without marking it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.inner.peek(key)
+    }
+
+    /// Insert an entry as the most recently used one.
+    ///
+    /// If the key is already contained, its value is replaced and the old entry is returned.
+    /// Otherwise, if the queue is full, the most recently used entry is evicted and returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        let returned = match self.inner.remove_key(&key) {
+            Some(old) => Some((key.clone(), old)),
+            None if self.len() >= self.capacity => self.inner.remove_victim(),
+            None => None,
+        };
+        self.inner.insert(key, value);
+        returned
+    }
+
+    /// Remove the most recently used entry, and return it if there was one.
+    pub fn pop_mru(&mut self) -> Option<(K, V)> {
+        self.inner.remove_victim()
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Convert the queue into a vec, from the least to the most recently used entry.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+}
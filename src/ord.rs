@@ -0,0 +1,158 @@
+//! Implementation of an addressable FIFO queue for keys which implement `Ord` instead of `Hash`.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::ops::RangeBounds;
+use storage::Engine;
+
+/// An addressable FIFO queue whose keys are indexed by a `BTreeMap`.
+///
+/// Keys only have to implement `Ord`, lookups and removals by key take O(log n).
+pub struct Queue<K, V> {
+    inner: Engine<K, V, BTreeMap<K, u64>>,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Ord,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            inner: Engine::new(),
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Returns the smallest key in the queue.
+    pub fn min_key(&self) -> Option<&K> {
+        self.inner.index.keys().next()
+    }
+
+    /// Returns the largest key in the queue.
+    pub fn max_key(&self) -> Option<&K> {
+        self.inner.index.keys().next_back()
+    }
+
+    /// Insert an entry at the end of the queue.
+    ///
+    /// An existing entry for the key is removed.
+    ///
+    /// ```
+    /// use addressable_queue::ord::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(3u8, 'a');
+    /// queue.insert(1u8, 'b');
+    /// queue.insert(3u8, 'c');
+    ///
+    /// assert_eq!(Some(&1), queue.min_key());
+    /// assert_eq!(queue.into_vec(), vec![(1, 'b'), (3, 'c')]);
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) {
+        self.inner.push_back(key, value);
+    }
+
+    /// Remove the current head of the queue, and return it if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        self.inner.pop_front()
+    }
+
+    /// Remove the current tail of the queue, and return it if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        self.inner.pop_back()
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Remove all entries whose keys fall in the range, and return them in queue order.
+    ///
+    /// The remaining entries keep their order.
+    ///
+    /// ```
+    /// use addressable_queue::ord::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert(20u32, 'a');
+    /// queue.insert(5u32, 'b');
+    /// queue.insert(12u32, 'c');
+    /// queue.insert(10u32, 'd');
+    ///
+    /// assert_eq!(queue.drain_range(10..20), vec![(12, 'c'), (10, 'd')]);
+    /// assert_eq!(queue.into_vec(), vec![(20, 'a'), (5, 'b')]);
+    /// ```
+    pub fn drain_range<R>(&mut self, range: R) -> Vec<(K, V)>
+    where
+        R: RangeBounds<K>,
+    {
+        let mut positions: Vec<u64> = self.inner.index.range(range).map(|(_, &pos)| pos).collect();
+        positions.sort_unstable_by_key(|&pos| self.inner.slot(pos));
+        let mut drained = Vec::with_capacity(positions.len());
+        for pos in positions {
+            if let Some((key, value)) = self.inner.take(pos) {
+                self.inner.index.remove(&key);
+                drained.push((key, value));
+            }
+        }
+        self.inner.maybe_compact();
+        drained
+    }
+
+    /// Remove all entries whose keys fall in the range, and return how many were removed.
+    pub fn remove_range<R>(&mut self, range: R) -> usize
+    where
+        R: RangeBounds<K>,
+    {
+        self.drain_range(range).len()
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Ord,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
@@ -0,0 +1,264 @@
+//! A versioned format for persisting queues together with their configuration.
+//!
+//! Queues serialize as a bare sequence of their entries by default, which stays the compatible
+//! format. A [`Snapshot`](struct.Snapshot.html) additionally records the version of the format
+//! and the capacity and policies of the queue, so a queue restored from it behaves like the one
+//! that was persisted, and snapshots written by older versions of the crate can still be read.
+//!
+//! With the `serde` feature, a snapshot serializes as a struct with the fields `version`,
+//! `capacity`, `overflow`, `admission` and `entries`, where the policies are written as strings.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use fifo::Overflow;
+use lru::Admission;
+
+/// The version of the snapshot format written by this version of the crate.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The entries of a queue in order, together with its configuration.
+///
+/// Created by the `snapshot` methods of the queues, and turned back into a queue by their
+/// `restore` functions. Settings which do not apply to a queue are `None`.
+///
+/// ```
+/// extern crate addressable_queue;
+/// extern crate serde_json;
+///
+/// use addressable_queue::fifo::{BoundedQueue, Overflow};
+///
+/// # fn main() {
+/// let mut queue = BoundedQueue::new(2);
+/// queue.insert("a", 1);
+/// queue.insert("b", 2);
+///
+/// let json = serde_json::to_string(&queue.snapshot()).unwrap();
+/// assert_eq!(
+///     r#"{"version":1,"capacity":2,"overflow":"evict_head","admission":null,"entries":[["a",1],["b",2]]}"#,
+///     json
+/// );
+///
+/// let mut restored: BoundedQueue<&str, i32> = BoundedQueue::restore(serde_json::from_str(&json).unwrap());
+/// assert_eq!(Some(("a", 1)), restored.insert("c", 3));
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot<K, V> {
+    /// The version of the format the snapshot was written in.
+    pub version: u32,
+    /// The maximum number of entries of the queue.
+    pub capacity: Option<usize>,
+    /// What a full bounded queue does when another entry is inserted.
+    pub overflow: Option<Overflow>,
+    /// Which new entries a full LRU queue admits.
+    pub admission: Option<Admission>,
+    /// The entries of the queue, in the order they are removed.
+    pub entries: Vec<(K, V)>,
+}
+
+impl<K, V> Snapshot<K, V> {
+    /// Create a snapshot of the current format version, which holds `entries` and no settings.
+    pub fn new(entries: Vec<(K, V)>) -> Self {
+        Snapshot {
+            version: FORMAT_VERSION,
+            capacity: None,
+            overflow: None,
+            admission: None,
+            entries,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_compat {
+    use super::{Snapshot, FORMAT_VERSION};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::fmt;
+    use core::marker::PhantomData;
+    use fifo::Overflow;
+    use lru::Admission;
+    use serde::de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor};
+    use serde::ser::SerializeStruct;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    const FIELDS: &[&str] = &["version", "capacity", "overflow", "admission", "entries"];
+
+    impl<K, V> Serialize for Snapshot<K, V>
+    where
+        K: Serialize,
+        V: Serialize,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let overflow = self.overflow.map(|overflow| match overflow {
+                Overflow::EvictHead => "evict_head",
+                Overflow::Reject => "reject",
+            });
+            let admission = self.admission.map(|admission| match admission {
+                Admission::Always => "always",
+                Admission::TinyLfu => "tiny_lfu",
+            });
+            let mut snapshot = serializer.serialize_struct("Snapshot", FIELDS.len())?;
+            snapshot.serialize_field("version", &self.version)?;
+            snapshot.serialize_field("capacity", &self.capacity)?;
+            snapshot.serialize_field("overflow", &overflow)?;
+            snapshot.serialize_field("admission", &admission)?;
+            snapshot.serialize_field("entries", &self.entries)?;
+            snapshot.end()
+        }
+    }
+
+    impl<'de, K, V> Deserialize<'de> for Snapshot<K, V>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_struct(
+                "Snapshot",
+                FIELDS,
+                SnapshotVisitor {
+                    _marker: PhantomData,
+                },
+            )
+        }
+    }
+
+    struct SnapshotVisitor<K, V> {
+        _marker: PhantomData<(K, V)>,
+    }
+
+    impl<'de, K, V> Visitor<'de> for SnapshotVisitor<K, V>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        type Value = Snapshot<K, V>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a queue snapshot")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let missing = |index| de::Error::invalid_length(index, &self);
+            let version = seq.next_element()?.ok_or_else(|| missing(0))?;
+            check_version(version)?;
+            let capacity = seq.next_element()?.ok_or_else(|| missing(1))?;
+            let overflow: Option<String> = seq.next_element()?.ok_or_else(|| missing(2))?;
+            let admission: Option<String> = seq.next_element()?.ok_or_else(|| missing(3))?;
+            let entries = seq.next_element()?.ok_or_else(|| missing(4))?;
+            Ok(Snapshot {
+                version,
+                capacity,
+                overflow: overflow.map(|name| parse_overflow(&name)).transpose()?,
+                admission: admission.map(|name| parse_admission(&name)).transpose()?,
+                entries,
+            })
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut version = None;
+            let mut capacity = None;
+            let mut overflow = None;
+            let mut admission = None;
+            let mut entries: Option<Vec<(K, V)>> = None;
+            while let Some(field) = map.next_key::<String>()? {
+                match field.as_str() {
+                    "version" => version = Some(map.next_value()?),
+                    "capacity" => capacity = map.next_value()?,
+                    "overflow" => {
+                        let name: Option<String> = map.next_value()?;
+                        overflow = name.map(|name| parse_overflow(&name)).transpose()?;
+                    }
+                    "admission" => {
+                        let name: Option<String> = map.next_value()?;
+                        admission = name.map(|name| parse_admission(&name)).transpose()?;
+                    }
+                    "entries" => entries = Some(map.next_value()?),
+                    // Fields added by later versions are skipped.
+                    _ => {
+                        map.next_value::<IgnoredAny>()?;
+                    }
+                }
+            }
+            let version = version.ok_or_else(|| de::Error::missing_field("version"))?;
+            check_version(version)?;
+            Ok(Snapshot {
+                version,
+                capacity,
+                overflow,
+                admission,
+                entries: entries.ok_or_else(|| de::Error::missing_field("entries"))?,
+            })
+        }
+    }
+
+    fn check_version<E: de::Error>(version: u32) -> Result<(), E> {
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(E::custom(format_args!(
+                "unsupported snapshot format version {}",
+                version
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse_overflow<E: de::Error>(name: &str) -> Result<Overflow, E> {
+        match name {
+            "evict_head" => Ok(Overflow::EvictHead),
+            "reject" => Ok(Overflow::Reject),
+            _ => Err(E::unknown_variant(name, &["evict_head", "reject"])),
+        }
+    }
+
+    fn parse_admission<E: de::Error>(name: &str) -> Result<Admission, E> {
+        match name {
+            "always" => Ok(Admission::Always),
+            "tiny_lfu" => Ok(Admission::TinyLfu),
+            _ => Err(E::unknown_variant(name, &["always", "tiny_lfu"])),
+        }
+    }
+
+    #[cfg(test)]
+    #[test]
+    fn snapshots_of_later_versions_are_rejected() {
+        use alloc::string::ToString;
+        use serde_json;
+        let json = r#"{"version":2,"entries":[],"expires":null}"#;
+        let error = serde_json::from_str::<Snapshot<u8, u8>>(json)
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("unsupported snapshot format version 2"));
+
+        let json = r#"{"entries":[[1,2]],"version":1}"#;
+        let snapshot: Snapshot<u8, u8> = serde_json::from_str(json).unwrap();
+        assert_eq!(Snapshot::new(vec![(1, 2)]), snapshot);
+    }
+}
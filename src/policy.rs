@@ -0,0 +1,327 @@
+//! Eviction and ordering policies on top of the addressable deque.
+//!
+//! A [`Policy`](trait.Policy.html) decides where entries are placed when they are inserted or
+//! accessed, and which entry is evicted next. [`Queue`](struct.Queue.html) applies a policy to an
+//! addressable deque, so custom policies do not have to reimplement the queue.
+//!
+//! [`lru::Queue`](../lru/struct.Queue.html) and [`fifo::Queue`](../fifo/struct.Queue.html) are
+//! built on this queue with the [`Lru`](struct.Lru.html) and [`Fifo`](struct.Fifo.html)
+//! policies. Operations the hooks of a policy do not cover, like the policies of the FIFO queue
+//! for duplicate keys and its requeueing and renaming of entries, work on the deque underneath.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+use deque::{DefaultHashBuilder, Deque, Iter};
+
+/// Where an entry is placed in the queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Placement {
+    /// The entry stays where it is. New entries are placed at the back.
+    Keep,
+    /// The entry is moved to the front.
+    Front,
+    /// The entry is moved to the back.
+    Back,
+}
+
+/// Decides how a [`Queue`](struct.Queue.html) orders and evicts its entries.
+///
+/// All methods have defaults, which make the queue behave like a FIFO queue.
+pub trait Policy<K, V> {
+    /// Called before an entry is inserted, returns where it is placed.
+    ///
+    /// An entry which was contained for the key is removed before, with a call to
+    /// [`on_remove`](#method.on_remove). `Keep` places the entry at the back.
+    fn on_insert(&mut self, _key: &K) -> Placement {
+        Placement::Back
+    }
+
+    /// Called when an entry is accessed, returns where it is moved.
+    fn on_access(&mut self, _key: &K) -> Placement {
+        Placement::Keep
+    }
+
+    /// Called after an entry was removed from the queue.
+    fn on_remove(&mut self, _key: &K) {}
+
+    /// Returns the key of the entry to evict next, given the entries from front to back.
+    fn pick_victim<'a>(&mut self, mut entries: Iter<'a, K, V>) -> Option<&'a K> {
+        entries.next().map(|(key, _)| key)
+    }
+}
+
+/// Evict entries in the order they were inserted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fifo;
+
+impl<K, V> Policy<K, V> for Fifo {}
+
+/// Evict the least recently used entry.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lru;
+
+impl<K, V> Policy<K, V> for Lru {
+    fn on_access(&mut self, _key: &K) -> Placement {
+        Placement::Back
+    }
+}
+
+/// Evict the most recently used entry.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mru;
+
+impl<K, V> Policy<K, V> for Mru {
+    fn on_access(&mut self, _key: &K) -> Placement {
+        Placement::Back
+    }
+
+    fn pick_victim<'a>(&mut self, mut entries: Iter<'a, K, V>) -> Option<&'a K> {
+        entries.next_back().map(|(key, _)| key)
+    }
+}
+
+/// An addressable queue ordered by a policy.
+///
+/// ```
+/// use addressable_queue::deque::Iter;
+/// use addressable_queue::policy::{Placement, Policy, Queue};
+///
+/// /// Evicts the largest value first.
+/// struct Largest;
+///
+/// impl Policy<char, u32> for Largest {
+///     fn pick_victim<'a>(&mut self, entries: Iter<'a, char, u32>) -> Option<&'a char> {
+///         entries.max_by_key(|&(_, value)| *value).map(|(key, _)| key)
+///     }
+/// }
+///
+/// let mut queue = Queue::with_policy(Largest);
+/// queue.insert('a', 3);
+/// queue.insert('b', 7);
+/// queue.insert('c', 5);
+///
+/// assert_eq!(Some(('b', 7)), queue.remove_victim());
+/// assert_eq!(Some(('c', 5)), queue.remove_victim());
+/// ```
+pub struct Queue<K, V, P, S = DefaultHashBuilder> {
+    pub(crate) inner: Deque<K, V, S>,
+    policy: P,
+}
+
+impl<K, V, P> Queue<K, V, P>
+where
+    K: Clone + Eq + Hash,
+    P: Policy<K, V>,
+{
+    /// Create a new instance of a queue with the default instance of the policy.
+    pub fn new() -> Self
+    where
+        P: Default,
+    {
+        Queue::with_policy(P::default())
+    }
+
+    /// Create a new instance of a queue with the given policy.
+    pub fn with_policy(policy: P) -> Self {
+        Queue {
+            inner: Deque::new(),
+            policy,
+        }
+    }
+}
+
+impl<K, V, P, S> Queue<K, V, P, S>
+where
+    K: Clone + Eq + Hash,
+    P: Policy<K, V>,
+    S: BuildHasher,
+{
+    /// Create a new queue from an existing deque and a policy.
+    pub fn from_parts(inner: Deque<K, V, S>, policy: P) -> Self {
+        Queue { inner, policy }
+    }
+
+    /// Returns a reference to the policy.
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the specified key, without counting as an access.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the specified key, without counting as an
+    /// access.
+    pub fn peek_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)
+    }
+
+    /// Access an entry, which moves it according to the policy.
+    ///
+    /// ```
+    /// use addressable_queue::policy::{Lru, Queue};
+    ///
+    /// let mut queue: Queue<_, _, Lru> = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// assert_eq!(Some(&4), queue.get(&2));
+    /// assert_eq!(Some((3, 6)), queue.remove_victim());
+    /// ```
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.touch(key);
+        self.inner.get(key)
+    }
+
+    /// Access an entry mutably, which moves it according to the policy.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.touch(key);
+        self.inner.get_mut(key)
+    }
+
+    /// Access an entry without reading it, which moves it according to the policy.
+    ///
+    /// Returns false if there is no entry for the key.
+    pub fn touch(&mut self, key: &K) -> bool {
+        if !self.inner.contains_key(key) {
+            return false;
+        }
+        match self.policy.on_access(key) {
+            Placement::Keep => true,
+            Placement::Front => self.inner.move_to_front(key),
+            Placement::Back => self.inner.move_to_back(key),
+        }
+    }
+
+    /// Move an entry to the front regardless of the policy, which is not notified.
+    ///
+    /// Returns false if there is no entry for the key.
+    pub fn move_to_front(&mut self, key: &K) -> bool {
+        self.inner.move_to_front(key)
+    }
+
+    /// Insert an entry at the position chosen by the policy, and return the old value of the
+    /// key.
+    ///
+    /// ```
+    /// use addressable_queue::policy::{Fifo, Queue};
+    ///
+    /// let mut queue: Queue<_, _, Fifo> = Queue::new();
+    /// queue.insert(2u8, 4u8);
+    /// queue.insert(3u8, 6u8);
+    ///
+    /// assert_eq!(Some(4), queue.insert(2, 5));
+    /// assert_eq!(queue.into_vec(), vec![(3, 6), (2, 5)]);
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.remove_key(&key);
+        match self.policy.on_insert(&key) {
+            Placement::Front => self.inner.push_front(key, value),
+            Placement::Keep | Placement::Back => self.inner.push_back(key, value),
+        }
+        old
+    }
+
+    /// Remove the entry picked by the policy, and return it if there was one.
+    pub fn remove_victim(&mut self) -> Option<(K, V)> {
+        let key = self.policy.pick_victim(self.inner.iter())?.clone();
+        let value = self.remove_key(&key)?;
+        Some((key, value))
+    }
+
+    /// Remove the current head of the queue, and return it if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        let (key, value) = self.inner.pop_front()?;
+        self.policy.on_remove(&key);
+        Some((key, value))
+    }
+
+    /// Remove the current tail of the queue, and return it if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        let (key, value) = self.inner.pop_back()?;
+        self.policy.on_remove(&key);
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let value = self.inner.remove_key(key)?;
+        self.policy.on_remove(key);
+        Some(value)
+    }
+
+    /// Convert the queue into a vec, where the first element is the head.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+}
+
+impl<K, V, P, S> Default for Queue<K, V, P, S>
+where
+    K: Clone + Eq + Hash,
+    P: Policy<K, V> + Default,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Queue::from_parts(Deque::default(), P::default())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn replaced_entries_are_removed_from_the_policy() {
+    /// Counts the entries it was notified about.
+    #[derive(Default)]
+    struct Counting(usize);
+
+    impl Policy<u8, u8> for Counting {
+        fn on_insert(&mut self, _key: &u8) -> Placement {
+            self.0 += 1;
+            Placement::Back
+        }
+
+        fn on_remove(&mut self, _key: &u8) {
+            self.0 -= 1;
+        }
+    }
+
+    let mut queue: Queue<u8, u8, Counting> = Queue::new();
+    queue.insert(2, 4);
+    queue.insert(3, 6);
+    queue.insert(2, 5);
+    assert_eq!(2, queue.policy().0);
+
+    queue.remove_victim();
+    queue.remove_key(&2);
+    assert_eq!(0, queue.policy().0);
+}
@@ -0,0 +1,496 @@
+//! Implementation of an addressable priority queue, where entries can be removed and their
+//! priority changed by specifying their key.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::hash::Hash;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// An addressable priority queue, which is an indexed binary heap.
+///
+/// The entry with the lowest priority is removed first, use `core::cmp::Reverse` for the highest
+/// priority first. Entries with equal priorities are removed in the order they were pushed.
+/// Pushing, popping, removing by key and changing the priority of an entry take `O(log n)` time.
+///
+/// ```
+/// use addressable_queue::priority::Queue;
+///
+/// let mut queue = Queue::new();
+/// queue.push("backup", 3, ());
+/// queue.push("deploy", 1, ());
+/// queue.push("report", 2, ());
+///
+/// queue.remove_key(&"report");
+/// queue.change_priority(&"backup", 0);
+///
+/// assert_eq!(Some(("backup", 0, ())), queue.pop_min());
+/// assert_eq!(Some(("deploy", 1, ())), queue.pop_min());
+/// ```
+pub struct Queue<K, P, V> {
+    heap: Vec<Node<K, P, V>>,
+    index: HashMap<K, usize>,
+    next_seq: u64,
+}
+
+struct Node<K, P, V> {
+    key: K,
+    priority: P,
+    /// When the entry was pushed, which orders entries of equal priority.
+    seq: u64,
+    value: V,
+}
+
+impl<K, P, V> Queue<K, P, V>
+where
+    K: Clone + Eq + Hash,
+    P: Ord,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            heap: Vec::new(),
+            index: HashMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the priority of the entry with the specified key.
+    pub fn priority(&self, key: &K) -> Option<&P> {
+        self.index.get(key).map(|&pos| &self.heap[pos].priority)
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.index.get(key).map(|&pos| &self.heap[pos].value)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let pos = *self.index.get(key)?;
+        Some(&mut self.heap[pos].value)
+    }
+
+    /// Returns the entry with the lowest priority.
+    pub fn peek(&self) -> Option<(&K, &P, &V)> {
+        self.heap
+            .first()
+            .map(|node| (&node.key, &node.priority, &node.value))
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its entry is replaced and the old priority and value are
+    /// returned. The entry then counts as pushed last among the entries of equal priority.
+    ///
+    /// ```
+    /// use addressable_queue::priority::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.push("first", 1, ());
+    /// queue.push("second", 1, ());
+    /// queue.push("third", 1, ());
+    ///
+    /// assert_eq!(Some(("first", 1, ())), queue.pop_min());
+    /// assert_eq!(Some(("second", 1, ())), queue.pop_min());
+    /// ```
+    pub fn push(&mut self, key: K, priority: P, value: V) -> Option<(P, V)> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if let Some(&pos) = self.index.get(&key) {
+            let node = &mut self.heap[pos];
+            node.seq = seq;
+            let old_priority = ::core::mem::replace(&mut node.priority, priority);
+            let old_value = ::core::mem::replace(&mut node.value, value);
+            self.restore(pos);
+            return Some((old_priority, old_value));
+        }
+        let pos = self.heap.len();
+        self.index.insert(key.clone(), pos);
+        self.heap.push(Node {
+            key,
+            priority,
+            seq,
+            value,
+        });
+        self.sift_up(pos);
+        None
+    }
+
+    /// Remove the entry with the lowest priority, and return it if there was one.
+    pub fn pop_min(&mut self) -> Option<(K, P, V)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        Some(self.take(0))
+    }
+
+    /// Remove the entry with the highest priority, and return it if there was one.
+    ///
+    /// Among the entries of equal priority the one pushed last is removed. The heap only orders
+    /// entries towards the lowest priority, so this searches its leaves and takes `O(n)` time,
+    /// use a [`MinMaxQueue`](struct.MinMaxQueue.html) to remove from both ends often.
+    ///
+    /// ```
+    /// use addressable_queue::priority::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.push("a", 2, ());
+    /// queue.push("b", 3, ());
+    /// queue.push("c", 1, ());
+    ///
+    /// assert_eq!(Some(("b", 3, ())), queue.pop_max());
+    /// assert_eq!(Some(("c", 1, ())), queue.pop_min());
+    /// ```
+    pub fn pop_max(&mut self) -> Option<(K, P, V)> {
+        let leaves = self.heap.len() / 2..self.heap.len();
+        let pos = leaves.reduce(|max, pos| if self.less(max, pos) { pos } else { max })?;
+        Some(self.take(pos))
+    }
+
+    /// Remove an entry by specifying its key, and return its priority and value.
+    pub fn remove_key(&mut self, key: &K) -> Option<(P, V)> {
+        let pos = *self.index.get(key)?;
+        let (_, priority, value) = self.take(pos);
+        Some((priority, value))
+    }
+
+    /// Change the priority of an entry, and return the old priority.
+    ///
+    /// Among the entries of equal priority the entry keeps the position of when it was pushed.
+    pub fn change_priority(&mut self, key: &K, priority: P) -> Option<P> {
+        let pos = *self.index.get(key)?;
+        let old = ::core::mem::replace(&mut self.heap[pos].priority, priority);
+        self.restore(pos);
+        Some(old)
+    }
+
+    /// Lower the priority of an entry, so it is removed earlier, and return the old priority.
+    ///
+    /// In contrast to [`change_priority`](#method.change_priority) the entry is only moved
+    /// towards the top of the heap. In debug builds it panics if the priority is raised.
+    ///
+    /// ```
+    /// use addressable_queue::priority::Queue;
+    ///
+    /// // Distances of Dijkstra's algorithm.
+    /// let mut queue = Queue::new();
+    /// queue.push('a', 7, ());
+    /// queue.push('b', 4, ());
+    ///
+    /// assert_eq!(Some(7), queue.decrease_key(&'a', 2));
+    /// assert_eq!(Some(('a', 2, ())), queue.pop_min());
+    /// ```
+    pub fn decrease_key(&mut self, key: &K, priority: P) -> Option<P> {
+        let pos = *self.index.get(key)?;
+        debug_assert!(
+            priority <= self.heap[pos].priority,
+            "decrease_key must not raise the priority"
+        );
+        let old = ::core::mem::replace(&mut self.heap[pos].priority, priority);
+        self.sift_up(pos);
+        Some(old)
+    }
+
+    /// Raise the priority of an entry, so it is removed later, and return the old priority.
+    ///
+    /// In contrast to [`change_priority`](#method.change_priority) the entry is only moved
+    /// towards the bottom of the heap. In debug builds it panics if the priority is lowered.
+    pub fn increase_key(&mut self, key: &K, priority: P) -> Option<P> {
+        let pos = *self.index.get(key)?;
+        debug_assert!(
+            priority >= self.heap[pos].priority,
+            "increase_key must not lower the priority"
+        );
+        let old = ::core::mem::replace(&mut self.heap[pos].priority, priority);
+        self.sift_down(pos);
+        Some(old)
+    }
+
+    /// Convert the queue into a vec, ordered by ascending priority.
+    pub fn into_sorted_vec(mut self) -> Vec<(K, P, V)> {
+        let mut sorted = Vec::with_capacity(self.len());
+        while let Some(entry) = self.pop_min() {
+            sorted.push(entry);
+        }
+        sorted
+    }
+
+    /// Remove the entry at a position of the heap.
+    fn take(&mut self, pos: usize) -> (K, P, V) {
+        let last = self.heap.len() - 1;
+        self.swap(pos, last);
+        let node = self.heap.pop().unwrap();
+        self.index.remove(&node.key);
+        if pos < self.heap.len() {
+            self.restore(pos);
+        }
+        (node.key, node.priority, node.value)
+    }
+
+    /// Move the entry at a position up or down until the heap is ordered again.
+    fn restore(&mut self, pos: usize) {
+        let pos = self.sift_up(pos);
+        self.sift_down(pos);
+    }
+
+    fn sift_up(&mut self, mut pos: usize) -> usize {
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if !self.less(pos, parent) {
+                break;
+            }
+            self.swap(pos, parent);
+            pos = parent;
+        }
+        pos
+    }
+
+    fn sift_down(&mut self, mut pos: usize) {
+        loop {
+            let mut smallest = pos;
+            for child in [2 * pos + 1, 2 * pos + 2] {
+                if child < self.heap.len() && self.less(child, smallest) {
+                    smallest = child;
+                }
+            }
+            if smallest == pos {
+                return;
+            }
+            self.swap(pos, smallest);
+            pos = smallest;
+        }
+    }
+
+    /// Returns true if the entry at `a` is removed before the entry at `b`.
+    fn less(&self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.heap[a], &self.heap[b]);
+        (&a.priority, a.seq) < (&b.priority, b.seq)
+    }
+
+    /// Swap two entries of the heap, and update their positions in the index.
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        for &pos in &[a, b] {
+            if let Some(slot) = self.index.get_mut(&self.heap[pos].key) {
+                *slot = pos;
+            }
+        }
+    }
+}
+
+impl<K, P, V> Default for Queue<K, P, V>
+where
+    K: Clone + Eq + Hash,
+    P: Ord,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// An addressable double-ended priority queue, where entries can be removed with the lowest as
+/// well as with the highest priority.
+///
+/// Entries with equal priorities are ordered by when they were pushed: `pop_min` removes the
+/// oldest of them, `pop_max` the newest. All operations take `O(log n)` time.
+///
+/// ```
+/// use addressable_queue::priority::MinMaxQueue;
+///
+/// // Keep the three best scores.
+/// let mut best = MinMaxQueue::new();
+/// for (name, score) in vec![("a", 7), ("b", 3), ("c", 9), ("d", 5)] {
+///     best.push(name, score, ());
+///     if best.len() > 3 {
+///         best.pop_min();
+///     }
+/// }
+///
+/// assert_eq!(Some(("c", 9, ())), best.pop_max());
+/// assert_eq!(Some(("d", 5, ())), best.pop_min());
+/// ```
+pub struct MinMaxQueue<K, P, V> {
+    order: BTreeMap<(P, u64), K>,
+    entries: HashMap<K, (P, u64, V)>,
+    next_seq: u64,
+}
+
+impl<K, P, V> MinMaxQueue<K, P, V>
+where
+    K: Clone + Eq + Hash,
+    P: Clone + Ord,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        MinMaxQueue {
+            order: BTreeMap::new(),
+            entries: HashMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns the priority of the entry with the specified key.
+    pub fn priority(&self, key: &K) -> Option<&P> {
+        self.entries.get(key).map(|(priority, _, _)| priority)
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(_, _, value)| value)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.entries.get_mut(key).map(|(_, _, value)| value)
+    }
+
+    /// Returns the entry with the lowest priority.
+    pub fn peek_min(&self) -> Option<(&K, &P, &V)> {
+        let (_, key) = self.order.iter().next()?;
+        self.peek_entry(key)
+    }
+
+    /// Returns the entry with the highest priority.
+    pub fn peek_max(&self) -> Option<(&K, &P, &V)> {
+        let (_, key) = self.order.iter().next_back()?;
+        self.peek_entry(key)
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its entry is replaced and the old priority and value are
+    /// returned.
+    pub fn push(&mut self, key: K, priority: P, value: V) -> Option<(P, V)> {
+        let old = self.remove_key(&key);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.order.insert((priority.clone(), seq), key.clone());
+        self.entries.insert(key, (priority, seq, value));
+        old
+    }
+
+    /// Remove the entry with the lowest priority, and return it if there was one.
+    pub fn pop_min(&mut self) -> Option<(K, P, V)> {
+        let key = self.order.iter().next()?.1.clone();
+        let (priority, value) = self.remove_key(&key)?;
+        Some((key, priority, value))
+    }
+
+    /// Remove the entry with the highest priority, and return it if there was one.
+    pub fn pop_max(&mut self) -> Option<(K, P, V)> {
+        let key = self.order.iter().next_back()?.1.clone();
+        let (priority, value) = self.remove_key(&key)?;
+        Some((key, priority, value))
+    }
+
+    /// Remove an entry by specifying its key, and return its priority and value.
+    pub fn remove_key(&mut self, key: &K) -> Option<(P, V)> {
+        let (priority, seq, value) = self.entries.remove(key)?;
+        self.order.remove(&(priority.clone(), seq));
+        Some((priority, value))
+    }
+
+    /// Change the priority of an entry, and return the old priority.
+    pub fn change_priority(&mut self, key: &K, priority: P) -> Option<P> {
+        let entry = self.entries.get_mut(key)?;
+        let key = self.order.remove(&(entry.0.clone(), entry.1))?;
+        self.order.insert((priority.clone(), entry.1), key);
+        Some(::core::mem::replace(&mut entry.0, priority))
+    }
+
+    fn peek_entry<'a>(&'a self, key: &'a K) -> Option<(&'a K, &'a P, &'a V)> {
+        self.entries
+            .get(key)
+            .map(|(priority, _, value)| (key, priority, value))
+    }
+}
+
+impl<K, P, V> Default for MinMaxQueue<K, P, V>
+where
+    K: Clone + Eq + Hash,
+    P: Clone + Ord,
+{
+    fn default() -> Self {
+        MinMaxQueue::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn heap_stays_indexed() {
+    let mut queue = Queue::new();
+    for key in 0..32u32 {
+        queue.push(key, (key * 7) % 32, key);
+    }
+    for key in (0..32).step_by(3) {
+        queue.remove_key(&key);
+    }
+    for key in (1..32).step_by(4) {
+        queue.change_priority(&key, key / 2);
+    }
+    for (key, &pos) in queue.index.iter() {
+        assert_eq!(key, &queue.heap[pos].key);
+    }
+
+    let (_, max, _) = queue.pop_max().unwrap();
+    assert!(queue.heap.iter().all(|node| node.priority <= max));
+    for (key, &pos) in queue.index.iter() {
+        assert_eq!(key, &queue.heap[pos].key);
+    }
+
+    let priorities: Vec<_> = queue
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(_, priority, _)| priority)
+        .collect();
+    let mut sorted = priorities.clone();
+    sorted.sort();
+    assert_eq!(sorted, priorities);
+}
@@ -0,0 +1,185 @@
+//! Implementation of an addressable bounded queue evicting a random entry.
+//!
+//! This module requires the `rand` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+use deque::{DefaultHashBuilder, Deque};
+use rand::rngs::StdRng;
+use rand::Rng;
+#[cfg(feature = "std")]
+use rand::SeedableRng;
+
+/// An addressable queue holding at most `capacity` entries, which evicts a uniformly random
+/// entry when it is full.
+///
+/// ```
+/// use addressable_queue::random::Queue;
+///
+/// let mut queue = Queue::new(2);
+/// queue.insert('a', 1);
+/// queue.insert('b', 2);
+///
+/// let (key, _) = queue.insert('c', 3).unwrap();
+/// assert!(key == 'a' || key == 'b');
+/// assert_eq!(2, queue.len());
+/// ```
+pub struct Queue<K, V, R = StdRng, S = DefaultHashBuilder> {
+    inner: Deque<K, V, S>,
+    capacity: usize,
+    rng: R,
+}
+
+#[cfg(feature = "std")]
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the queue holding at most `capacity` elements, with a random
+    /// number generator seeded by the operating system.
+    pub fn new(capacity: usize) -> Self {
+        Queue::with_rng(capacity, StdRng::from_entropy())
+    }
+}
+
+impl<K, V, R> Queue<K, V, R>
+where
+    K: Clone + Eq + Hash,
+    R: Rng,
+{
+    /// Create a new instance of the queue holding at most `capacity` elements, which uses the
+    /// given random number generator to pick the entries to evict.
+    pub fn with_rng(capacity: usize, rng: R) -> Self {
+        Queue {
+            inner: Deque::with_capacity(capacity),
+            capacity,
+            rng,
+        }
+    }
+}
+
+impl<K, V, R, S> Queue<K, V, R, S>
+where
+    K: Clone + Eq + Hash,
+    R: Rng,
+    S: BuildHasher,
+{
+    /// Returns the maximum number of elements.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)
+    }
+
+    /// Returns a uniformly random entry, picked with the given random number generator.
+    ///
+    /// ```
+    /// use addressable_queue::random::Queue;
+    ///
+    /// let mut queue = Queue::new(4);
+    /// queue.insert('a', 1);
+    ///
+    /// assert_eq!(Some((&'a', &1)), queue.sample(&mut rand::thread_rng()));
+    /// ```
+    pub fn sample<G>(&self, rng: &mut G) -> Option<(&K, &V)>
+    where
+        G: Rng + ?Sized,
+    {
+        sample(&self.inner, rng)
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its value is replaced and the old entry is returned.
+    /// Otherwise, if the queue is full, a random entry is evicted and returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        if let Some(slot) = self.inner.get_mut(&key) {
+            let old = ::core::mem::replace(slot, value);
+            return Some((key, old));
+        }
+        let evicted = if self.inner.len() >= self.capacity {
+            self.remove_random()
+        } else {
+            None
+        };
+        self.inner.push_back(key, value);
+        evicted
+    }
+
+    /// Remove a uniformly random entry, and return it if there was one.
+    pub fn remove_random(&mut self) -> Option<(K, V)> {
+        let key = sample(&self.inner, &mut self.rng)?.0.clone();
+        let value = self.inner.remove_key(&key)?;
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+
+    /// Convert the queue into a vec, where the first element is the oldest one.
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner.into_vec()
+    }
+}
+
+/// Returns a uniformly random entry of the deque.
+fn sample<'a, K, V, S, G>(deque: &'a Deque<K, V, S>, rng: &mut G) -> Option<(&'a K, &'a V)>
+where
+    K: Clone + Eq + Hash,
+    S: BuildHasher,
+    G: Rng + ?Sized,
+{
+    if deque.is_empty() {
+        return None;
+    }
+    // Removed entries leave holes until the deque is compacted, which keeps them rare enough for
+    // retrying to be cheap.
+    let nodes = &deque.raw.nodes;
+    loop {
+        if let Some((ref key, ref value)) = nodes[rng.gen_range(0..nodes.len())] {
+            return Some((key, value));
+        }
+    }
+}
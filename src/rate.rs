@@ -0,0 +1,267 @@
+//! Implementation of an addressable FIFO queue, where removing entries is limited by token
+//! buckets.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fifo;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Instant;
+
+/// A token bucket, which holds up to `burst` tokens and is refilled with `rate` tokens per
+/// second.
+///
+/// ```
+/// use addressable_queue::rate::TokenBucket;
+/// use std::time::{Duration, Instant};
+///
+/// let now = Instant::now();
+/// let mut bucket = TokenBucket::new(10.0, 1.0, now);
+///
+/// assert!(bucket.try_take(1.0, now));
+/// assert!(!bucket.try_take(1.0, now));
+/// assert!(bucket.try_take(1.0, now + Duration::from_millis(100)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full token bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` or `burst` is negative.
+    pub fn new(rate: f64, burst: f64, now: Instant) -> Self {
+        assert!(rate >= 0.0, "the rate must not be negative");
+        assert!(burst >= 0.0, "the burst must not be negative");
+        TokenBucket {
+            rate,
+            burst,
+            tokens: burst,
+            updated: now,
+        }
+    }
+
+    /// Returns the number of tokens per second.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Returns the maximum number of tokens.
+    pub fn burst(&self) -> f64 {
+        self.burst
+    }
+
+    /// Returns the number of tokens available at `now`.
+    pub fn tokens(&mut self, now: Instant) -> f64 {
+        self.refill(now);
+        self.tokens
+    }
+
+    /// Take `tokens` tokens if they are available at `now`, and return whether they were.
+    pub fn try_take(&mut self, tokens: f64, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens < tokens {
+            return false;
+        }
+        self.tokens -= tokens;
+        true
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now > self.updated {
+            let elapsed = now.duration_since(self.updated).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+            self.updated = now;
+        }
+    }
+}
+
+/// An addressable FIFO queue, where removing an entry takes a token from a token bucket.
+///
+/// There can be one bucket for the whole queue, and one bucket for every class of keys. When the
+/// bucket of the class of the head is empty, the first entry of a class with a token is removed
+/// instead.
+///
+/// ```
+/// use addressable_queue::rate::Queue;
+/// use std::time::{Duration, Instant};
+///
+/// let mut queue = Queue::new(1.0, 2.0);
+/// for key in 0..3 {
+///     queue.insert(key, ());
+/// }
+/// let now = Instant::now();
+///
+/// assert_eq!(Some((0, ())), queue.pop_ready(now));
+/// assert_eq!(Some((1, ())), queue.pop_ready(now));
+/// assert_eq!(None, queue.pop_ready(now));
+/// assert_eq!(Some((2, ())), queue.pop_ready(now + Duration::from_secs(1)));
+/// ```
+pub struct Queue<K, V, C = ()> {
+    inner: fifo::Queue<K, V>,
+    global: Option<TokenBucket>,
+    classes: Option<ClassLimit<K, C>>,
+}
+
+/// Assigns keys to classes.
+type ClassifyFn<K, C> = Box<dyn Fn(&K) -> C + Send + Sync>;
+
+/// The token buckets of the classes of keys.
+struct ClassLimit<K, C> {
+    classify: ClassifyFn<K, C>,
+    rate: f64,
+    burst: f64,
+    buckets: HashMap<C, TokenBucket>,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue, where all entries share one bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` or `burst` is negative.
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Queue {
+            inner: fifo::Queue::new(),
+            global: Some(TokenBucket::new(rate, burst, Instant::now())),
+            classes: None,
+        }
+    }
+}
+
+impl<K, V, C> Queue<K, V, C>
+where
+    K: Clone + Eq + Hash,
+    C: Eq + Hash,
+{
+    /// Create a new instance of a queue, where the entries of every class of keys share a
+    /// bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` or `burst` is negative.
+    ///
+    /// ```
+    /// use addressable_queue::rate::Queue;
+    /// use std::time::Instant;
+    ///
+    /// let now = Instant::now();
+    /// let mut queue = Queue::per_class(1.0, 1.0, |&(tenant, _): &(char, u32)| tenant);
+    /// queue.insert(('a', 0), ());
+    /// queue.insert(('a', 1), ());
+    /// queue.insert(('b', 0), ());
+    ///
+    /// assert_eq!(Some((('a', 0), ())), queue.pop_ready(now));
+    /// assert_eq!(Some((('b', 0), ())), queue.pop_ready(now));
+    /// assert_eq!(None, queue.pop_ready(now));
+    /// ```
+    pub fn per_class<F>(rate: f64, burst: f64, classify: F) -> Self
+    where
+        F: Fn(&K) -> C + Send + Sync + 'static,
+    {
+        assert!(rate >= 0.0, "the rate must not be negative");
+        assert!(burst >= 0.0, "the burst must not be negative");
+        Queue {
+            inner: fifo::Queue::new(),
+            global: None,
+            classes: Some(ClassLimit {
+                classify: Box::new(classify),
+                rate,
+                burst,
+                buckets: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Limit removing entries from the whole queue by a bucket, in addition to the buckets of
+    /// the classes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` or `burst` is negative.
+    pub fn set_global_limit(&mut self, rate: f64, burst: f64) {
+        self.global = Some(TokenBucket::new(rate, burst, Instant::now()));
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Insert an entry at the end of the queue.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.inner.insert(key, value);
+    }
+
+    /// Remove the first entry for which a token is available at `now`, and return it if there
+    /// was one.
+    pub fn pop_ready(&mut self, now: Instant) -> Option<(K, V)> {
+        if let Some(ref mut global) = self.global {
+            if global.tokens(now) < 1.0 {
+                return None;
+            }
+        }
+        let key = match self.classes {
+            Some(ref mut limit) => {
+                let ClassLimit {
+                    ref classify,
+                    rate,
+                    burst,
+                    ref mut buckets,
+                } = *limit;
+                let (key, _) = self.inner.find(|key, _| {
+                    buckets
+                        .entry(classify(key))
+                        .or_insert_with(|| TokenBucket::new(rate, burst, now))
+                        .try_take(1.0, now)
+                })?;
+                key.clone()
+            }
+            None => self.inner.first_key()?.clone(),
+        };
+        if let Some(ref mut global) = self.global {
+            global.try_take(1.0, now);
+        }
+        let value = self.inner.remove_key(&key)?;
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key)
+    }
+}
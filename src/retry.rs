@@ -0,0 +1,404 @@
+//! Implementation of an addressable retry queue, where failed entries become available again
+//! after an exponential backoff.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use delay;
+use fifo;
+use std::boxed::Box;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::{Duration, Instant};
+use time::{Clock, SystemClock};
+
+/// How long a [`Queue`](struct.Queue.html) waits before an entry is retried.
+///
+/// The first retry waits `initial`, every further retry `factor` times as long as the one
+/// before, up to `max`. With jitter, every delay is shortened by a random share of up to
+/// `jitter`, so entries which failed together are not all retried at the same time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Backoff {
+    /// The delay before the first retry.
+    pub initial: Duration,
+    /// The factor by which the delay grows with every retry.
+    pub factor: u32,
+    /// The longest delay.
+    pub max: Duration,
+    /// The largest share of a delay removed at random, between `0.0` and `1.0`. Other values are
+    /// clamped to that range, and NaN disables jitter.
+    pub jitter: f64,
+}
+
+impl Backoff {
+    /// Returns the delay before the given retry, where the first retry is `1`, without jitter.
+    ///
+    /// ```
+    /// use addressable_queue::retry::Backoff;
+    /// use std::time::Duration;
+    ///
+    /// let backoff = Backoff {
+    ///     initial: Duration::from_secs(1),
+    ///     factor: 2,
+    ///     max: Duration::from_secs(10),
+    ///     jitter: 0.0,
+    /// };
+    ///
+    /// assert_eq!(Duration::from_secs(1), backoff.delay(1));
+    /// assert_eq!(Duration::from_secs(4), backoff.delay(3));
+    /// assert_eq!(Duration::from_secs(10), backoff.delay(5));
+    /// ```
+    pub fn delay(&self, retry: u32) -> Duration {
+        let mut delay = self.initial;
+        if self.factor > 1 {
+            for _ in 1..retry {
+                if delay >= self.max {
+                    break;
+                }
+                delay = delay.saturating_mul(self.factor);
+            }
+        }
+        delay.min(self.max)
+    }
+}
+
+impl Default for Backoff {
+    /// Starts at 100 milliseconds and doubles up to a minute, with up to half of every delay
+    /// removed at random.
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(100),
+            factor: 2,
+            max: Duration::from_secs(60),
+            jitter: 0.5,
+        }
+    }
+}
+
+/// An addressable queue of entries which are retried after a backoff when processing them
+/// failed.
+///
+/// Entries are available as soon as they are inserted. An entry which was removed for
+/// processing is either reported with [`fail`](#method.fail), which puts it back until its
+/// backoff elapsed, or with [`complete`](#method.complete). The queue counts the attempts of an
+/// entry until it completes. With a maximum number of attempts, entries which failed that often
+/// are moved to a queue of dead letters instead, where they can be inspected and replayed.
+///
+/// ```
+/// use addressable_queue::retry::{Backoff, Queue};
+/// use std::time::{Duration, Instant};
+///
+/// let mut queue = Queue::with_backoff(Backoff {
+///     jitter: 0.0,
+///     ..Backoff::default()
+/// });
+/// queue.insert("upload", ());
+///
+/// let (key, value) = queue.pop_ready(Instant::now()).unwrap();
+/// let retry_at = queue.fail(key, value).unwrap();
+/// assert_eq!(Some(1), queue.attempts(&"upload"));
+///
+/// assert_eq!(None, queue.pop_ready(retry_at - Duration::from_millis(1)));
+/// assert_eq!(Some(("upload", ())), queue.pop_ready(retry_at));
+/// assert_eq!(Some(1), queue.complete(&"upload"));
+/// ```
+pub struct Queue<K, V> {
+    pending: delay::Queue<K, V>,
+    /// The number of failed attempts of every entry, also while it is processed.
+    attempts: HashMap<K, u32>,
+    backoff: Backoff,
+    max_attempts: Option<u32>,
+    /// The entries which failed too often.
+    dead_letters: fifo::Queue<K, V>,
+    /// State of the generator of the jitter.
+    random: u64,
+    clock: Box<dyn Clock>,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue with the default backoff.
+    pub fn new() -> Self {
+        Queue::with_backoff(Backoff::default())
+    }
+
+    /// Create a new instance of a queue with the given backoff.
+    pub fn with_backoff(backoff: Backoff) -> Self {
+        Queue {
+            pending: delay::Queue::new(),
+            attempts: HashMap::new(),
+            backoff,
+            max_attempts: None,
+            dead_letters: fifo::Queue::new(),
+            random: RandomState::new().build_hasher().finish() | 1,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// Read the current time from `clock` for scheduling entries, instead of from the system.
+    ///
+    /// ```
+    /// use addressable_queue::retry::{Backoff, Queue};
+    /// use addressable_queue::time::{Clock, MockClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = MockClock::new();
+    /// let mut queue = Queue::with_backoff(Backoff {
+    ///     initial: Duration::from_secs(1),
+    ///     jitter: 0.0,
+    ///     ..Backoff::default()
+    /// });
+    /// queue.set_clock(clock.clone());
+    /// queue.insert("upload", ());
+    ///
+    /// let (key, value) = queue.pop_ready(clock.now()).unwrap();
+    /// queue.fail(key, value);
+    /// assert_eq!(None, queue.pop_ready(clock.now()));
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// assert_eq!(Some(("upload", ())), queue.pop_ready(clock.now()));
+    /// ```
+    pub fn set_clock<C>(&mut self, clock: C)
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Box::new(clock);
+    }
+
+    /// Returns the backoff of the queue.
+    pub fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    /// Returns the number of failed attempts after which an entry is moved to the dead letters,
+    /// or `None` if entries are retried indefinitely.
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    /// Set the number of failed attempts after which an entry is moved to the dead letters, or
+    /// `None` to retry entries indefinitely.
+    ///
+    /// ```
+    /// use addressable_queue::retry::Queue;
+    /// use std::time::Instant;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.set_max_attempts(Some(1));
+    /// queue.insert("upload", ());
+    ///
+    /// let (key, value) = queue.pop_ready(Instant::now()).unwrap();
+    /// assert_eq!(None, queue.fail(key, value));
+    /// assert!(queue.dead_letters().contains_key(&"upload"));
+    ///
+    /// assert!(queue.replay(&"upload"));
+    /// assert_eq!(Some(("upload", ())), queue.pop_ready(Instant::now()));
+    /// ```
+    pub fn set_max_attempts(&mut self, max_attempts: Option<u32>) {
+        self.max_attempts = max_attempts;
+    }
+
+    /// Returns the entries which failed too often, in the order they were moved there.
+    pub fn dead_letters(&self) -> &fifo::Queue<K, V> {
+        &self.dead_letters
+    }
+
+    /// Returns the entries which failed too often, to remove them.
+    pub fn dead_letters_mut(&mut self) -> &mut fifo::Queue<K, V> {
+        &mut self.dead_letters
+    }
+
+    /// Move a dead letter back into the queue, where it is available right away and its attempts
+    /// are counted from zero, and return whether it was a dead letter.
+    pub fn replay(&mut self, key: &K) -> bool {
+        match self.dead_letters.remove_key(key) {
+            Some(value) => {
+                self.insert(key.clone(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move all dead letters back into the queue as by [`replay`](#method.replay), and return how
+    /// many there were.
+    pub fn replay_all(&mut self) -> usize {
+        let mut count = 0;
+        while let Some((key, value)) = self.dead_letters.remove_head() {
+            self.insert(key, value);
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns the number of entries waiting in the queue, which excludes the ones being
+    /// processed.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns true if no entries are waiting in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns true if an entry for the specified key is waiting in the queue.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.pending.contains_key(key)
+    }
+
+    /// Returns the number of failed attempts of the entry with the specified key, while it is
+    /// waiting or being processed.
+    pub fn attempts(&self, key: &K) -> Option<u32> {
+        self.attempts.get(key).cloned()
+    }
+
+    /// Returns the instant the waiting entry with the specified key becomes available.
+    pub fn ready_at(&self, key: &K) -> Option<Instant> {
+        self.pending.available_at(key)
+    }
+
+    /// Returns the earliest instant a waiting entry becomes available.
+    pub fn next_ready(&self) -> Option<Instant> {
+        self.pending.next_available()
+    }
+
+    /// Returns a reference to the value of the waiting entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.pending.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the waiting entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.pending.get_mut(key)
+    }
+
+    /// Insert an entry which is available right away, and return the old value of the key.
+    ///
+    /// This starts counting the attempts of the key from zero, and removes a dead letter of the
+    /// key.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.attempts.insert(key.clone(), 0);
+        let dead = self.dead_letters.remove_key(&key);
+        let now = self.clock.now();
+        self.pending.insert_at(key, value, now).or(dead)
+    }
+
+    /// Remove an entry whose backoff elapsed at `now` for processing, in the order they became
+    /// available.
+    ///
+    /// The queue keeps counting the attempts of the entry until it is reported with
+    /// [`fail`](#method.fail) or [`complete`](#method.complete).
+    pub fn pop_ready(&mut self, now: Instant) -> Option<(K, V)> {
+        self.pending.poll_expired(now).next()
+    }
+
+    /// Report that processing an entry failed, which puts it back into the queue until its
+    /// backoff elapsed, and return the instant it becomes available again.
+    ///
+    /// If the entry reached the maximum number of attempts, it is moved to the dead letters
+    /// instead and `None` is returned. A backoff too long to be represented as an `Instant` is
+    /// shortened until it can.
+    pub fn fail(&mut self, key: K, value: V) -> Option<Instant> {
+        let attempts = {
+            let attempts = self.attempts.entry(key.clone()).or_insert(0);
+            *attempts = attempts.saturating_add(1);
+            *attempts
+        };
+        if self.max_attempts.is_some_and(|max| attempts >= max) {
+            self.attempts.remove(&key);
+            self.dead_letters.insert(key, value);
+            return None;
+        }
+        let mut delay = self.backoff.delay(attempts);
+        delay -= self.jitter(delay);
+        let now = self.clock.now();
+        let at = loop {
+            match now.checked_add(delay) {
+                Some(at) => break at,
+                None => delay /= 2,
+            }
+        };
+        self.pending.insert_at(key, value, at);
+        Some(at)
+    }
+
+    /// Report that processing an entry completed, which stops counting its attempts, and return
+    /// the number of failed attempts.
+    pub fn complete(&mut self, key: &K) -> Option<u32> {
+        self.attempts.remove(key)
+    }
+
+    /// Remove a waiting entry by specifying its key, which also stops counting its attempts.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let value = self.pending.remove_key(key)?;
+        self.attempts.remove(key);
+        Some(value)
+    }
+
+    /// Returns a random share of `delay`, of at most the jitter of the backoff.
+    fn jitter(&mut self, delay: Duration) -> Duration {
+        let max_share = if self.backoff.jitter.is_nan() {
+            0.0
+        } else {
+            self.backoff.jitter.clamp(0.0, 1.0)
+        };
+        let share = max_share * self.next_random();
+        Duration::try_from_secs_f64(delay.as_secs_f64() * share)
+            .unwrap_or(delay)
+            .min(delay)
+    }
+
+    /// Returns a random number between `0.0` and `1.0`.
+    fn next_random(&mut self) -> f64 {
+        // xorshift64
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        (self.random >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn extreme_backoffs_do_not_panic() {
+    for &jitter in &[-1.0, 0.5, 2.0, f64::NAN, f64::INFINITY] {
+        let mut queue = Queue::with_backoff(Backoff {
+            initial: Duration::MAX,
+            factor: 2,
+            max: Duration::MAX,
+            jitter,
+        });
+        queue.insert("upload", ());
+        let now = queue.clock.now();
+        let (key, value) = queue.pop_ready(now).unwrap();
+
+        assert!(queue.fail(key, value).unwrap() >= now);
+        assert_eq!(None, queue.pop_ready(now));
+    }
+}
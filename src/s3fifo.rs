@@ -0,0 +1,172 @@
+//! Implementation of an addressable S3-FIFO cache, which is built from three FIFO queues.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::hash::Hash;
+use deque::Deque;
+use set;
+
+/// The highest access count of an entry.
+const MAX_FREQUENCY: u8 = 3;
+
+/// An addressable S3-FIFO cache holding at most `capacity` entries.
+///
+/// New entries are placed in a small FIFO queue taking a tenth of the capacity, the other
+/// entries are in the main FIFO queue. Accesses only increment a small counter of the entry.
+///
+/// When the small queue is evicted from, entries which were accessed there move to the main
+/// queue, the others are evicted and their keys remembered in a ghost queue. Keys in the ghost
+/// queue are inserted into the main queue directly. When the main queue is evicted from, entries
+/// which were accessed are reinserted at its end with a decremented counter, the first entry
+/// which was not is evicted.
+///
+/// ```
+/// use addressable_queue::s3fifo::Cache;
+///
+/// let mut cache = Cache::new(10);
+/// for key in 0..10 {
+///     cache.insert(key, ());
+/// }
+/// cache.get(&0);
+///
+/// // The entry was accessed, so it moves to the main queue instead of being evicted.
+/// assert_eq!(Some((1, ())), cache.insert(10, ()));
+/// assert!(cache.contains_key(&0));
+/// ```
+pub struct Cache<K, V> {
+    small: Deque<K, (u8, V)>,
+    main: Deque<K, (u8, V)>,
+    ghost: set::Queue<K>,
+    capacity: usize,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Cache {
+            small: Deque::new(),
+            main: Deque::with_capacity(capacity),
+            ghost: set::Queue::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.small.len() + self.main.len()
+    }
+
+    /// Returns true if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.small.is_empty() && self.main.is_empty()
+    }
+
+    /// Returns true if the cache contains an entry for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.small.contains_key(key) || self.main.contains_key(key)
+    }
+
+    /// Access an entry, which increments its access count.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Access an entry mutably, which increments its access count.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let entry = match self.main.get_mut(key) {
+            Some(entry) => Some(entry),
+            None => self.small.get_mut(key),
+        };
+        entry.map(|&mut (ref mut frequency, ref mut value)| {
+            *frequency = (*frequency + 1).min(MAX_FREQUENCY);
+            value
+        })
+    }
+
+    /// Returns a reference to the value of the specified key, without counting as an access.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.main
+            .get(key)
+            .or_else(|| self.small.get(key))
+            .map(|(_, value)| value)
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its value is replaced, its access count incremented, and
+    /// the old entry is returned. Otherwise, if the cache is full, an entry is evicted and
+    /// returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        if let Some(slot) = self.get_mut(&key) {
+            let old = ::core::mem::replace(slot, value);
+            return Some((key, old));
+        }
+        let evicted = if self.len() >= self.capacity {
+            self.evict()
+        } else {
+            None
+        };
+        if self.ghost.remove(&key) {
+            self.main.push_back(key, (0, value));
+        } else {
+            self.small.push_back(key, (0, value));
+        }
+        evicted
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let entry = match self.main.remove_key(key) {
+            Some(entry) => Some(entry),
+            None => self.small.remove_key(key),
+        };
+        entry.map(|(_, value)| value)
+    }
+
+    /// Evict an entry, and return it if there was one.
+    pub fn evict(&mut self) -> Option<(K, V)> {
+        let small_share = (self.capacity / 10).max(1);
+        loop {
+            if self.small.len() >= small_share || self.main.is_empty() {
+                let (key, (frequency, value)) = self.small.pop_front()?;
+                if frequency > 0 {
+                    self.main.push_back(key, (0, value));
+                    continue;
+                }
+                self.ghost.insert(key.clone());
+                if self.ghost.len() > self.capacity.saturating_sub(small_share).max(1) {
+                    self.ghost.remove_head();
+                }
+                return Some((key, value));
+            }
+            let (key, (frequency, value)) = self.main.pop_front()?;
+            if frequency == 0 {
+                return Some((key, value));
+            }
+            self.main.push_back(key, (frequency - 1, value));
+        }
+    }
+}
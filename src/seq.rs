@@ -0,0 +1,178 @@
+//! Implementation of an addressable FIFO queue which assigns a sequence number to every insertion.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::{self, Deque};
+
+/// An addressable FIFO queue where every insertion is assigned a sequence number.
+///
+/// Sequence numbers increase monotonically, so the entries of the queue are always ordered by
+/// their sequence number.
+pub struct Queue<K, V> {
+    inner: Deque<K, (u64, V)>,
+    next_seq: u64,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            inner: Deque::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns the sequence number the next insertion will be assigned.
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Returns the sequence number of the entry with the specified key.
+    pub fn seq_of(&self, key: &K) -> Option<u64> {
+        self.inner.get(key).map(|&(seq, _)| seq)
+    }
+
+    /// Insert an entry at the end of the queue, and return its sequence number.
+    ///
+    /// An existing entry for the key is removed.
+    pub fn insert(&mut self, key: K, value: V) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.inner.remove_key(&key);
+        self.inner.push_back(key, (seq, value));
+        seq
+    }
+
+    /// Remove the current head of the queue, and return it with its sequence number.
+    pub fn remove_head(&mut self) -> Option<(u64, K, V)> {
+        self.inner
+            .pop_front()
+            .map(|(key, (seq, value))| (seq, key, value))
+    }
+
+    /// Remove the current tail of the queue, and return it with its sequence number.
+    pub fn remove_tail(&mut self) -> Option<(u64, K, V)> {
+        self.inner
+            .pop_back()
+            .map(|(key, (seq, value))| (seq, key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.inner.remove_key(key).map(|(_, value)| value)
+    }
+
+    /// Remove all entries with a sequence number lower than `seq`, and return how many were
+    /// removed.
+    ///
+    /// ```
+    /// use addressable_queue::seq::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert('a', 1);
+    /// let checkpoint = queue.insert('b', 2);
+    /// queue.insert('c', 3);
+    /// queue.insert('a', 4);
+    ///
+    /// assert_eq!(1, queue.remove_older_than(checkpoint + 1));
+    /// let remaining: Vec<_> = queue.iter().collect();
+    /// assert_eq!(remaining, vec![(2, &'c', &3), (3, &'a', &4)]);
+    /// ```
+    pub fn remove_older_than(&mut self, seq: u64) -> usize {
+        let mut removed = 0;
+        while let Some((_, &(head, _))) = self.inner.peek_front() {
+            if head >= seq {
+                break;
+            }
+            self.inner.pop_front();
+            removed += 1;
+        }
+        removed
+    }
+
+    /// Returns an iterator over the entries and their sequence numbers, starting at the head.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.inner.iter(),
+        }
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    pub fn into_vec(self) -> Vec<(u64, K, V)> {
+        self.inner
+            .into_vec()
+            .into_iter()
+            .map(|(key, (seq, value))| (seq, key, value))
+            .collect()
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// Iterator over the entries of a queue and their sequence numbers.
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: deque::Iter<'a, K, (u64, V)>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (u64, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(key, (seq, value))| (*seq, key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|(key, (seq, value))| (*seq, key, value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
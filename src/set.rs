@@ -0,0 +1,192 @@
+//! Implementation of an addressable FIFO set, that is a FIFO queue of keys where each key is
+//! contained at most once and can be removed directly.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::{self, Deque};
+
+/// An addressable FIFO set.
+pub struct Queue<K> {
+    inner: Deque<K, ()>,
+}
+
+impl<K> Queue<K>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the set.
+    pub fn new() -> Self {
+        Queue {
+            inner: Deque::new(),
+        }
+    }
+
+    /// Returns the number of keys in the set.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the set contains no keys.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the set contains the key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Insert a key at the end of the queue.
+    ///
+    /// Returns false, and leaves the position of the key unchanged, if the key was already
+    /// contained in the set.
+    ///
+    /// ```
+    /// use addressable_queue::set::Queue;
+    ///
+    /// let mut set = Queue::new();
+    /// assert!(set.insert(2u8));
+    /// assert!(set.insert(3u8));
+    /// assert!(!set.insert(2u8));
+    ///
+    /// assert_eq!(set.into_vec(), vec![2, 3]);
+    /// ```
+    pub fn insert(&mut self, key: K) -> bool {
+        if self.inner.contains_key(&key) {
+            return false;
+        }
+        self.inner.push_back(key, ());
+        true
+    }
+
+    /// Insert a key at the front of the queue.
+    ///
+    /// Returns false, and leaves the position of the key unchanged, if the key was already
+    /// contained in the set.
+    pub fn insert_head(&mut self, key: K) -> bool {
+        if self.inner.contains_key(&key) {
+            return false;
+        }
+        self.inner.push_front(key, ());
+        true
+    }
+
+    /// Returns the current head of the queue.
+    pub fn first(&self) -> Option<&K> {
+        self.inner.peek_front().map(|(key, _)| key)
+    }
+
+    /// Returns the current tail of the queue.
+    pub fn last(&self) -> Option<&K> {
+        self.inner.peek_back().map(|(key, _)| key)
+    }
+
+    /// Remove the current head of the queue, and return it if there was one.
+    ///
+    /// ```
+    /// use addressable_queue::set::Queue;
+    ///
+    /// let mut set = Queue::new();
+    /// set.insert(2u8);
+    /// set.insert(3u8);
+    ///
+    /// assert_eq!(Some(2), set.remove_head());
+    /// assert_eq!(Some(3), set.remove_head());
+    /// assert_eq!(None, set.remove_head());
+    /// ```
+    pub fn remove_head(&mut self) -> Option<K> {
+        self.inner.pop_front().map(|(key, _)| key)
+    }
+
+    /// Remove the current tail of the queue, and return it if there was one.
+    pub fn remove_tail(&mut self) -> Option<K> {
+        self.inner.pop_back().map(|(key, _)| key)
+    }
+
+    /// Remove a key from the set, returning true if it was contained.
+    ///
+    /// ```
+    /// use addressable_queue::set::Queue;
+    ///
+    /// let mut set = Queue::new();
+    /// set.insert(2u8);
+    /// set.insert(3u8);
+    ///
+    /// assert!(set.remove(&2));
+    /// assert!(!set.remove(&2));
+    /// assert_eq!(set.into_vec(), vec![3]);
+    /// ```
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.inner.remove_key(key).is_some()
+    }
+
+    /// Returns an iterator over the keys from head to tail.
+    ///
+    /// ```
+    /// use addressable_queue::set::Queue;
+    ///
+    /// let mut set = Queue::new();
+    /// set.insert(2u8);
+    /// set.insert(3u8);
+    ///
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec![&2, &3]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter {
+            inner: self.inner.iter(),
+        }
+    }
+
+    /// Convert the set into a vec, where the first element is the head (oldest key).
+    pub fn into_vec(self) -> Vec<K> {
+        self.inner.into_vec().into_iter().map(|(key, _)| key).collect()
+    }
+}
+
+impl<K> Default for Queue<K>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// Iterator over the keys of a set.
+///
+/// Created by [`Queue::iter`](struct.Queue.html#method.iter).
+pub struct Iter<'a, K: 'a> {
+    inner: deque::Iter<'a, K, ()>,
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K> DoubleEndedIterator for Iter<'a, K> {
+    fn next_back(&mut self) -> Option<&'a K> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
@@ -0,0 +1,384 @@
+//! Implementation of handles to an addressable FIFO queue which is shared between threads.
+//!
+//! [`Queue`](struct.Queue.html) locks the queue for every operation, while
+//! [`RwQueue`](struct.RwQueue.html) lets operations which only read run at the same time.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fifo::{self, RenameError};
+use primitives::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::hash::Hash;
+use std::time::Duration;
+use std::vec::Vec;
+
+/// A handle to an addressable FIFO queue shared between threads, with the API of
+/// [`fifo::Queue`] on `&self`.
+///
+/// Cloning the handle does not clone the queue, all clones refer to the same entries. Every
+/// method holds the lock only while it runs, so a sequence of calls is not atomic; use
+/// [`with`](#method.with) for that. Methods which would return references return clones
+/// instead.
+///
+/// [`fifo::Queue`]: ../fifo/struct.Queue.html
+///
+/// ```
+/// use addressable_queue::shared::Queue;
+/// use std::thread;
+///
+/// let queue = Queue::new();
+/// let handles: Vec<_> = (0..4u32)
+///     .map(|i| {
+///         let queue = queue.clone();
+///         thread::spawn(move || queue.insert(i, i * 2))
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+///
+/// assert_eq!(4, queue.len());
+/// assert_eq!(Some(6), queue.remove_key(&3));
+/// ```
+pub struct Queue<K, V> {
+    inner: Arc<Mutex<fifo::Queue<K, V>>>,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue::from(fifo::Queue::new())
+    }
+
+    /// Create a new instance of a queue with space for at least `capacity` elements.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Queue::from(fifo::Queue::with_capacity(capacity))
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.lock().contains_key(key)
+    }
+
+    /// Returns a clone of the head of the queue.
+    pub fn peek_head(&self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        let queue = self.lock();
+        let (key, value) = queue.peek_head()?;
+        Some((key.clone(), value.clone()))
+    }
+
+    /// Returns a clone of the key of the head of the queue.
+    pub fn first_key(&self) -> Option<K> {
+        self.lock().first_key().cloned()
+    }
+
+    /// Returns a clone of the key of the tail of the queue.
+    pub fn last_key(&self) -> Option<K> {
+        self.lock().last_key().cloned()
+    }
+
+    /// Insert an entry at the end of the queue.
+    pub fn insert(&self, key: K, value: V) {
+        self.lock().insert(key, value);
+    }
+
+    /// Insert an entry at the end of the queue, which expires once `ttl` passed.
+    pub fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        self.lock().insert_with_ttl(key, value, ttl);
+    }
+
+    /// Insert an entry at the end of the queue, or hand the pair back in the error if that
+    /// would require allocating.
+    pub fn insert_within_capacity(&self, key: K, value: V) -> Result<(), (K, V)> {
+        self.lock().insert_within_capacity(key, value)
+    }
+
+    /// Insert an entry at the front of the queue.
+    pub fn insert_head(&self, key: K, value: V) {
+        self.lock().insert_head(key, value);
+    }
+
+    /// Remove the current head of the queue, and return the value if there was one.
+    pub fn remove_head(&self) -> Option<(K, V)> {
+        self.lock().remove_head()
+    }
+
+    /// Remove the current tail of the queue, and return the value if there was one.
+    pub fn remove_tail(&self) -> Option<(K, V)> {
+        self.lock().remove_tail()
+    }
+
+    /// Replace the value of a key without changing its position, and return the old value.
+    pub fn replace(&self, key: &K, value: V) -> Option<V> {
+        self.lock().replace(key, value)
+    }
+
+    /// Change the key of an entry without changing its position.
+    pub fn rename_key(&self, old: &K, new: K) -> Result<(), RenameError> {
+        self.lock().rename_key(old, new)
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&self, key: &K) -> Option<V> {
+        self.lock().remove_key(key)
+    }
+
+    /// Remove all expired entries, and return them from head to tail.
+    pub fn purge_expired(&self) -> Vec<(K, V)> {
+        self.lock().purge_expired()
+    }
+
+    /// Remove the entries which were inserted at least `age` ago, and return them from head to
+    /// tail.
+    pub fn expire_older_than(&self, age: Duration) -> Vec<(K, V)> {
+        self.lock().expire_older_than(age)
+    }
+
+    /// Returns how long ago the head of the queue was inserted, if its insertion time was
+    /// recorded.
+    pub fn age_of_head(&self) -> Option<Duration> {
+        self.lock().age_of_head()
+    }
+
+    /// Run `f` on the queue while holding the lock, so that several operations are applied at
+    /// once.
+    ///
+    /// ```
+    /// use addressable_queue::shared::Queue;
+    ///
+    /// let queue = Queue::new();
+    /// queue.insert("a", 1);
+    ///
+    /// // Move the head to the tail without another thread seeing the queue without it.
+    /// queue.with(|queue| {
+    ///     if let Some((key, value)) = queue.remove_head() {
+    ///         queue.insert(key, value + 1);
+    ///     }
+    /// });
+    /// assert_eq!(Some(("a", 2)), queue.peek_head());
+    /// ```
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut fifo::Queue<K, V>) -> R,
+    {
+        f(&mut self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, fifo::Queue<K, V>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<K, V> From<fifo::Queue<K, V>> for Queue<K, V> {
+    fn from(queue: fifo::Queue<K, V>) -> Self {
+        Queue {
+            inner: Arc::new(Mutex::new(queue)),
+        }
+    }
+}
+
+impl<K, V> Clone for Queue<K, V> {
+    fn clone(&self) -> Self {
+        Queue {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// A handle to an addressable FIFO queue shared between threads, where operations which only
+/// read the queue take a shared lock.
+///
+/// This reduces contention compared to [`Queue`](struct.Queue.html) when the queue is mostly
+/// inspected, for example by monitoring threads, and rarely changed.
+///
+/// ```
+/// use addressable_queue::shared::RwQueue;
+///
+/// let queue = RwQueue::new();
+/// queue.insert("a", 1);
+/// queue.insert("b", 2);
+///
+/// let reader = queue.clone();
+/// assert!(reader.contains_key(&"a"));
+/// assert_eq!(vec![("a", 1), ("b", 2)], reader.iter_snapshot());
+/// ```
+pub struct RwQueue<K, V> {
+    inner: Arc<RwLock<fifo::Queue<K, V>>>,
+}
+
+impl<K, V> RwQueue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        RwQueue::from(fifo::Queue::new())
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.read_lock().len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.read_lock().is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.read_lock().contains_key(key)
+    }
+
+    /// Returns a clone of the head of the queue.
+    pub fn peek_head(&self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        let queue = self.read_lock();
+        let (key, value) = queue.peek_head()?;
+        Some((key.clone(), value.clone()))
+    }
+
+    /// Returns a clone of all entries which are not expired, from head to tail.
+    pub fn iter_snapshot(&self) -> Vec<(K, V)>
+    where
+        V: Clone,
+    {
+        self.read(|queue| {
+            queue
+                .iter()
+                .filter(|&(key, _)| queue.contains_key(key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect()
+        })
+    }
+
+    /// Insert an entry at the end of the queue.
+    pub fn insert(&self, key: K, value: V) {
+        self.write_lock().insert(key, value);
+    }
+
+    /// Insert an entry at the front of the queue.
+    pub fn insert_head(&self, key: K, value: V) {
+        self.write_lock().insert_head(key, value);
+    }
+
+    /// Remove the current head of the queue, and return the value if there was one.
+    pub fn remove_head(&self) -> Option<(K, V)> {
+        self.write_lock().remove_head()
+    }
+
+    /// Remove the current tail of the queue, and return the value if there was one.
+    pub fn remove_tail(&self) -> Option<(K, V)> {
+        self.write_lock().remove_tail()
+    }
+
+    /// Replace the value of a key without changing its position, and return the old value.
+    pub fn replace(&self, key: &K, value: V) -> Option<V> {
+        self.write_lock().replace(key, value)
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&self, key: &K) -> Option<V> {
+        self.write_lock().remove_key(key)
+    }
+
+    /// Remove all expired entries, and return them from head to tail.
+    pub fn purge_expired(&self) -> Vec<(K, V)> {
+        self.write_lock().purge_expired()
+    }
+
+    /// Run `f` on the queue while holding a shared lock, which other readers can hold as well.
+    pub fn read<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&fifo::Queue<K, V>) -> R,
+    {
+        f(&self.read_lock())
+    }
+
+    /// Run `f` on the queue while holding the exclusive lock.
+    pub fn write<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut fifo::Queue<K, V>) -> R,
+    {
+        f(&mut self.write_lock())
+    }
+
+    fn read_lock(&self) -> RwLockReadGuard<'_, fifo::Queue<K, V>> {
+        self.inner
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write_lock(&self) -> RwLockWriteGuard<'_, fifo::Queue<K, V>> {
+        self.inner
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<K, V> From<fifo::Queue<K, V>> for RwQueue<K, V> {
+    fn from(queue: fifo::Queue<K, V>) -> Self {
+        RwQueue {
+            inner: Arc::new(RwLock::new(queue)),
+        }
+    }
+}
+
+impl<K, V> Clone for RwQueue<K, V> {
+    fn clone(&self) -> Self {
+        RwQueue {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for RwQueue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        RwQueue::new()
+    }
+}
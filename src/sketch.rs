@@ -0,0 +1,99 @@
+//! Approximate counting of how often keys were used, for the TinyLFU admission policy.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Multipliers deriving the index of each row from the hash of a key.
+const SEEDS: [u64; 4] = [
+    0x9e37_79b9_7f4a_7c15,
+    0xc2b2_ae3d_27d4_eb4f,
+    0x1656_67b1_9e37_79f9,
+    0xff51_afd7_ed55_8ccd,
+];
+
+/// The highest count, counters saturate there.
+const MAX_COUNT: u8 = 15;
+
+/// A count-min sketch with four rows of small saturating counters.
+///
+/// Once as many uses as ten times the width were counted, all counters are halved, so the counts
+/// reflect the recent window of uses instead of the whole history.
+pub(crate) struct FrequencySketch {
+    counters: Vec<u8>,
+    width: usize,
+    additions: usize,
+    window: usize,
+}
+
+impl FrequencySketch {
+    /// Create a sketch suited to a cache holding `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        let width = capacity.max(16).next_power_of_two();
+        FrequencySketch {
+            counters: vec![0; SEEDS.len() * width],
+            width,
+            additions: 0,
+            window: 10 * width,
+        }
+    }
+
+    /// Resize the sketch for a cache holding `capacity` entries, which restarts the counts if
+    /// its width changes.
+    pub fn resize(&mut self, capacity: usize) {
+        let resized = FrequencySketch::new(capacity);
+        if resized.width != self.width {
+            *self = resized;
+        }
+    }
+
+    /// Resize the sketch if a cache holding `capacity` entries outgrew it.
+    pub fn grow(&mut self, capacity: usize) {
+        if capacity > self.width {
+            *self = FrequencySketch::new(capacity);
+        }
+    }
+
+    /// Count a use of the key with the given hash.
+    pub fn increment(&mut self, hash: u64) {
+        for row in 0..SEEDS.len() {
+            let index = self.index(hash, row);
+            if self.counters[index] < MAX_COUNT {
+                self.counters[index] += 1;
+            }
+        }
+        self.additions += 1;
+        if self.additions >= self.window {
+            for counter in self.counters.iter_mut() {
+                *counter /= 2;
+            }
+            self.additions /= 2;
+        }
+    }
+
+    /// Returns the estimated number of uses of the key with the given hash.
+    pub fn frequency(&self, hash: u64) -> u8 {
+        (0..SEEDS.len())
+            .map(|row| self.counters[self.index(hash, row)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn index(&self, hash: u64, row: usize) -> usize {
+        let mixed = hash.wrapping_mul(SEEDS[row]);
+        row * self.width + ((mixed >> 32) as usize & (self.width - 1))
+    }
+}
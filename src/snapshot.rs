@@ -0,0 +1,236 @@
+//! Implementation of an addressable FIFO queue with a single writer, which publishes immutable
+//! snapshots that readers load without locking.
+//!
+//! This module requires the `snapshot` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use arc_swap::ArcSwap;
+use fifo;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::slice;
+use std::sync::Arc;
+use std::vec::Vec;
+
+/// An addressable FIFO queue owned by a single writer, which readers on other threads observe
+/// through published snapshots.
+///
+/// Mutations only change the queue of the writer. Calling [`publish`](#method.publish) copies
+/// the entries into a new [`Snapshot`](struct.Snapshot.html) and swaps it in atomically, so a
+/// [`Reader`](struct.Reader.html) never blocks the writer and always sees a consistent state,
+/// which may be older than the queue. As publishing copies every entry, a writer applying many
+/// mutations should publish once after a batch of them.
+///
+/// ```
+/// use addressable_queue::snapshot::Queue;
+/// use std::thread;
+///
+/// let mut queue = Queue::new();
+/// let reader = queue.reader();
+/// queue.insert("a", 1);
+/// queue.insert("b", 2);
+/// assert!(reader.load().is_empty());
+///
+/// queue.publish();
+/// let monitor = thread::spawn(move || {
+///     let snapshot = reader.load();
+///     (snapshot.len(), snapshot.peek_head().cloned())
+/// });
+/// assert_eq!((2, Some(("a", 1))), monitor.join().unwrap());
+/// ```
+pub struct Queue<K, V> {
+    queue: fifo::Queue<K, V>,
+    published: Arc<ArcSwap<Snapshot<K, V>>>,
+    version: u64,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// Create a new instance of a queue, which publishes an empty snapshot.
+    pub fn new() -> Self {
+        Queue::from(fifo::Queue::new())
+    }
+
+    /// Returns a reference to the queue of the writer, including unpublished changes.
+    pub fn get_ref(&self) -> &fifo::Queue<K, V> {
+        &self.queue
+    }
+
+    /// Returns a mutable reference to the queue of the writer, to apply any of its mutations.
+    ///
+    /// The changes become visible to readers on the next [`publish`](#method.publish).
+    pub fn get_mut(&mut self) -> &mut fifo::Queue<K, V> {
+        &mut self.queue
+    }
+
+    /// Insert an entry at the end of the queue.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.queue.insert(key, value);
+    }
+
+    /// Remove the current head of the queue, and return the value if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        self.queue.remove_head()
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.queue.remove_key(key)
+    }
+
+    /// Make the current state of the queue visible to all readers.
+    pub fn publish(&mut self) {
+        self.version += 1;
+        let snapshot = Snapshot::of(&self.queue, self.version);
+        self.published.store(Arc::new(snapshot));
+    }
+
+    /// Create a reader of the published snapshots, which can be sent to other threads.
+    pub fn reader(&self) -> Reader<K, V> {
+        Reader {
+            published: self.published.clone(),
+        }
+    }
+}
+
+impl<K, V> From<fifo::Queue<K, V>> for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    /// Create a writer of the queue, which publishes its current state.
+    fn from(queue: fifo::Queue<K, V>) -> Self {
+        let snapshot = Snapshot::of(&queue, 0);
+        Queue {
+            queue,
+            published: Arc::new(ArcSwap::from_pointee(snapshot)),
+            version: 0,
+        }
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// A handle loading the snapshots published by a [`Queue`](struct.Queue.html).
+pub struct Reader<K, V> {
+    published: Arc<ArcSwap<Snapshot<K, V>>>,
+}
+
+impl<K, V> Reader<K, V> {
+    /// Returns the latest published snapshot, without blocking the writer.
+    ///
+    /// The snapshot stays valid for as long as it is held, also after newer ones were published.
+    pub fn load(&self) -> Arc<Snapshot<K, V>> {
+        self.published.load_full()
+    }
+}
+
+impl<K, V> Clone for Reader<K, V> {
+    fn clone(&self) -> Self {
+        Reader {
+            published: self.published.clone(),
+        }
+    }
+}
+
+/// An immutable copy of the entries of a [`Queue`](struct.Queue.html) at the time it was
+/// published.
+pub struct Snapshot<K, V> {
+    entries: Vec<(K, V)>,
+    positions: HashMap<K, usize>,
+    version: u64,
+}
+
+impl<K, V> Snapshot<K, V>
+where
+    K: Clone + Eq + Hash,
+    V: Clone,
+{
+    fn of(queue: &fifo::Queue<K, V>, version: u64) -> Self {
+        let entries: Vec<(K, V)> = queue
+            .live()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let positions = entries
+            .iter()
+            .enumerate()
+            .map(|(position, (key, _))| (key.clone(), position))
+            .collect();
+        Snapshot {
+            entries,
+            positions,
+            version,
+        }
+    }
+}
+
+impl<K, V> Snapshot<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Returns how many times the queue was published before this snapshot, starting at `0` for
+    /// the one published on creation.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the number of elements in the snapshot.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the snapshot contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns true if the snapshot contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let position = *self.positions.get(key)?;
+        Some(&self.entries[position].1)
+    }
+
+    /// Returns the position of the key, where the head is at position `0`.
+    pub fn position(&self, key: &K) -> Option<usize> {
+        self.positions.get(key).cloned()
+    }
+
+    /// Returns the head of the snapshot.
+    pub fn peek_head(&self) -> Option<&(K, V)> {
+        self.entries.first()
+    }
+
+    /// Returns an iterator over the entries from head to tail.
+    pub fn iter(&self) -> slice::Iter<'_, (K, V)> {
+        self.entries.iter()
+    }
+}
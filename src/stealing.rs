@@ -0,0 +1,211 @@
+//! Implementation of a set of addressable work queues, one per worker, where idle workers steal
+//! from the others.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use deque::Deque;
+use primitives::{Mutex, MutexGuard};
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Addressable work queues of several workers, where every key is unique across all of them.
+///
+/// Every worker takes its own entries from the head of its queue, and once it runs out steals
+/// from the tail of the queue of another worker, which takes the entries its owner would reach
+/// last. A shared index records which worker holds a key, so an entry can be cancelled or
+/// replaced without knowing where it is queued.
+///
+/// Workers are identified by their index, from `0` to [`workers`](#method.workers) exclusive.
+/// The methods taking a worker panic if it is out of range.
+///
+/// ```
+/// use addressable_queue::stealing::WorkQueues;
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// let queues = Arc::new(WorkQueues::new(2));
+/// for task in 0..8u32 {
+///     queues.push(0, task, ());
+/// }
+/// queues.cancel(&7);
+///
+/// // The second worker has no tasks of its own, so it steals from the first.
+/// let thief = {
+///     let queues = queues.clone();
+///     thread::spawn(move || queues.pop_or_steal(1).map(|(task, _)| task))
+/// };
+/// assert_eq!(Some(6), thief.join().unwrap());
+/// assert_eq!(Some((0, ())), queues.pop(0));
+/// assert_eq!(5, queues.len());
+/// ```
+pub struct WorkQueues<K, V> {
+    locals: Box<[Mutex<Local<K, V>>]>,
+    /// The worker and ticket of every queued key.
+    ///
+    /// Its lock is taken before the lock of a worker queue, never while one is held. Entries
+    /// removed by their worker are removed from the index afterwards, and only if the ticket
+    /// still matches, so a newer entry of the same key stays indexed.
+    index: Mutex<Index<K>>,
+}
+
+/// The queue of a worker, storing the ticket of every entry with its value.
+type Local<K, V> = Deque<K, (u64, V)>;
+
+struct Index<K> {
+    workers: HashMap<K, (usize, u64)>,
+    next_ticket: u64,
+}
+
+impl<K, V> WorkQueues<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create work queues for `workers` workers, at least one.
+    pub fn new(workers: usize) -> Self {
+        let locals = (0..workers.max(1))
+            .map(|_| Mutex::new(Deque::new()))
+            .collect();
+        WorkQueues {
+            locals,
+            index: Mutex::new(Index {
+                workers: HashMap::new(),
+                next_ticket: 0,
+            }),
+        }
+    }
+
+    /// Returns the number of workers.
+    pub fn workers(&self) -> usize {
+        self.locals.len()
+    }
+
+    /// Returns the number of entries of all workers.
+    pub fn len(&self) -> usize {
+        (0..self.locals.len()).map(|i| self.local(i).len()).sum()
+    }
+
+    /// Returns true if no worker has any entries.
+    pub fn is_empty(&self) -> bool {
+        (0..self.locals.len()).all(|i| self.local(i).is_empty())
+    }
+
+    /// Returns the number of entries queued for the worker.
+    pub fn len_of(&self, worker: usize) -> usize {
+        self.local(worker).len()
+    }
+
+    /// Returns the worker whose queue holds the key.
+    pub fn worker_of(&self, key: &K) -> Option<usize> {
+        let index = self.index();
+        let (worker, ticket) = *index.workers.get(key)?;
+        match self.local(worker).get(key) {
+            Some(&(queued, _)) if queued == ticket => Some(worker),
+            _ => None,
+        }
+    }
+
+    /// Returns true if any worker holds the key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.worker_of(key).is_some()
+    }
+
+    /// Insert an entry at the end of the queue of the worker.
+    ///
+    /// If any worker already holds the key, its entry is removed and its value returned.
+    pub fn push(&self, worker: usize, key: K, value: V) -> Option<V> {
+        let mut index = self.index();
+        let old = index
+            .workers
+            .get(&key)
+            .and_then(|&(holder, _)| self.local(holder).remove_key(&key))
+            .map(|(_, value)| value);
+        let ticket = index.next_ticket;
+        index.next_ticket += 1;
+        index.workers.insert(key.clone(), (worker, ticket));
+        self.local(worker).push_back(key, (ticket, value));
+        old
+    }
+
+    /// Remove the head of the queue of the worker.
+    pub fn pop(&self, worker: usize) -> Option<(K, V)> {
+        let popped = self.local(worker).pop_front();
+        self.unindex(worker, popped)
+    }
+
+    /// Remove the tail of the queue of another worker, trying them in turn starting after
+    /// `thief`.
+    pub fn steal(&self, thief: usize) -> Option<(K, V)> {
+        let workers = self.locals.len();
+        assert!(thief < workers, "worker {} out of range", thief);
+        (1..workers)
+            .map(|offset| (thief + offset) % workers)
+            .filter_map(|victim| {
+                let stolen = self.local(victim).pop_back();
+                self.unindex(victim, stolen)
+            })
+            .next()
+    }
+
+    /// Remove the head of the queue of the worker, or steal if it is empty.
+    pub fn pop_or_steal(&self, worker: usize) -> Option<(K, V)> {
+        self.pop(worker).or_else(|| self.steal(worker))
+    }
+
+    /// Remove the entry of the key from whichever worker holds it, and return its value.
+    pub fn cancel(&self, key: &K) -> Option<V> {
+        let mut index = self.index();
+        let (worker, _) = index.workers.remove(key)?;
+        self.local(worker).remove_key(key).map(|(_, value)| value)
+    }
+
+    /// Remove an entry taken from the queue of the worker from the index.
+    fn unindex(&self, worker: usize, entry: Option<(K, (u64, V))>) -> Option<(K, V)> {
+        let (key, (ticket, value)) = entry?;
+        let mut index = self.index();
+        if index.workers.get(&key) == Some(&(worker, ticket)) {
+            index.workers.remove(&key);
+        }
+        Some((key, value))
+    }
+
+    fn index(&self) -> MutexGuard<'_, Index<K>> {
+        self.index
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn local(&self, worker: usize) -> MutexGuard<'_, Local<K, V>> {
+        self.locals[worker]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn pushing_a_key_again_moves_it_between_workers() {
+    let queues = WorkQueues::new(3);
+    queues.push(0, "task", 1);
+    assert_eq!(Some(1), queues.push(2, "task", 2));
+
+    assert_eq!(None, queues.pop(0));
+    assert_eq!(Some(2), queues.worker_of(&"task"));
+    assert_eq!(Some(("task", 2)), queues.steal(1));
+    assert_eq!(None, queues.cancel(&"task"));
+    assert!(queues.is_empty());
+}
@@ -0,0 +1,586 @@
+//! Pluggable storage backends of the queues.
+//!
+//! An [`Engine`](struct.Engine.html) keeps its entries in a [`NodeStore`](trait.NodeStore.html) and
+//! finds them through a [`KeyIndex`](trait.KeyIndex.html). Implementing these traits allows using
+//! the queue logic of this crate with custom backends.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use core::hash::{BuildHasher, Hash};
+use core::marker::PhantomData;
+use deque::{DuplicateKeys, RenameError};
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// Maps the keys of a queue to the positions of their nodes.
+///
+/// Positions are opaque to the index, it only has to store and return them.
+pub trait KeyIndex<K> {
+    /// Returns the number of keys in the index.
+    fn len(&self) -> usize;
+
+    /// Returns true if the index contains no keys.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the position stored for the key.
+    fn get(&self, key: &K) -> Option<u64>;
+
+    /// Returns a mutable reference to the position stored for the key.
+    fn get_mut(&mut self, key: &K) -> Option<&mut u64>;
+
+    /// Store the position of a key, and return the previous one.
+    fn insert(&mut self, key: K, pos: u64) -> Option<u64>;
+
+    /// Remove a key, and return its position.
+    fn remove(&mut self, key: &K) -> Option<u64>;
+
+    /// Returns true if the index contains the key.
+    fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl<K, S> KeyIndex<K> for HashMap<K, u64, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn get(&self, key: &K) -> Option<u64> {
+        HashMap::get(self, key).cloned()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut u64> {
+        HashMap::get_mut(self, key)
+    }
+
+    fn insert(&mut self, key: K, pos: u64) -> Option<u64> {
+        HashMap::insert(self, key, pos)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<u64> {
+        HashMap::remove(self, key)
+    }
+}
+
+impl<K> KeyIndex<K> for BTreeMap<K, u64>
+where
+    K: Ord,
+{
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn get(&self, key: &K) -> Option<u64> {
+        BTreeMap::get(self, key).cloned()
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut u64> {
+        BTreeMap::get_mut(self, key)
+    }
+
+    fn insert(&mut self, key: K, pos: u64) -> Option<u64> {
+        BTreeMap::insert(self, key, pos)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<u64> {
+        BTreeMap::remove(self, key)
+    }
+}
+
+/// Stores the nodes of a queue in queue order.
+///
+/// A node is `None` once its entry was removed from the middle of the queue. Index `0` is the
+/// front of the queue.
+pub trait NodeStore<K, V> {
+    /// Returns the number of nodes, including removed ones.
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no nodes.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the node at the index.
+    fn get(&self, index: usize) -> Option<&Option<(K, V)>>;
+
+    /// Returns a mutable reference to the node at the index.
+    fn get_mut(&mut self, index: usize) -> Option<&mut Option<(K, V)>>;
+
+    /// Add a node at the front.
+    fn push_front(&mut self, node: Option<(K, V)>);
+
+    /// Add a node at the back.
+    fn push_back(&mut self, node: Option<(K, V)>);
+
+    /// Remove the node at the front.
+    fn pop_front(&mut self) -> Option<Option<(K, V)>>;
+
+    /// Remove the node at the back.
+    fn pop_back(&mut self) -> Option<Option<(K, V)>>;
+
+    /// Drop all removed nodes, keeping the order of the others.
+    fn retain_occupied(&mut self);
+}
+
+impl<K, V> NodeStore<K, V> for VecDeque<Option<(K, V)>> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<&Option<(K, V)>> {
+        VecDeque::get(self, index)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut Option<(K, V)>> {
+        VecDeque::get_mut(self, index)
+    }
+
+    fn push_front(&mut self, node: Option<(K, V)>) {
+        VecDeque::push_front(self, node)
+    }
+
+    fn push_back(&mut self, node: Option<(K, V)>) {
+        VecDeque::push_back(self, node)
+    }
+
+    fn pop_front(&mut self) -> Option<Option<(K, V)>> {
+        VecDeque::pop_front(self)
+    }
+
+    fn pop_back(&mut self) -> Option<Option<(K, V)>> {
+        VecDeque::pop_back(self)
+    }
+
+    fn retain_occupied(&mut self) {
+        self.retain(Option::is_some)
+    }
+}
+
+/// The addressable deque logic, generic over its storage backends.
+///
+/// ```
+/// use addressable_queue::storage::Engine;
+/// use std::collections::BTreeMap;
+///
+/// let mut engine: Engine<u8, char, BTreeMap<u8, u64>> = Engine::new();
+/// engine.push_back(2, 'a');
+/// engine.push_back(1, 'b');
+/// engine.push_front(3, 'c');
+///
+/// assert_eq!(Some('a'), engine.remove_key(&2));
+/// assert_eq!(engine.into_vec(), vec![(3, 'c'), (1, 'b')]);
+/// ```
+pub struct Engine<K, V, I, N = VecDeque<Option<(K, V)>>> {
+    /// Entries in queue order, removed entries stay behind as `None` until they reach an end or
+    /// the engine is compacted.
+    pub(crate) nodes: N,
+    /// Maps every key to the position of its node.
+    pub(crate) index: I,
+    /// Position of the first node.
+    offset: u64,
+    pub(crate) duplicates: DuplicateKeys,
+    /// Removed nodes are reclaimed once there are more than this many per live entry.
+    pub(crate) compaction_threshold: f64,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V, I, N> Engine<K, V, I, N>
+where
+    K: Clone,
+    I: KeyIndex<K>,
+    N: NodeStore<K, V>,
+{
+    /// Create a new, empty engine.
+    pub fn new() -> Self
+    where
+        I: Default,
+        N: Default,
+    {
+        Engine::with_backends(I::default(), N::default())
+    }
+
+    /// Create a new engine using the given, empty backends.
+    pub fn with_backends(index: I, nodes: N) -> Self {
+        debug_assert!(index.is_empty() && nodes.is_empty());
+        Engine {
+            nodes,
+            index,
+            offset: 0,
+            duplicates: DuplicateKeys::default(),
+            compaction_threshold: 1.0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set what happens when an entry is inserted for a key which is already contained.
+    pub fn set_duplicate_keys(&mut self, policy: DuplicateKeys) {
+        self.duplicates = policy;
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns true if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns true if there is an entry for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns a reference to the value of the entry with the specified key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let index = self.slot(self.index.get(key)?);
+        self.nodes.get(index)?.as_ref().map(|node| &node.1)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the specified key.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let index = self.slot(self.index.get(key)?);
+        self.nodes.get_mut(index)?.as_mut().map(|node| &mut node.1)
+    }
+
+    /// Returns the entry at the front.
+    pub fn peek_front(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    /// Returns the entry at the back.
+    pub fn peek_back(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+
+    /// Returns the entry at the back, with a mutable reference to its value.
+    pub fn peek_back_mut(&mut self) -> Option<(&K, &mut V)> {
+        let nodes = &self.nodes;
+        let index = (0..nodes.len())
+            .rev()
+            .find(|&i| nodes.get(i).and_then(Option::as_ref).is_some())?;
+        self.nodes
+            .get_mut(index)?
+            .as_mut()
+            .map(|node| (&node.0, &mut node.1))
+    }
+
+    /// Returns an iterator over the entries from front to back.
+    pub fn iter(&self) -> Iter<'_, K, V, N> {
+        Iter {
+            nodes: &self.nodes,
+            front: 0,
+            back: self.nodes.len(),
+            len: self.len(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Insert an entry at the back.
+    ///
+    /// If there already is an entry for the key, it is handled according to the configured
+    /// [`DuplicateKeys`](../deque/enum.DuplicateKeys.html) policy.
+    pub fn push_back(&mut self, key: K, value: V) {
+        self.push(key, value, false);
+    }
+
+    /// Insert an entry at the front.
+    ///
+    /// If there already is an entry for the key, it is handled according to the configured
+    /// [`DuplicateKeys`](../deque/enum.DuplicateKeys.html) policy.
+    pub fn push_front(&mut self, key: K, value: V) {
+        self.push(key, value, true);
+    }
+
+    /// Remove the entry at the front, and return it if there was one.
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        while let Some(node) = self.nodes.pop_front() {
+            self.offset = self.offset.wrapping_add(1);
+            if let Some((key, value)) = node {
+                self.index.remove(&key);
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    /// Remove the entry at the back, and return it if there was one.
+    pub fn pop_back(&mut self) -> Option<(K, V)> {
+        while let Some(node) = self.nodes.pop_back() {
+            if let Some((key, value)) = node {
+                self.index.remove(&key);
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    /// Move the entry at the front to the back.
+    ///
+    /// Returns false if there are no entries.
+    pub fn requeue_front(&mut self) -> bool {
+        while let Some(node) = self.nodes.pop_front() {
+            self.offset = self.offset.wrapping_add(1);
+            if let Some(node) = node {
+                let pos = self.offset.wrapping_add(self.nodes.len() as u64);
+                if let Some(p) = self.index.get_mut(&node.0) {
+                    *p = pos;
+                }
+                self.nodes.push_back(Some(node));
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Move the entry with the specified key to the back.
+    ///
+    /// Returns false if there is no entry for the key.
+    pub fn move_to_back(&mut self, key: &K) -> bool {
+        let back = self.offset.wrapping_add(self.nodes.len() as u64);
+        self.relocate(key, back, false)
+    }
+
+    /// Move the entry with the specified key to the front.
+    ///
+    /// Returns false if there is no entry for the key.
+    pub fn move_to_front(&mut self, key: &K) -> bool {
+        let front = self.offset.wrapping_sub(1);
+        self.relocate(key, front, true)
+    }
+
+    /// Replace the value of an entry without changing its position.
+    ///
+    /// Returns the old value, or `None` if there is no entry for the key, in which case nothing is
+    /// inserted.
+    pub fn replace(&mut self, key: &K, value: V) -> Option<V> {
+        self.get_mut(key)
+            .map(|slot| ::core::mem::replace(slot, value))
+    }
+
+    /// Change the key of an entry without changing its position.
+    pub fn rename_key(&mut self, old: &K, new: K) -> Result<(), RenameError>
+    where
+        K: PartialEq,
+    {
+        if !self.index.contains_key(old) {
+            return Err(RenameError::NotFound);
+        }
+        if *old == new {
+            return Ok(());
+        }
+        if self.index.contains_key(&new) {
+            return Err(RenameError::AlreadyExists);
+        }
+        let pos = self.index.remove(old).unwrap();
+        let index = self.slot(pos);
+        if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(index) {
+            node.0 = new.clone();
+        }
+        self.index.insert(new, pos);
+        Ok(())
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let pos = self.index.remove(key)?;
+        let value = self.take(pos).map(|(_, value)| value);
+        self.maybe_compact();
+        value
+    }
+
+    /// Convert the engine into a vec, where the first element is the front.
+    pub fn into_vec(mut self) -> Vec<(K, V)> {
+        let mut entries = Vec::with_capacity(self.len());
+        while let Some(node) = self.nodes.pop_front() {
+            entries.extend(node);
+        }
+        entries
+    }
+
+    /// Take the node at the position without updating the index or compacting.
+    pub(crate) fn take(&mut self, pos: u64) -> Option<(K, V)> {
+        let index = self.slot(pos);
+        self.nodes.get_mut(index).and_then(Option::take)
+    }
+
+    /// Compact the engine if the number of removed nodes exceeds the threshold.
+    pub(crate) fn maybe_compact(&mut self) {
+        let removed = self.nodes.len() - self.index.len();
+        if removed as f64 > self.compaction_threshold * self.index.len() as f64 {
+            self.compact();
+        }
+    }
+
+    /// Drop the removed nodes and update the positions of the remaining ones.
+    pub(crate) fn compact(&mut self) {
+        self.nodes.retain_occupied();
+        for i in 0..self.nodes.len() {
+            if let Some(&Some((ref key, _))) = self.nodes.get(i) {
+                if let Some(pos) = self.index.get_mut(key) {
+                    *pos = self.offset.wrapping_add(i as u64);
+                }
+            }
+        }
+    }
+
+    /// Returns the index in the node store of a position.
+    pub(crate) fn slot(&self, pos: u64) -> usize {
+        pos.wrapping_sub(self.offset) as usize
+    }
+
+    /// Replace the entry of a contained key by one at the back with the given value, and return
+    /// the old value.
+    ///
+    /// The key keeps its place in the index, and with `compact` the node store is compacted
+    /// after the old node was removed, so the new node fits without growing either backend.
+    pub(crate) fn requeue_back(&mut self, key: &K, value: V, compact: bool) -> Option<V> {
+        let old = self.index.get(key)?;
+        let (key, old_value) = self.take(old)?;
+        if compact {
+            self.compact();
+        }
+        let pos = self.offset.wrapping_add(self.nodes.len() as u64);
+        if let Some(p) = self.index.get_mut(&key) {
+            *p = pos;
+        }
+        self.nodes.push_back(Some((key, value)));
+        if !compact {
+            self.maybe_compact();
+        }
+        Some(old_value)
+    }
+
+    /// Move the node of a key to the new position at one of the ends.
+    fn relocate(&mut self, key: &K, new: u64, front: bool) -> bool {
+        let old = match self.index.get_mut(key) {
+            Some(pos) => ::core::mem::replace(pos, new),
+            None => return false,
+        };
+        let node = self.take(old);
+        if front {
+            self.offset = new;
+            self.nodes.push_front(node);
+        } else {
+            self.nodes.push_back(node);
+        }
+        self.maybe_compact();
+        true
+    }
+
+    fn push(&mut self, key: K, value: V, front: bool) {
+        if self.duplicates != DuplicateKeys::Requeue {
+            if let Some(pos) = self.index.get(&key) {
+                if self.duplicates == DuplicateKeys::KeepPosition {
+                    let index = self.slot(pos);
+                    if let Some(&mut Some(ref mut node)) = self.nodes.get_mut(index) {
+                        node.1 = value;
+                    }
+                }
+                return;
+            }
+        }
+        let pos = if front {
+            self.offset.wrapping_sub(1)
+        } else {
+            self.offset.wrapping_add(self.nodes.len() as u64)
+        };
+        let old = self.index.insert(key.clone(), pos);
+        if front {
+            self.offset = pos;
+            self.nodes.push_front(Some((key, value)));
+        } else {
+            self.nodes.push_back(Some((key, value)));
+        }
+        if let Some(old) = old {
+            self.take(old);
+            self.maybe_compact();
+        }
+    }
+}
+
+impl<K, V, I, N> Default for Engine<K, V, I, N>
+where
+    K: Clone,
+    I: KeyIndex<K> + Default,
+    N: NodeStore<K, V> + Default,
+{
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+/// Iterator over the entries of an engine.
+///
+/// Created by [`Engine::iter`](struct.Engine.html#method.iter).
+pub struct Iter<'a, K: 'a, V: 'a, N: 'a> {
+    nodes: &'a N,
+    front: usize,
+    back: usize,
+    len: usize,
+    _marker: PhantomData<&'a (K, V)>,
+}
+
+impl<'a, K, V, N> Iterator for Iter<'a, K, V, N>
+where
+    N: NodeStore<K, V>,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        while self.front < self.back {
+            let node = self.nodes.get(self.front);
+            self.front += 1;
+            if let Some(Some((key, value))) = node {
+                self.len = self.len.saturating_sub(1);
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, K, V, N> DoubleEndedIterator for Iter<'a, K, V, N>
+where
+    N: NodeStore<K, V>,
+{
+    fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+        while self.front < self.back {
+            self.back -= 1;
+            if let Some(Some((key, value))) = self.nodes.get(self.back) {
+                self.len = self.len.saturating_sub(1);
+                return Some((key, value));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, K, V, N> ExactSizeIterator for Iter<'a, K, V, N> where N: NodeStore<K, V> {}
@@ -0,0 +1,570 @@
+//! Implementation of an addressable FIFO queue which can be shared between threads, and of a
+//! channel built on it.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fifo::BoundedQueue;
+use primitives::{Arc, AtomicUsize, Condvar, Mutex, MutexGuard, Ordering};
+use std::error;
+use std::fmt;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+pub use fifo::Full;
+
+/// A handle to an addressable FIFO queue shared between threads.
+///
+/// Cloning the handle does not clone the queue, all clones refer to the same entries. Consumers
+/// can wait for entries with [`pop_head`](#method.pop_head), which is woken by inserting. A queue
+/// can have a capacity, in which case producers have to decide what happens when it is full:
+/// [`insert`](#method.insert) hands the entry back, [`push_evicting`](#method.push_evicting)
+/// drops the oldest entry and [`push_blocking`](#method.push_blocking) waits for room.
+///
+/// Closing the queue wakes all waiting threads. Consumers receive the remaining entries and then
+/// [`Closed`](struct.Closed.html), so worker threads can terminate once the queue is drained.
+///
+/// ```
+/// use addressable_queue::sync::{Full, Queue};
+/// use std::thread;
+///
+/// let queue = Queue::bounded(1);
+/// queue.insert("a", 1).unwrap();
+/// assert_eq!(Err(Full("b", 2)), queue.insert("b", 2));
+///
+/// let consumer = queue.clone();
+/// let handle = thread::spawn(move || consumer.remove_head());
+/// queue.push_blocking("b", 2).unwrap();
+///
+/// assert_eq!(Some(("a", 1)), handle.join().unwrap());
+/// assert_eq!(Some(("b", 2)), queue.remove_head());
+/// ```
+///
+/// Waiting for entries:
+///
+/// ```
+/// use addressable_queue::sync::Queue;
+/// use std::thread;
+///
+/// let queue = Queue::new();
+/// let consumer = queue.clone();
+/// let handle = thread::spawn(move || consumer.pop_head());
+///
+/// queue.insert("job", 1).unwrap();
+/// assert_eq!(Ok(("job", 1)), handle.join().unwrap());
+/// ```
+///
+/// Shutting down workers:
+///
+/// ```
+/// use addressable_queue::sync::Queue;
+/// use std::thread;
+///
+/// let queue = Queue::new();
+/// let worker = queue.clone();
+/// let handle = thread::spawn(move || {
+///     let mut done = Vec::new();
+///     while let Ok((key, ())) = worker.pop_head() {
+///         done.push(key);
+///     }
+///     done
+/// });
+///
+/// for key in 0..3 {
+///     queue.insert(key, ()).unwrap();
+/// }
+/// queue.close();
+/// assert_eq!(vec![0, 1, 2], handle.join().unwrap());
+/// ```
+pub struct Queue<K, V> {
+    shared: Arc<Shared<K, V>>,
+}
+
+struct Shared<K, V> {
+    state: Mutex<State<K, V>>,
+    /// Notified when an entry is inserted.
+    not_empty: Condvar,
+    /// Notified when an entry is removed.
+    not_full: Condvar,
+}
+
+struct State<K, V> {
+    queue: BoundedQueue<K, V>,
+    closed: bool,
+}
+
+/// The error returned when removing from a queue which is closed and empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the queue is closed")
+    }
+}
+
+impl error::Error for Closed {}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue without a capacity.
+    pub fn new() -> Self {
+        Queue::bounded(usize::MAX)
+    }
+
+    /// Create a new instance of a queue which holds at most `capacity` elements.
+    pub fn bounded(capacity: usize) -> Self {
+        Queue {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    queue: BoundedQueue::new(capacity),
+                    closed: false,
+                }),
+                not_empty: Condvar::new(),
+                not_full: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Returns the maximum number of elements in the queue.
+    pub fn capacity(&self) -> usize {
+        self.lock().queue.bound()
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.lock().queue.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.lock().queue.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.lock().queue.contains_key(key)
+    }
+
+    /// Insert an entry at the end of the queue, or hand the pair back in the error if the queue
+    /// is full.
+    ///
+    /// Replacing the value of a key in the queue always succeeds.
+    pub fn insert(&self, key: K, value: V) -> Result<(), Full<K, V>> {
+        self.lock().queue.try_insert(key, value)?;
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Insert an entry at the end of the queue, evicting and returning the head if the queue is
+    /// full.
+    pub fn push_evicting(&self, key: K, value: V) -> Option<(K, V)> {
+        let evicted = self.lock().queue.push_evicting(key, value);
+        self.shared.not_empty.notify_one();
+        evicted
+    }
+
+    /// Insert an entry at the end of the queue, waiting until there is room if the queue is
+    /// full.
+    ///
+    /// If the queue is closed while it is full, waiting ends and the pair is handed back in the
+    /// error. With a capacity of zero this only returns once the queue is closed.
+    pub fn push_blocking(&self, key: K, value: V) -> Result<(), Full<K, V>> {
+        let mut state = self.lock();
+        while state.queue.is_full() && !state.queue.contains_key(&key) {
+            if state.closed {
+                return Err(Full(key, value));
+            }
+            state = self
+                .shared
+                .not_full
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        state.queue.try_insert(key, value)?;
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Returns a clone of the current head of the queue.
+    pub fn peek_head(&self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        self.lock()
+            .queue
+            .iter()
+            .next()
+            .map(|(key, value)| (key.clone(), value.clone()))
+    }
+
+    /// Remove the current head of the queue, waiting until there is one if the queue is empty.
+    ///
+    /// Once the queue is closed, the remaining entries are still returned, and then the error.
+    pub fn pop_head(&self) -> Result<(K, V), Closed> {
+        let mut state = self.lock();
+        loop {
+            if let Some(entry) = state.queue.remove_head() {
+                drop(state);
+                self.shared.not_full.notify_one();
+                return Ok(entry);
+            }
+            if state.closed {
+                return Err(Closed);
+            }
+            state = self
+                .shared
+                .not_empty
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Remove the current head of the queue, waiting at most `timeout` until there is one if the
+    /// queue is empty, and return it if there was one in time.
+    ///
+    /// Once the queue is closed, the remaining entries are still returned, and then the error.
+    ///
+    /// ```
+    /// use addressable_queue::sync::{Closed, Queue};
+    /// use std::time::Duration;
+    ///
+    /// let queue: Queue<&str, u32> = Queue::new();
+    /// assert_eq!(Ok(None), queue.pop_timeout(Duration::from_millis(10)));
+    ///
+    /// queue.close();
+    /// assert_eq!(Err(Closed), queue.pop_timeout(Duration::from_millis(10)));
+    /// ```
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<Option<(K, V)>, Closed> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        loop {
+            if let Some(entry) = state.queue.remove_head() {
+                drop(state);
+                self.shared.not_full.notify_one();
+                return Ok(Some(entry));
+            }
+            if state.closed {
+                return Err(Closed);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            state = self
+                .shared
+                .not_empty
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+
+    /// Remove the current head of the queue without waiting, and return the value if there was
+    /// one.
+    pub fn remove_head(&self) -> Option<(K, V)> {
+        let entry = self.lock().queue.remove_head();
+        if entry.is_some() {
+            self.shared.not_full.notify_one();
+        }
+        entry
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&self, key: &K) -> Option<V> {
+        let value = self.lock().queue.remove_key(key);
+        if value.is_some() {
+            self.shared.not_full.notify_one();
+        }
+        value
+    }
+
+    /// Insert an entry at the end of the queue, waiting until there is room if the queue is
+    /// full, unless the queue is closed, in which case the pair is handed back.
+    fn push_open(&self, key: K, value: V) -> Result<(), Full<K, V>> {
+        let mut state = self.lock();
+        while !state.closed && state.queue.is_full() && !state.queue.contains_key(&key) {
+            state = self
+                .shared
+                .not_full
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        if state.closed {
+            return Err(Full(key, value));
+        }
+        state.queue.try_insert(key, value)?;
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Close the queue, which wakes all threads waiting to insert or remove entries.
+    ///
+    /// Entries can still be inserted and removed without waiting.
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.shared.not_empty.notify_all();
+        self.shared.not_full.notify_all();
+    }
+
+    /// Returns true if the queue was closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<K, V>> {
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<K, V> Clone for Queue<K, V> {
+    fn clone(&self) -> Self {
+        Queue {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+/// Create a channel of entries which are addressable by their key, holding at most `capacity`
+/// entries.
+///
+/// The [`Sender`](struct.Sender.html) can be cloned to insert entries from several threads, and
+/// cancel them by their key as long as they were not received. Once all senders are dropped, the
+/// [`Receiver`](struct.Receiver.html) receives the remaining entries and then
+/// [`Closed`](struct.Closed.html). Once the receiver is dropped, inserting fails.
+///
+/// ```
+/// use addressable_queue::sync;
+/// use std::thread;
+///
+/// let (sender, receiver) = sync::channel(16);
+/// let producer = thread::spawn(move || {
+///     sender.insert("draft", 1).unwrap();
+///     sender.insert("final", 2).unwrap();
+///     sender.remove_key(&"draft");
+/// });
+/// producer.join().unwrap();
+///
+/// assert_eq!(Some(("final", 2)), receiver.peek());
+/// assert_eq!(Ok(("final", 2)), receiver.pop());
+/// assert_eq!(Err(sync::Closed), receiver.pop());
+/// ```
+pub fn channel<K, V>(capacity: usize) -> (Sender<K, V>, Receiver<K, V>)
+where
+    K: Clone + Eq + Hash,
+{
+    let queue = Queue::bounded(capacity);
+    let sender = Sender {
+        queue: queue.clone(),
+        senders: Arc::new(AtomicUsize::new(1)),
+    };
+    (sender, Receiver { queue })
+}
+
+/// The sending half of a [`channel`](fn.channel.html).
+pub struct Sender<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    queue: Queue<K, V>,
+    senders: Arc<AtomicUsize>,
+}
+
+impl<K, V> Sender<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Insert an entry, waiting until there is room if the channel is full.
+    ///
+    /// If the receiver was dropped, the pair is handed back in the error.
+    pub fn insert(&self, key: K, value: V) -> Result<(), Disconnected<K, V>> {
+        self.queue
+            .push_open(key, value)
+            .map_err(|Full(key, value)| Disconnected(key, value))
+    }
+
+    /// Cancel an entry which was not yet received, and return its value.
+    pub fn remove_key(&self, key: &K) -> Option<V> {
+        self.queue.remove_key(key)
+    }
+
+    /// Returns the number of entries which were not yet received.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if all entries were received.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl<K, V> Clone for Sender<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn clone(&self) -> Self {
+        self.senders.fetch_add(1, Ordering::Relaxed);
+        Sender {
+            queue: self.queue.clone(),
+            senders: self.senders.clone(),
+        }
+    }
+}
+
+impl<K, V> Drop for Sender<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn drop(&mut self) {
+        if self.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.queue.close();
+        }
+    }
+}
+
+/// The receiving half of a [`channel`](fn.channel.html).
+pub struct Receiver<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    queue: Queue<K, V>,
+}
+
+impl<K, V> Receiver<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Receive the next entry, waiting until there is one.
+    ///
+    /// Once all senders were dropped, the remaining entries are still returned, and then the
+    /// error.
+    pub fn pop(&self) -> Result<(K, V), Closed> {
+        self.queue.pop_head()
+    }
+
+    /// Receive the next entry, waiting at most `timeout` until there is one.
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<Option<(K, V)>, Closed> {
+        self.queue.pop_timeout(timeout)
+    }
+
+    /// Receive the next entry if there is one, without waiting.
+    pub fn try_pop(&self) -> Option<(K, V)> {
+        self.queue.remove_head()
+    }
+
+    /// Returns a clone of the next entry, without receiving it.
+    pub fn peek(&self) -> Option<(K, V)>
+    where
+        V: Clone,
+    {
+        self.queue.peek_head()
+    }
+
+    /// Returns the number of entries waiting to be received.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if no entries are waiting to be received.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl<K, V> Drop for Receiver<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+/// The error returned when inserting into a [`channel`](fn.channel.html) whose receiver was
+/// dropped, which hands back the pair.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Disconnected<K, V>(pub K, pub V);
+
+impl<K, V> fmt::Debug for Disconnected<K, V>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Disconnected")
+            .field(&self.0)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<K, V> fmt::Display for Disconnected<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the receiver was dropped")
+    }
+}
+
+impl<K, V> error::Error for Disconnected<K, V> where K: fmt::Debug {}
+
+#[cfg(test)]
+#[test]
+fn every_waiting_consumer_receives_an_entry() {
+    use std::thread;
+    use std::vec::Vec;
+
+    let queue = Queue::new();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let queue = queue.clone();
+            thread::spawn(move || queue.pop_head().unwrap().0)
+        })
+        .collect();
+    for key in 0..4 {
+        queue.insert(key, ()).unwrap();
+    }
+    let mut keys: Vec<u32> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    keys.sort();
+    assert_eq!(keys, vec![0, 1, 2, 3]);
+    assert!(queue.is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn dropping_the_receiver_disconnects_senders() {
+    let (sender, receiver) = channel(1);
+    sender.insert(1, ()).unwrap();
+    let blocked = sender.clone();
+    let handle = ::std::thread::spawn(move || blocked.insert(2, ()));
+    drop(receiver);
+
+    assert_eq!(Err(Disconnected(2, ())), handle.join().unwrap());
+    assert_eq!(Err(Disconnected(3, ())), sender.insert(3, ()));
+}
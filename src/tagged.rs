@@ -0,0 +1,216 @@
+//! Implementation of an addressable FIFO queue where entries can be tagged, and all entries with
+//! a tag can be removed at once.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use deque::Deque;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+use set;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+/// An addressable FIFO queue whose entries can carry tags.
+///
+/// For every tag the keys of the entries carrying it are tracked in queue order, so removing all
+/// entries with a tag takes time proportional to the number of matches.
+pub struct Queue<K, T, V> {
+    inner: Deque<K, (Vec<T>, V)>,
+    tags: HashMap<T, set::Queue<K>>,
+}
+
+impl<K, T, V> Queue<K, T, V>
+where
+    K: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+{
+    /// Create a new instance of a queue.
+    pub fn new() -> Self {
+        Queue {
+            inner: Deque::new(),
+            tags: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the queue contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns true if the queue contains an element for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns the tags of the entry with the specified key.
+    pub fn tags_of(&self, key: &K) -> Option<&[T]> {
+        self.inner.get(key).map(|(tags, _)| &tags[..])
+    }
+
+    /// Returns the number of entries carrying the tag.
+    pub fn count_tag(&self, tag: &T) -> usize {
+        self.tags.get(tag).map_or(0, set::Queue::len)
+    }
+
+    /// Insert an entry without tags at the end of the queue.
+    ///
+    /// An existing entry for the key is removed, including its tags.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.insert_with_tags(key, None, value);
+    }
+
+    /// Insert an entry with tags at the end of the queue.
+    ///
+    /// An existing entry for the key is removed, including its tags.
+    ///
+    /// ```
+    /// use addressable_queue::tagged::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_with_tags(1u32, vec!["tenant-a", "urgent"], 'x');
+    /// queue.insert_with_tags(2u32, vec!["tenant-b"], 'y');
+    /// queue.insert_with_tags(3u32, vec!["tenant-a"], 'z');
+    ///
+    /// assert_eq!(2, queue.count_tag(&"tenant-a"));
+    /// assert_eq!(Some(&["tenant-b"][..]), queue.tags_of(&2));
+    /// ```
+    pub fn insert_with_tags<I>(&mut self, key: K, tags: I, value: V)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.remove_key(&key);
+        let mut entry_tags = Vec::new();
+        for tag in tags {
+            if entry_tags.contains(&tag) {
+                continue;
+            }
+            self.tags
+                .entry(tag.clone())
+                .or_default()
+                .insert(key.clone());
+            entry_tags.push(tag);
+        }
+        self.inner.push_back(key, (entry_tags, value));
+    }
+
+    /// Remove the current head of the queue, and return it if there was one.
+    pub fn remove_head(&mut self) -> Option<(K, V)> {
+        let (key, (tags, value)) = self.inner.pop_front()?;
+        self.untag(&key, &tags);
+        Some((key, value))
+    }
+
+    /// Remove the current tail of the queue, and return it if there was one.
+    pub fn remove_tail(&mut self) -> Option<(K, V)> {
+        let (key, (tags, value)) = self.inner.pop_back()?;
+        self.untag(&key, &tags);
+        Some((key, value))
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        let (tags, value) = self.inner.remove_key(key)?;
+        self.untag(key, &tags);
+        Some(value)
+    }
+
+    /// Remove all entries carrying the tag, returning how many were removed.
+    ///
+    /// ```
+    /// use addressable_queue::tagged::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_with_tags(1u32, vec!["tenant-a"], 'x');
+    /// queue.insert_with_tags(2u32, vec!["tenant-b"], 'y');
+    /// queue.insert_with_tags(3u32, vec!["tenant-a"], 'z');
+    ///
+    /// assert_eq!(2, queue.remove_by_tag(&"tenant-a"));
+    /// assert_eq!(0, queue.remove_by_tag(&"tenant-a"));
+    /// assert_eq!(queue.into_vec(), vec![(2, 'y')]);
+    /// ```
+    pub fn remove_by_tag(&mut self, tag: &T) -> usize {
+        self.drain_by_tag(tag).len()
+    }
+
+    /// Remove all entries carrying the tag, and return them in queue order.
+    ///
+    /// ```
+    /// use addressable_queue::tagged::Queue;
+    ///
+    /// let mut queue = Queue::new();
+    /// queue.insert_with_tags(1u32, vec!["tenant-a"], 'x');
+    /// queue.insert_with_tags(2u32, vec!["tenant-b", "tenant-a"], 'y');
+    /// queue.insert_with_tags(3u32, vec!["tenant-a"], 'z');
+    ///
+    /// assert_eq!(queue.drain_by_tag(&"tenant-a"), vec![(1, 'x'), (2, 'y'), (3, 'z')]);
+    /// assert_eq!(0, queue.count_tag(&"tenant-b"));
+    /// assert!(queue.is_empty());
+    /// ```
+    pub fn drain_by_tag(&mut self, tag: &T) -> Vec<(K, V)> {
+        let mut keys = match self.tags.remove(tag) {
+            Some(keys) => keys,
+            None => return Vec::new(),
+        };
+        let mut drained = Vec::with_capacity(keys.len());
+        while let Some(key) = keys.remove_head() {
+            if let Some((tags, value)) = self.inner.remove_key(&key) {
+                self.untag(&key, &tags);
+                drained.push((key, value));
+            }
+        }
+        drained
+    }
+
+    /// Convert the queue into a vec, where the first element is the head (oldest element).
+    pub fn into_vec(self) -> Vec<(K, V)> {
+        self.inner
+            .into_vec()
+            .into_iter()
+            .map(|(key, (_, value))| (key, value))
+            .collect()
+    }
+
+    fn untag(&mut self, key: &K, tags: &[T]) {
+        for tag in tags {
+            let now_empty = match self.tags.get_mut(tag) {
+                Some(keys) => {
+                    keys.remove(key);
+                    keys.is_empty()
+                }
+                None => false,
+            };
+            if now_empty {
+                self.tags.remove(tag);
+            }
+        }
+    }
+}
+
+impl<K, T, V> Default for Queue<K, T, V>
+where
+    K: Clone + Eq + Hash,
+    T: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Queue::new()
+    }
+}
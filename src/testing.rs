@@ -0,0 +1,311 @@
+//! Property testing of queues against a simple reference model.
+//!
+//! A [`Model`](struct.Model.html) keeps the keys of a FIFO queue in a `Vec` and their values in a
+//! `HashMap`, without tombstones or compaction. Sequences of [`Op`](enum.Op.html)s generated by
+//! the strategies of this module can be applied to both a [`fifo::Queue`](../fifo/struct.Queue.html)
+//! and the model with [`check`](fn.check.html), which panics as soon as they disagree.
+//!
+//! ```
+//! extern crate addressable_queue;
+//! extern crate proptest;
+//!
+//! use addressable_queue::testing;
+//! use proptest::test_runner::TestRunner;
+//!
+//! # fn main() {
+//! // Few distinct keys, so that operations often hit an existing entry.
+//! let mut runner = TestRunner::default();
+//! runner
+//!     .run(&testing::ops(0..8u8, 0..100u32, 0..200), |ops| {
+//!         testing::check(ops);
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! # }
+//! ```
+//!
+//! This module requires the `testing` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use fifo;
+use proptest::collection::{self, SizeRange};
+use proptest::strategy::{Just, Strategy};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::mem;
+use std::vec::Vec;
+
+/// An operation on a FIFO queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Op<K, V> {
+    /// Insert an entry at the end of the queue.
+    Insert(K, V),
+    /// Insert an entry at the head of the queue.
+    InsertHead(K, V),
+    /// Remove the head of the queue.
+    RemoveHead,
+    /// Remove the tail of the queue.
+    RemoveTail,
+    /// Remove the entry of the key.
+    RemoveKey(K),
+    /// Replace the value of the key, if there is an entry for it.
+    Replace(K, V),
+    /// Reclaim the slots of removed entries, which does not change the contents of the queue.
+    Compact,
+}
+
+impl<K, V> Op<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Apply the operation to the queue.
+    ///
+    /// Returns the entry which was removed, or the old value of a replaced entry together with
+    /// its key.
+    pub fn apply<S>(self, queue: &mut fifo::Queue<K, V, S>) -> Option<(K, V)>
+    where
+        S: BuildHasher,
+    {
+        match self {
+            Op::Insert(key, value) => {
+                queue.insert(key, value);
+                None
+            }
+            Op::InsertHead(key, value) => {
+                queue.insert_head(key, value);
+                None
+            }
+            Op::RemoveHead => queue.remove_head(),
+            Op::RemoveTail => queue.remove_tail(),
+            Op::RemoveKey(key) => queue.remove_key(&key).map(|value| (key, value)),
+            Op::Replace(key, value) => queue.replace(&key, value).map(|old| (key, old)),
+            Op::Compact => {
+                queue.compact();
+                None
+            }
+        }
+    }
+}
+
+/// The reference model of a FIFO queue, where inserting a key again moves it to the new
+/// position.
+///
+/// ```
+/// use addressable_queue::testing::{Model, Op};
+///
+/// let mut model = Model::new();
+/// model.apply(Op::Insert(2u8, 4u8));
+/// model.apply(Op::Insert(3, 6));
+/// model.apply(Op::Insert(2, 5));
+///
+/// assert_eq!(Some((3, 6)), model.apply(Op::RemoveHead));
+/// assert_eq!(vec![(2, 5)], model.to_vec());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Model<K, V> {
+    keys: Vec<K>,
+    values: HashMap<K, V>,
+}
+
+impl<K, V> Model<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create an empty model.
+    pub fn new() -> Self {
+        Model {
+            keys: Vec::new(),
+            values: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns true if there is an entry for the key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// Returns the entries from head to tail.
+    pub fn to_vec(&self) -> Vec<(K, V)>
+    where
+        V: Clone,
+    {
+        self.keys
+            .iter()
+            .map(|key| (key.clone(), self.values[key].clone()))
+            .collect()
+    }
+
+    /// Apply the operation, returning what [`Op::apply`](enum.Op.html#method.apply) returns for a
+    /// queue.
+    pub fn apply(&mut self, op: Op<K, V>) -> Option<(K, V)> {
+        match op {
+            Op::Insert(key, value) => {
+                self.unlink(&key);
+                self.keys.push(key.clone());
+                self.values.insert(key, value);
+                None
+            }
+            Op::InsertHead(key, value) => {
+                self.unlink(&key);
+                self.keys.insert(0, key.clone());
+                self.values.insert(key, value);
+                None
+            }
+            Op::RemoveHead => {
+                if self.keys.is_empty() {
+                    return None;
+                }
+                let key = self.keys.remove(0);
+                let value = self.values.remove(&key)?;
+                Some((key, value))
+            }
+            Op::RemoveTail => {
+                let key = self.keys.pop()?;
+                let value = self.values.remove(&key)?;
+                Some((key, value))
+            }
+            Op::RemoveKey(key) => {
+                let value = self.values.remove(&key)?;
+                self.unlink(&key);
+                Some((key, value))
+            }
+            Op::Replace(key, value) => {
+                let old = self.values.get_mut(&key)?;
+                Some((key, mem::replace(old, value)))
+            }
+            Op::Compact => None,
+        }
+    }
+
+    fn unlink(&mut self, key: &K) {
+        self.keys.retain(|queued| queued != key);
+    }
+}
+
+impl<K, V> Default for Model<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Model::new()
+    }
+}
+
+/// Apply the operations to a new queue and a new model, and panic as soon as their results or
+/// contents differ.
+pub fn check<K, V>(ops: Vec<Op<K, V>>)
+where
+    K: fmt::Debug + Clone + Eq + Hash,
+    V: fmt::Debug + Clone + PartialEq,
+{
+    let mut queue = fifo::Queue::new();
+    let mut model = Model::new();
+    for (step, op) in ops.into_iter().enumerate() {
+        let described = format!("{:?}", op);
+        assert_eq!(
+            model.apply(op.clone()),
+            op.apply(&mut queue),
+            "result of step {}: {}",
+            step,
+            described
+        );
+        let entries: Vec<(K, V)> = queue
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        assert_eq!(
+            model.to_vec(),
+            entries,
+            "entries after step {}: {}",
+            step,
+            described
+        );
+        assert_eq!(model.len(), queue.len(), "length after step {}", step);
+        assert!(
+            model.keys.iter().all(|key| queue.contains_key(key)),
+            "lookup after step {}: {}",
+            step,
+            described
+        );
+    }
+}
+
+/// A strategy for single operations with keys and values from the given strategies.
+///
+/// Insertions are generated most often, so that the queue grows over a sequence of operations.
+pub fn op<K, V>(key: K, value: V) -> impl Strategy<Value = Op<K::Value, V::Value>>
+where
+    K: Strategy + Clone,
+    K::Value: Clone,
+    V: Strategy + Clone,
+    V::Value: Clone,
+{
+    proptest::prop_oneof![
+        4 => (key.clone(), value.clone()).prop_map(|(key, value)| Op::Insert(key, value)),
+        1 => (key.clone(), value.clone()).prop_map(|(key, value)| Op::InsertHead(key, value)),
+        2 => Just(Op::RemoveHead),
+        1 => Just(Op::RemoveTail),
+        2 => key.clone().prop_map(Op::RemoveKey),
+        1 => (key, value).prop_map(|(key, value)| Op::Replace(key, value)),
+        1 => Just(Op::Compact),
+    ]
+}
+
+/// A strategy for sequences of operations, with a length in `len`.
+pub fn ops<K, V, L>(key: K, value: V, len: L) -> impl Strategy<Value = Vec<Op<K::Value, V::Value>>>
+where
+    K: Strategy + Clone,
+    K::Value: Clone,
+    V: Strategy + Clone,
+    V::Value: Clone,
+    L: Into<SizeRange>,
+{
+    collection::vec(op(key, value), len)
+}
+
+/// A strategy for queues built by applying a sequence of operations of length in `len`, so
+/// their storage also holds the slots of removed entries.
+pub fn queue<K, V, L>(
+    key: K,
+    value: V,
+    len: L,
+) -> impl Strategy<Value = fifo::Queue<K::Value, V::Value>>
+where
+    K: Strategy + Clone,
+    K::Value: Clone + Eq + Hash,
+    V: Strategy + Clone,
+    V::Value: Clone,
+    L: Into<SizeRange>,
+{
+    ops(key, value, len).prop_map(|ops| {
+        let mut queue = fifo::Queue::new();
+        for op in ops {
+            op.apply(&mut queue);
+        }
+        queue
+    })
+}
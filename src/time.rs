@@ -0,0 +1,103 @@
+//! Sources of the current time for the queues which depend on it.
+//!
+//! Queues read the current time from the [`SystemClock`](struct.SystemClock.html) unless they
+//! were given another clock, such as a [`MockClock`](struct.MockClock.html) in tests, which lets
+//! time pass without sleeping.
+//!
+//! This module requires the `std` feature.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+/// The clock of the system, as returned by [`Instant::now`].
+///
+/// [`Instant::now`]: https://doc.rust-lang.org/std/time/struct.Instant.html#method.now
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock which only advances when told to.
+///
+/// Clones share their time, so a clone given to a queue can be advanced from a test.
+///
+/// ```
+/// use addressable_queue::time::{Clock, MockClock};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+/// clock.advance(Duration::from_secs(5));
+///
+/// assert_eq!(start + Duration::from_secs(5), clock.now());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Create a new clock, which starts at the current time of the system.
+    pub fn new() -> Self {
+        MockClock::starting_at(Instant::now())
+    }
+
+    /// Create a new clock, which starts at `now`.
+    pub fn starting_at(now: Instant) -> Self {
+        MockClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Let `duration` pass.
+    pub fn advance(&self, duration: Duration) {
+        *self.lock() += duration;
+    }
+
+    /// Set the current time, which may also go back.
+    pub fn set(&self, now: Instant) {
+        *self.lock() = now;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Instant> {
+        self.now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.lock()
+    }
+}
@@ -0,0 +1,153 @@
+//! Implementation of an addressable 2Q cache, which resists being flushed by scans.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::hash::Hash;
+use deque::Deque;
+use set;
+
+/// An addressable 2Q cache holding at most `capacity` entries.
+///
+/// New entries are placed in a FIFO queue (A1in) taking about a quarter of the capacity. Entries
+/// evicted from it are remembered by key only (A1out), and if such a key is inserted again, its
+/// entry is placed in the main LRU queue (Am). So entries only used once, like those of a scan,
+/// never displace the frequently used entries in the main queue.
+///
+/// ```
+/// use addressable_queue::two_q::Queue;
+///
+/// let mut queue = Queue::new(4);
+/// queue.insert(0, "hot");
+/// for key in 1..5 {
+///     queue.insert(key, "once");
+/// }
+/// // The key was evicted from A1in recently, so its entry is placed in the main queue.
+/// queue.insert(0, "hot");
+///
+/// for key in 5..100 {
+///     queue.insert(key, "scan");
+/// }
+/// assert_eq!(Some(&"hot"), queue.get(&0));
+/// ```
+pub struct Queue<K, V> {
+    a1in: Deque<K, V>,
+    a1out: set::Queue<K>,
+    am: Deque<K, V>,
+    capacity: usize,
+}
+
+impl<K, V> Queue<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new instance of the cache holding at most `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Queue {
+            a1in: Deque::new(),
+            a1out: set::Queue::new(),
+            am: Deque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.a1in.len() + self.am.len()
+    }
+
+    /// Returns true if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.a1in.is_empty() && self.am.is_empty()
+    }
+
+    /// Returns true if the cache contains an entry for the specified key.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.a1in.contains_key(key) || self.am.contains_key(key)
+    }
+
+    /// Access an entry. Entries of the main queue are marked as recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Access an entry mutably. Entries of the main queue are marked as recently used.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if self.am.move_to_back(key) {
+            return self.am.get_mut(key);
+        }
+        self.a1in.get_mut(key)
+    }
+
+    /// Returns a reference to the value of the specified key, without counting as an access.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.am.get(key).or_else(|| self.a1in.get(key))
+    }
+
+    /// Insert an entry.
+    ///
+    /// If the key is already contained, its value is replaced and the old entry is returned.
+    /// Otherwise, if the cache is full, an entry is evicted and returned: the oldest entry of A1in
+    /// if it holds at least its share, or else the least recently used entry of the main queue.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if self.capacity == 0 {
+            return Some((key, value));
+        }
+        if self.am.move_to_back(&key) {
+            let old = self.am.replace(&key, value)?;
+            return Some((key, old));
+        }
+        if let Some(slot) = self.a1in.get_mut(&key) {
+            let old = ::core::mem::replace(slot, value);
+            return Some((key, old));
+        }
+        let evicted = if self.len() >= self.capacity {
+            self.reclaim()
+        } else {
+            None
+        };
+        if self.a1out.remove(&key) {
+            self.am.push_back(key, value);
+        } else {
+            self.a1in.push_back(key, value);
+        }
+        evicted
+    }
+
+    /// Remove a value by specifying its key.
+    pub fn remove_key(&mut self, key: &K) -> Option<V> {
+        self.am
+            .remove_key(key)
+            .or_else(|| self.a1in.remove_key(key))
+    }
+
+    /// Evict an entry to make room for a new one.
+    fn reclaim(&mut self) -> Option<(K, V)> {
+        let a1in_share = (self.capacity / 4).max(1);
+        if self.a1in.len() < a1in_share && !self.am.is_empty() {
+            return self.am.pop_front();
+        }
+        let (key, value) = self.a1in.pop_front()?;
+        self.a1out.insert(key.clone());
+        if self.a1out.len() > (self.capacity / 2).max(1) {
+            self.a1out.remove_head();
+        }
+        Some((key, value))
+    }
+}
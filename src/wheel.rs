@@ -0,0 +1,267 @@
+//! Hierarchical timing wheel backing the delay queue.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use deque::Deque;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+/// Number of bits of a tick which select the slot of a level.
+const BITS: u32 = 6;
+const SLOTS: usize = 1 << BITS;
+const LEVELS: usize = 6;
+/// The slot of the entries which are due.
+const READY: usize = LEVELS * SLOTS;
+
+/// A hierarchical timing wheel, where inserting and removing an entry takes `O(1)` time.
+///
+/// Time is counted in ticks of `resolution` since the wheel was created. Level `n` has 64 slots
+/// spanning `64^n` ticks each, an entry is placed on the lowest level whose slots tell it apart
+/// from the current tick. When the wheel reaches a slot above the first level, its entries are
+/// placed again, which moves them down.
+pub(crate) struct Wheel<K, V> {
+    slots: Vec<Deque<K, (Instant, V)>>,
+    /// The slot of every key.
+    index: HashMap<K, usize>,
+    /// Which slots of every level are not empty.
+    occupied: [u64; LEVELS],
+    start: Instant,
+    resolution: Duration,
+    /// The tick the wheel has advanced to.
+    elapsed: u64,
+}
+
+impl<K, V> Wheel<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Create a new wheel, which rounds the instants of its entries up to multiples of
+    /// `resolution`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resolution` is zero.
+    pub fn new(resolution: Duration) -> Self {
+        assert!(
+            resolution > Duration::ZERO,
+            "the resolution must be positive"
+        );
+        Wheel {
+            slots: (0..=READY).map(|_| Deque::new()).collect(),
+            index: HashMap::new(),
+            occupied: [0; LEVELS],
+            start: Instant::now(),
+            resolution,
+            elapsed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&(Instant, V)> {
+        let slot = *self.index.get(key)?;
+        self.slots[slot].get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut (Instant, V)> {
+        let slot = *self.index.get(key)?;
+        self.slots[slot].get_mut(key)
+    }
+
+    /// Returns the earliest instant an entry is released, which is the instant of the entry
+    /// rounded up to the next tick.
+    pub fn next_instant(&self) -> Option<Instant> {
+        let slot = if self.slots[READY].is_empty() {
+            let (level, slot, _) = self.next_expiration()?;
+            level * SLOTS + slot
+        } else {
+            READY
+        };
+        let at = self.slots[slot].iter().map(|(_, &(at, _))| at).min()?;
+        Some(self.instant_of(self.tick_of(at)).unwrap_or(at))
+    }
+
+    /// Insert an entry, and return the old instant and value of the key.
+    pub fn insert(&mut self, key: K, at: Instant, value: V) -> Option<(Instant, V)> {
+        let old = self.remove_key(&key);
+        self.place(key, at, value);
+        old
+    }
+
+    /// Remove an entry whose instant is not after `now`, in the order of their ticks.
+    pub fn pop_due(&mut self, now: Instant) -> Option<(K, Instant, V)> {
+        self.advance(now);
+        let (key, (at, value)) = self.slots[READY].pop_front()?;
+        self.index.remove(&key);
+        Some((key, at, value))
+    }
+
+    /// Change the instant of an entry, and return the old one.
+    pub fn reschedule(&mut self, key: &K, at: Instant) -> Option<Instant> {
+        let (old, value) = self.remove_key(key)?;
+        self.place(key.clone(), at, value);
+        Some(old)
+    }
+
+    pub fn remove_key(&mut self, key: &K) -> Option<(Instant, V)> {
+        let slot = self.index.remove(key)?;
+        let entry = self.slots[slot].remove_key(key);
+        if slot != READY && self.slots[slot].is_empty() {
+            self.occupied[slot / SLOTS] &= !(1 << (slot % SLOTS));
+        }
+        entry
+    }
+
+    /// Place an entry in the slot of its tick.
+    fn place(&mut self, key: K, at: Instant, value: V) {
+        let slot = self.slot_of(self.tick_of(at));
+        if slot != READY {
+            self.occupied[slot / SLOTS] |= 1 << (slot % SLOTS);
+        }
+        self.index.insert(key.clone(), slot);
+        self.slots[slot].push_back(key, (at, value));
+    }
+
+    /// Returns the first tick which is not before `at`.
+    fn tick_of(&self, at: Instant) -> u64 {
+        let nanos = at.saturating_duration_since(self.start).as_nanos();
+        let resolution = self.resolution.as_nanos();
+        let ticks = nanos.div_ceil(resolution);
+        ticks.min(u64::MAX as u128) as u64
+    }
+
+    /// Returns the instant `tick` starts at, or `None` if it is not representable.
+    fn instant_of(&self, tick: u64) -> Option<Instant> {
+        let nanos = self.resolution.as_nanos().saturating_mul(u128::from(tick));
+        let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+        let since = Duration::new(secs, (nanos % 1_000_000_000) as u32);
+        self.start.checked_add(since)
+    }
+
+    /// Returns the slot of the entries with the specified tick.
+    fn slot_of(&self, tick: u64) -> usize {
+        if tick <= self.elapsed {
+            return READY;
+        }
+        // Ticks too far ahead for the wheel go to the last level, and are placed again when the
+        // wheel reaches their slot.
+        let significant = 63 - ((self.elapsed ^ tick) | (SLOTS as u64 - 1)).leading_zeros();
+        let level = ((significant / BITS) as usize).min(LEVELS - 1);
+        level * SLOTS + ((tick >> (BITS * level as u32)) as usize % SLOTS)
+    }
+
+    /// Returns the level, slot and starting tick of the first slot the wheel reaches which is not
+    /// empty.
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        self.occupied
+            .iter()
+            .enumerate()
+            .find(|&(_, &occupied)| occupied != 0)
+            .map(|(level, &occupied)| {
+                let slot_range = 1u64 << (BITS * level as u32);
+                let level_range = slot_range << BITS;
+                let current = self.elapsed / slot_range;
+                let slot = (occupied.rotate_right(current as u32).trailing_zeros() as u64 + current)
+                    as usize
+                    % SLOTS;
+                let mut tick = (self.elapsed & !(level_range - 1)) + slot as u64 * slot_range;
+                if tick <= self.elapsed {
+                    // The slot of an entry too far ahead for the wheel, which is reached again
+                    // after a turn of the last level.
+                    tick += level_range;
+                }
+                (level, slot, tick)
+            })
+    }
+
+    /// Advance the wheel to `now`, moving the entries which are due to the ready slot.
+    fn advance(&mut self, now: Instant) {
+        let nanos = now.saturating_duration_since(self.start).as_nanos();
+        let target = (nanos / self.resolution.as_nanos()).min(u64::MAX as u128) as u64;
+        while let Some((level, slot, tick)) = self.next_expiration() {
+            if tick > target {
+                break;
+            }
+            self.elapsed = tick;
+            self.occupied[level] &= !(1 << slot);
+            let entries = ::std::mem::take(&mut self.slots[level * SLOTS + slot]);
+            for (key, (at, value)) in entries.into_vec() {
+                self.place(key, at, value);
+            }
+        }
+        self.elapsed = self.elapsed.max(target);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn wheel_matches_heap() {
+    use deadline;
+
+    let mut wheel = Wheel::new(Duration::from_millis(1));
+    let mut heap = deadline::Queue::new();
+    let start = wheel.start;
+    let mut state = 7u64;
+    let mut next = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        state >> 33
+    };
+    for key in 0..2000u64 {
+        // Up to 2^40 ticks ahead, which reaches past the last level of the wheel.
+        let at = start + Duration::from_millis(next() % (1 << (next() % 41)));
+        wheel.insert(key, at, ());
+        heap.insert(key, at, ());
+        if key % 7 == 0 {
+            wheel.remove_key(&(key / 2));
+            heap.remove_key(&(key / 2));
+        }
+    }
+    while let Some(at) = heap.peek_earliest().map(|(_, at, _)| at) {
+        assert_eq!(Some(at), wheel.next_instant());
+        let mut due: Vec<_> = ::std::iter::from_fn(|| heap.pop_due(at))
+            .map(|(key, _)| key)
+            .collect();
+        let mut ready: Vec<_> = ::std::iter::from_fn(|| wheel.pop_due(at))
+            .map(|(key, _, _)| key)
+            .collect();
+        due.sort();
+        ready.sort();
+        assert_eq!(due, ready);
+    }
+    assert_eq!(0, wheel.len());
+}
+
+#[cfg(test)]
+#[test]
+fn next_instant_releases_the_entry() {
+    let mut wheel = Wheel::new(Duration::from_millis(10));
+    let at = wheel.start + Duration::from_millis(25);
+    wheel.insert(1, at, ());
+    let next = wheel.next_instant().unwrap();
+    assert_eq!(wheel.start + Duration::from_millis(30), next);
+    assert!(wheel.pop_due(next - Duration::from_nanos(1)).is_none());
+    assert_eq!(Some(1), wheel.pop_due(next).map(|(key, _, _)| key));
+}
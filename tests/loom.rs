@@ -0,0 +1,133 @@
+//! Model checks of the queues which are shared between threads, exploring every interleaving of
+//! the threads using them.
+//!
+//! Run them with `RUSTFLAGS="--cfg loom" cargo test --release --test loom`.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(loom)]
+
+extern crate addressable_queue;
+extern crate loom;
+
+use addressable_queue::{concurrent, shared, stealing, sync};
+use loom::sync::Arc;
+use loom::thread;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+
+#[test]
+fn sync_queue_hands_over_entries_before_closing() {
+    loom::model(|| {
+        let queue = sync::Queue::bounded(1);
+        let producer = {
+            let queue = queue.clone();
+            thread::spawn(move || {
+                queue.push_blocking(1, ()).unwrap();
+                queue.close();
+            })
+        };
+
+        assert_eq!(Ok((1, ())), queue.pop_head());
+        assert_eq!(Err(sync::Closed), queue.pop_head());
+        producer.join().unwrap();
+    });
+}
+
+#[test]
+fn channel_closes_once_every_sender_is_dropped() {
+    loom::model(|| {
+        let (sender, receiver) = sync::channel(1);
+        let other = sender.clone();
+        let producer = thread::spawn(move || other.insert(1, ()).unwrap());
+        drop(sender);
+
+        assert_eq!(Ok((1, ())), receiver.pop());
+        assert_eq!(Err(sync::Closed), receiver.pop());
+        producer.join().unwrap();
+    });
+}
+
+#[test]
+fn concurrent_queue_removes_an_entry_once() {
+    loom::model(|| {
+        // A fixed hasher keeps the shards of the keys the same in every explored execution.
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+        let queue = Arc::new(concurrent::Queue::with_shards_and_hasher(2, hasher));
+        queue.insert(1, ());
+        queue.insert(2, ());
+        let canceller = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.remove_key(&1).is_some())
+        };
+
+        let head = queue.remove_head().map(|(key, _)| key);
+        let cancelled = canceller.join().unwrap();
+        assert!(head.is_some());
+        assert!(cancelled != (head == Some(1)));
+        assert_eq!(1 - cancelled as usize, queue.len());
+    });
+}
+
+#[test]
+fn work_queues_hand_out_every_entry_once() {
+    loom::model(|| {
+        let queues = Arc::new(stealing::WorkQueues::new(2));
+        queues.push(0, 1, ());
+        queues.push(0, 2, ());
+        let owner = {
+            let queues = queues.clone();
+            thread::spawn(move || queues.pop(0).map(|(key, _)| key))
+        };
+        let thief = {
+            let queues = queues.clone();
+            thread::spawn(move || queues.steal(1).map(|(key, _)| key))
+        };
+
+        let cancelled = queues.cancel(&2).map(|_| 2);
+        let mut handed_out: Vec<u32> =
+            vec![owner.join().unwrap(), thief.join().unwrap(), cancelled]
+                .into_iter()
+                .flatten()
+                .collect();
+        handed_out.sort();
+        assert_eq!(vec![1, 2], handed_out);
+        assert!(queues.is_empty());
+        assert!(!queues.contains_key(&1) && !queues.contains_key(&2));
+    });
+}
+
+#[test]
+fn rw_queue_readers_see_whole_insertions() {
+    loom::model(|| {
+        let queue = shared::RwQueue::new();
+        let writer = {
+            let queue = queue.clone();
+            thread::spawn(move || {
+                queue.insert(1, ());
+                queue.insert(2, ());
+            })
+        };
+
+        let snapshot = queue.iter_snapshot();
+        assert!(snapshot.len() <= 2);
+        assert!(snapshot
+            .iter()
+            .zip(&[(1, ()), (2, ())])
+            .all(|(a, b)| a == b));
+        writer.join().unwrap();
+        assert_eq!(2, queue.len());
+    });
+}
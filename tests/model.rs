@@ -0,0 +1,49 @@
+//! Differential tests of the FIFO queue against the reference model of the `testing` module.
+//!
+//! Run them with `cargo test --features testing --test model`.
+
+// Copyright 2018 Leonardo Schwarz <mail@leoschwarz.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "testing")]
+
+extern crate addressable_queue;
+#[macro_use]
+extern crate proptest;
+
+use addressable_queue::testing::{self, Op};
+use proptest::prelude::any;
+
+proptest! {
+    #[test]
+    fn queue_matches_model(ops in testing::ops(0..8u8, any::<u32>(), 0..300)) {
+        testing::check(ops);
+    }
+
+    // Long runs of removals and reinsertions leave many tombstones behind before compaction.
+    #[test]
+    fn queue_matches_model_with_churn(ops in testing::ops(0..64u16, any::<u8>(), 500..2000)) {
+        testing::check(ops);
+    }
+
+    #[test]
+    fn generated_queues_keep_their_order(mut queue in testing::queue(0..16u8, any::<u8>(), 0..100)) {
+        let entries: Vec<(u8, u8)> = queue.iter().map(|(&key, &value)| (key, value)).collect();
+        let mut removed = Vec::new();
+        while let Some(entry) = Op::RemoveHead.apply(&mut queue) {
+            removed.push(entry);
+        }
+        prop_assert_eq!(entries, removed);
+    }
+}